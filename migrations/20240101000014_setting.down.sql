@@ -0,0 +1,2 @@
+-- Drop setting table
+DROP TABLE IF EXISTS setting;
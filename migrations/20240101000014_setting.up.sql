@@ -0,0 +1,5 @@
+-- Create setting table for user preferences
+CREATE TABLE IF NOT EXISTS setting (
+    key TEXT NOT NULL PRIMARY KEY,
+    value TEXT NOT NULL
+);
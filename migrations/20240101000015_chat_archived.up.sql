@@ -0,0 +1,2 @@
+-- Archived chats are hidden from the chat history by default but not deleted
+ALTER TABLE chat ADD COLUMN archived BOOLEAN NOT NULL DEFAULT 0;
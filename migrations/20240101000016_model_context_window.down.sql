@@ -0,0 +1 @@
+ALTER TABLE model DROP COLUMN context_window;
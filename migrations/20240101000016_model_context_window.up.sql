@@ -0,0 +1,2 @@
+-- Approximate context window in tokens, conversations are truncated to fit when set
+ALTER TABLE model ADD COLUMN context_window INTEGER;
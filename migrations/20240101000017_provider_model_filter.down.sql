@@ -0,0 +1,2 @@
+ALTER TABLE provider DROP COLUMN model_exclude;
+ALTER TABLE provider DROP COLUMN model_include;
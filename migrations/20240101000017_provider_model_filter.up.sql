@@ -0,0 +1,3 @@
+-- Comma separated glob patterns (e.g. 'openai/*,anthropic/*') limiting which of a provider's models are offered
+ALTER TABLE provider ADD COLUMN model_include TEXT;
+ALTER TABLE provider ADD COLUMN model_exclude TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE chat DROP COLUMN json_mode;
+ALTER TABLE model DROP COLUMN supports_json_mode;
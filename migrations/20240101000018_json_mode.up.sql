@@ -0,0 +1,3 @@
+-- Models that accept response_format json_object, and chats that request it
+ALTER TABLE model ADD COLUMN supports_json_mode BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE chat ADD COLUMN json_mode BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+DELETE FROM chat_profile_model WHERE profile_id IN (SELECT id FROM named_profile);
+DROP TABLE IF EXISTS named_profile;
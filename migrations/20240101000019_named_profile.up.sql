@@ -0,0 +1,7 @@
+-- Named model selections that can be applied to new chats. Their models live in chat_profile_model
+-- under the named_profile id, the default profile keeps id 0
+CREATE TABLE IF NOT EXISTS named_profile (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    created_dt INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Estimated reasoning token count for assistant messages whose provider returned reasoning
+ALTER TABLE chat_message ADD COLUMN reasoning_tokens INTEGER;
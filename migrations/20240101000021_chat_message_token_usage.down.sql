@@ -0,0 +1,2 @@
+ALTER TABLE chat_message DROP COLUMN completion_tokens;
+ALTER TABLE chat_message DROP COLUMN prompt_tokens;
@@ -0,0 +1,3 @@
+-- Token usage as reported by the provider, NULL when the provider didn't report any
+ALTER TABLE chat_message ADD COLUMN prompt_tokens INTEGER;
+ALTER TABLE chat_message ADD COLUMN completion_tokens INTEGER;
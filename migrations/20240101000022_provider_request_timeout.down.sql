@@ -0,0 +1 @@
+ALTER TABLE provider DROP COLUMN request_timeout_secs;
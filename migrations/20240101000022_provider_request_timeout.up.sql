@@ -0,0 +1,2 @@
+-- Seconds to wait for a chat completion before giving up, NULL uses the default
+ALTER TABLE provider ADD COLUMN request_timeout_secs INTEGER;
@@ -0,0 +1,2 @@
+-- Set while a response is still being generated, so one that never finished (e.g. the app crashed) can be told apart
+ALTER TABLE chat_message ADD COLUMN incomplete BOOLEAN NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- JSON array of strings generation should stop at, NULL when there are none
+ALTER TABLE chat ADD COLUMN stop_sequences TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE chat DROP COLUMN system_prompt;
+DROP TABLE IF EXISTS system_prompt;
@@ -0,0 +1,9 @@
+-- Saved system prompts that can be applied to chats
+CREATE TABLE IF NOT EXISTS system_prompt (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_dt INTEGER NOT NULL
+);
+-- The chat's own system prompt, NULL uses the default
+ALTER TABLE chat ADD COLUMN system_prompt TEXT;
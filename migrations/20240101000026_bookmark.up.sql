@@ -0,0 +1,6 @@
+-- Messages bookmarked to come back to later, from any chat
+CREATE TABLE IF NOT EXISTS bookmark (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    message_id INTEGER NOT NULL UNIQUE REFERENCES chat_message(id) ON DELETE CASCADE,
+    created_dt INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Why the provider stopped generating a response, e.g. "length" when it ran into the token limit
+ALTER TABLE chat_message ADD COLUMN finish_reason TEXT;
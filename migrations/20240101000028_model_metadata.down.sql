@@ -0,0 +1,4 @@
+ALTER TABLE model DROP COLUMN size_bytes;
+ALTER TABLE model DROP COLUMN quantization;
+ALTER TABLE model DROP COLUMN parameter_size;
+ALTER TABLE model DROP COLUMN family;
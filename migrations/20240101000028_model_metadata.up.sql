@@ -0,0 +1,5 @@
+-- Details local model servers like Ollama report, NULL when the provider doesn't say
+ALTER TABLE model ADD COLUMN family TEXT;
+ALTER TABLE model ADD COLUMN parameter_size TEXT;
+ALTER TABLE model ADD COLUMN quantization TEXT;
+ALTER TABLE model ADD COLUMN size_bytes INTEGER;
@@ -0,0 +1,6 @@
+-- The prompt being typed, saved every so often so it survives a crash or quit. There's at most one row
+CREATE TABLE IF NOT EXISTS prompt_draft (
+    id INTEGER NOT NULL PRIMARY KEY CHECK (id = 1),
+    content TEXT NOT NULL,
+    saved_dt INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+-- JSON object of header name to value sent with every request, e.g. OpenRouter's HTTP-Referer and X-Title
+ALTER TABLE provider ADD COLUMN extra_headers TEXT;
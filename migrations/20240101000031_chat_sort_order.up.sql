@@ -0,0 +1,2 @@
+-- Where a chat was moved to in the history list, compared against dt * 1000 so chats that were never moved keep their place by creation date
+ALTER TABLE chat ADD COLUMN sort_order INTEGER;
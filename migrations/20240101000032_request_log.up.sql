@@ -0,0 +1,14 @@
+-- One row per inference request when the request_log setting is on. The model's name is copied so entries
+-- still make sense after the model or chat is gone
+CREATE TABLE IF NOT EXISTS request_log (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    dt INTEGER NOT NULL,
+    chat_id INTEGER REFERENCES chat(id) ON DELETE SET NULL,
+    model_id INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    message_count INTEGER NOT NULL,
+    latency_ms INTEGER NOT NULL,
+    prompt_tokens INTEGER,
+    completion_tokens INTEGER,
+    error TEXT
+);
@@ -0,0 +1 @@
+ALTER TABLE provider DROP COLUMN no_models_endpoint;
@@ -0,0 +1,3 @@
+-- Set for providers that serve completions but have no models endpoint, like some gateways. Their models
+-- are added by hand with --add-model and they're never synced or marked down for failing to list models
+ALTER TABLE provider ADD COLUMN no_models_endpoint BOOLEAN NOT NULL DEFAULT 0;
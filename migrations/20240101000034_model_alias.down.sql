@@ -0,0 +1 @@
+ALTER TABLE model DROP COLUMN alias;
@@ -0,0 +1,2 @@
+-- A short name shown instead of the model's own, which is still what's sent to the provider. NULL shows the model name
+ALTER TABLE model ADD COLUMN alias TEXT;
@@ -0,0 +1 @@
+ALTER TABLE chat_message DROP COLUMN answered_by_model_id;
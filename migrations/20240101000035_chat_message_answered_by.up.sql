@@ -0,0 +1,2 @@
+-- The fallback model that answered when the chat's own model failed, NULL when it answered itself
+ALTER TABLE chat_message ADD COLUMN answered_by_model_id INTEGER;
@@ -0,0 +1 @@
+UPDATE provider SET no_models_endpoint = 0 WHERE name IN ('Perplexity', 'MiniMax', 'zAI');
@@ -0,0 +1,3 @@
+-- These built in providers serve completions but have no models endpoint. Without this every refresh
+-- fails to list their models and marks them down, so none of their seeded models could be used
+UPDATE provider SET no_models_endpoint = 1 WHERE name IN ('Perplexity', 'MiniMax', 'zAI');
@@ -0,0 +1 @@
+ALTER TABLE provider DROP COLUMN system_role;
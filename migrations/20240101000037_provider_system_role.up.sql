@@ -0,0 +1,2 @@
+-- The role the system prompt is sent in, 'developer' for models that prefer it. NULL sends 'system'
+ALTER TABLE provider ADD COLUMN system_role TEXT;
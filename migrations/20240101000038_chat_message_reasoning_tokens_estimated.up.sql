@@ -0,0 +1,4 @@
+-- Whether reasoning_tokens was estimated from the reasoning text rather than reported in the provider's usage
+ALTER TABLE chat_message ADD COLUMN reasoning_tokens_estimated BOOLEAN NOT NULL DEFAULT 0;
+-- every count stored before this was an estimate
+UPDATE chat_message SET reasoning_tokens_estimated = 1 WHERE reasoning_tokens IS NOT NULL;
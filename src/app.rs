@@ -1,11 +1,23 @@
-use crate::database::Database;
+use crate::database::{DEFAULT_PROFILE_ID, Database};
+use crate::hyperlink::{draw_hyperlinks, find_hyperlinks};
+use crate::log_buffer::LogBuffer;
+use crate::model::chat::Bookmark;
 use crate::model::chat::Chat;
+use crate::model::chat::ChatExport;
 use crate::model::chat::ChatMessage;
+use crate::model::chat::ChatRole;
+use crate::model::chat::ChatSort;
 use crate::model::chat::ChatProfile;
+use crate::model::chat::LibraryPrompt;
+use crate::model::chat::NamedProfile;
+use crate::model::chat::RequestLogEntry;
 use crate::model::model::Model;
 use crate::model_select_modal::{ModalResult, ModelSelectModal, ModelSelectionMode};
 use crate::provider::OpenAIProvider;
-use crate::provider::provider::ProviderClient;
+use crate::provider::provider::{
+    GenerationOptions, GenerationResult, ListedModel, Provider, ProviderClient, ResponseFormat,
+};
+use crate::settings::Settings;
 use crate::ui::*;
 use anyhow::Result;
 use copypasta::{ClipboardContext, ClipboardProvider};
@@ -15,7 +27,7 @@ use crossterm::{
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use edtui::EditorMode;
-use edtui::{EditorEventHandler, EditorState};
+use edtui::{EditorEventHandler, EditorState, Index2};
 use futures::StreamExt;
 use ratatui::{
     Terminal,
@@ -23,17 +35,20 @@ use ratatui::{
 };
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
+use tokio::sync::{Semaphore, mpsc};
 use tokio::task::JoinHandle;
 use tracing::error;
 use tracing::info;
+use unicode_width::UnicodeWidthStr;
 use tracing::instrument;
 
 // Helper function to get text from EditorState
-fn editor_state_to_string(state: &EditorState) -> String {
+pub fn editor_state_to_string(state: &EditorState) -> String {
     // Collect all characters and convert to string
     let all_chars: String = state
         .lines
@@ -61,21 +76,112 @@ pub enum AppState {
     DatabaseSelection,
     ProviderDialog,
     DeleteConfirmation,
+    ClearConfirmation,
+    ClearAllChatsConfirmation,
+    QuitConfirmation,
     TitleEdit,
+    ProfileNameEdit,
+    ModelAliasEdit,
+    StopSequencesEdit,
+    ProfilePicker,
+    PromptLibrary,
+    PromptLibraryNameEdit,
+    PromptLibraryContentEdit,
+    PromptLibraryDeleteConfirmation,
+    BookmarkList,
+    GlobalSearch,
     UnavailableModelsError,
+    ErrorDetail,
+    RequestPreview,
+    LogViewer,
+    RequestLog,
+}
+
+/// How the chat's custom system prompt is shown above the messages, H cycles through these
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SystemPromptHeader {
+    Hidden,
+    Collapsed,
+    Expanded,
+}
+
+impl SystemPromptHeader {
+    fn next(self) -> Self {
+        match self {
+            SystemPromptHeader::Hidden => SystemPromptHeader::Collapsed,
+            SystemPromptHeader::Collapsed => SystemPromptHeader::Expanded,
+            SystemPromptHeader::Expanded => SystemPromptHeader::Hidden,
+        }
+    }
+}
+
+/// A search over every database in the data directory, they're kept open while the dialog is
+pub struct GlobalSearch {
+    pub databases: Vec<(PathBuf, Arc<Database>)>,
+    pub textarea: EditorState,
+    pub results: Vec<(PathBuf, Chat)>, // newest first, with the database each chat is in
+    pub index: usize,
+    pub searched: bool, // a search has run, so no results means nothing matched
 }
 
+/// How many chats each database contributes to a global search at most
+const GLOBAL_SEARCH_LIMIT: i32 = 100;
+
+/// How many of the most recent requests the request log shows
+const REQUEST_LOG_LIMIT: i64 = 500;
+
+/// How long a status message stays under the prompt
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+/// How often providers marked down are checked again in the background
+const PROVIDER_RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How many columns h/l pan a message by when wrapping is off
+const HORIZONTAL_SCROLL_STEP: usize = 4;
+
+/// How many of the chat's earlier prompts Up and Down can recall
+const PROMPT_HISTORY_CAPACITY: usize = 100;
+
+/// Sent after a response that was cut off at the token limit to have the model pick it back up
+const CONTINUE_PROMPT: &str =
+    "Continue your previous response exactly where it left off, without repeating anything.";
+
+/// Warn once a provider has less than this share of its requests or tokens left
+const RATE_LIMIT_WARNING_FRACTION: f64 = 0.1;
+
 #[derive(Debug)]
 pub enum InferenceEvent {
+    InferenceStarted {
+        model_id: i64,
+        origin_message_id: i64,
+        truncated_message_count: usize,
+    },
     InferenceComplete {
         chat_id: i64,
         model_id: i64,
         origin_message_id: i64,
-        result: ChatMessage,
+        result: Box<ChatMessage>,
+        candidates: Vec<String>, // every candidate response, the result's first. Empty with one
+        request_failed: bool, // the model's own provider errored, as opposed to e.g. an empty response
+        fallback_results: Vec<(i64, bool)>, // (model id, request failed) for each fallback model tried, in order
+    },
+    ContinuationComplete {
+        chat_id: i64,
+        model_id: i64,
+        message: Box<ChatMessage>, // the continued message, or the original one on failure
+        error: Option<String>,
     },
     TitleInferenceComplete {
         chat_id: i64,
-        title: String,
+        title: Option<String>, // None if the request failed or came back empty
+    },
+    ModelsRefreshed {
+        models: Vec<Model>, // every non-deprecated model after the sync
+        added: usize,
+        removed: usize,
+        answered_provider_ids: Vec<i64>, // providers whose models endpoint was asked and answered
+        failed_provider_ids: Vec<i64>,
+        recheck_only: bool, // only providers marked down were synced
     },
 }
 
@@ -83,6 +189,10 @@ pub enum InferenceEvent {
 // and isolate it in one place so it is clearer where it comes from
 pub struct App {
     pub database: Arc<Database>,
+    pub db_path: PathBuf,
+    pub data_dir: PathBuf, // directory holding the database, exports go here too
+    pub read_only: bool, // --read-only, chats can be browsed but nothing is sent, deleted or renamed
+    pub clipboard_unavailable: bool, // set once copying failed, yanks go to a file from then on
     pub state: AppState,
     pub default_profile: ChatProfile,
     pub current_chat: Chat,
@@ -90,13 +200,19 @@ pub struct App {
     pub current_chat_profile: ChatProfile,
     pub chat_history: Vec<Chat>,
     pub current_messages: HashMap<i64, Vec<ChatMessage>>, // model_id -> messages
+    pub older_messages_before_dt: Option<i64>, // set while a long chat has older messages to load
     pub chat_history_index: usize,
+    pub chat_history_visual_start: Option<usize>, // where marking chats for bulk deletion started
+    pub chat_history_pages: usize, // pages of the unfiltered list loaded so far
+    pub chat_history_exhausted: bool, // the last page came back short, there's nothing more to load
     pub current_selected_message_index: Option<usize>, // this is populated when rendering
+    pub current_selected_chunk_text: Option<String>, // text of the selected chunk as displayed, also populated when rendering
     pub current_message_index: HashMap<i64, usize>,    // model_id -> message index (0-indexed)
     pub current_chunk_idx: HashMap<i64, usize>, // model_id -> chunk index within current message
-    pub current_message_chunks_length: HashMap<i64, usize>, // model_id -> number of chunks in current message (written by render)
+    pub chat_viewport: (usize, usize), // text width and height of the chat area, written by render so navigation can count chunks
     pub chat_item_selections: HashMap<i64, Option<i64>>, // model_id -> relative item index (0=none, positive=from start, negative=from end)
     pub chat_history_collapsed: bool,
+    pub show_archived: bool, // archived chats are hidden from history and search unless this is set
     pub textarea: EditorState,
     pub title_textarea: EditorState,
     pub search_textarea: EditorState,
@@ -105,6 +221,10 @@ pub struct App {
     pub user_event_tx: mpsc::UnboundedSender<InferenceEvent>,
     pub title_inference_in_progress_by_chat: HashSet<i64>,
     pub inference_in_progress_by_message_and_model: HashSet<(i64, i64)>, // message and model id -> handle
+    pub inference_running_by_message_and_model: HashMap<(i64, i64), Instant>, // subset of the above that hold a semaphore permit (and since when), the rest are queued
+    pub inference_semaphore: Arc<Semaphore>, // shared across all models to cap simultaneous requests
+    pub settings: Settings,
+    pub context_truncated_by_message_and_model: HashMap<(i64, i64), usize>, // message and model id -> number of older messages left out of the request
     pub inference_handles_by_chat_and_model: HashMap<(i64, i64), JoinHandle<Vec<ChatMessage>>>, // chat and model id -> handle
     pub provider_clients: HashMap<i64, Arc<dyn ProviderClient>>, // provider_id -> provider client
     pub provider_api_keys_set: HashMap<i64, bool>,               // provider_id -> api key set
@@ -112,19 +232,82 @@ pub struct App {
     pub available_models: HashMap<i64, Model>,                   // model_id -> model
     pub all_models: HashMap<i64, Model>,
     pub provider_names: HashMap<i64, String>, // provider_id -> provider name
+    pub providers: HashMap<i64, Provider>,    // provider_id -> provider
+    pub providers_marked_down: HashSet<i64>,  // providers whose models endpoint failed on the last refresh
+    pub provider_failure_streaks: HashMap<i64, usize>, // failed requests in a row by provider id
+    pub model_refresh_provider_count: Option<usize>, // number of providers being refreshed, while a refresh is running
+    pub last_provider_recheck: Instant,
+    pub status_message: Option<(String, Instant)>, // transient notice shown under the prompt, with when it was set
     // Model selection dialog state
     pub model_select_modal: Option<ModelSelectModal>,
+    // Named profile state
+    pub named_profiles: Vec<NamedProfile>, // loaded when the picker opens
+    pub profile_picker_index: usize,
+    pub pending_profile_model_ids: Vec<i64>, // models waiting on a name before being saved as a profile
+    pub alias_model_id: Option<i64>, // the model whose alias is being edited
+    // Prompt library state
+    pub library_prompts: Vec<LibraryPrompt>, // loaded when the library opens
+    pub prompt_library_index: usize,
+    pub editing_library_prompt_id: Option<i64>, // None while creating a new prompt
+    pub pending_library_prompt_name: String,
+    pub library_prompt_textarea: EditorState,
+    // Bookmark state
+    pub bookmarks: Vec<Bookmark>, // loaded when the list opens
+    pub bookmark_list_index: usize,
+    pub global_search: Option<GlobalSearch>,
+    pub bookmarked_message_ids: HashSet<i64>, // every bookmarked message, so the chat can mark them
+    // Request log state
+    pub request_log: Vec<RequestLogEntry>, // loaded when the log opens
+    pub request_log_index: usize,
     // Spinner animation state
     pub spinner_frame: usize,
     pub last_spinner_update: Instant,
     // Vim-style numeric prefix for navigation
     pub numeric_prefix: Option<usize>,
     pub clear_last_key_press: bool,
+    pub external_edit_requested: bool, // Ctrl+e, handled by run_app since it needs the terminal
+    pub prompt_preview: bool, // Ctrl+o shows the prompt rendered as markdown in place of the editor
+    // Word wrap state, when off long lines are clipped and can be panned
+    pub wrap_messages: bool,
+    pub horizontal_offset_by_model: HashMap<i64, usize>,
+    // models whose view jumps to newly arrived responses
+    // set by G or submitting, cleared by scrolling up
+    pub following_latest: HashSet<i64>,
+    pub collapsed_message_ids: HashSet<i64>, // messages shown as a short preview, toggled with f
+    pub raw_message_ids: HashSet<i64>, // messages shown as their markdown source, toggled with v
+    pub continuing_message_ids: HashSet<i64>, // cut off responses waiting on their continuation
+    // candidate responses by message id and the one shown, cycled with < and > until the next
+    // prompt. the shown one is what's saved, so that's the one the conversation continues with
+    pub response_candidates: HashMap<i64, (Vec<String>, usize)>,
+    // U interleaves every model's responses under each prompt, scrolled by line rather than message
+    pub unified_view: bool,
+    pub unified_scroll: usize, // clamped by render
+    pub unified_line_count: usize, // lines in the unified view when it was last rendered
+    // after older messages load above the unified view, how far from its end the scroll should
+    // be. Render knows how many lines they took, so it turns this back into unified_scroll
+    pub unified_scroll_from_end: Option<usize>,
+    pub system_prompt_header: SystemPromptHeader,
     // Unavailable models error state
     pub unavailable_models_info: Vec<(String, String)>, // (model_name, provider_name)
+    // Error detail modal state
+    pub error_detail: Option<(String, String, String)>, // (model_name, provider_name, error)
+    pub error_detail_scroll: u16,
+    // Request preview modal state
+    pub request_preview: Option<(String, String)>, // (model_name, request)
+    pub request_preview_scroll: u16,
+    // Log viewer modal state
+    pub log_buffer: Option<LogBuffer>, // None when started with --no-log-viewer
+    pub log_viewer_scroll: usize, // lines scrolled up from the newest one
+    pub log_viewer_rows: usize,   // log lines the dialog fits, written by render so scrolling stops at the oldest page
     // Track last key press for double-tap detection (e.g., 'cc' to clear)
     pub last_key_press: Option<KeyCode>,
     pub editor_event_handler: EditorEventHandler,
+    // the current chat's recent prompts, oldest first, for recalling with Up and Down
+    pub prompt_history: VecDeque<String>,
+    pub prompt_history_index: Option<usize>, // the recalled prompt, None when not recalling
+    // the prompt as of the last auto-save, so an unchanged draft isn't written again
+    pub saved_prompt_draft: String,
+    pub last_draft_autosave: Instant,
 }
 
 /// Find the first viable model for the default chat profile
@@ -141,10 +324,13 @@ async fn find_first_viable_model(database: &Database) -> Result<Option<i64>> {
         // Get models for this provider, ordered by ID
         let models = database.get_models_for_provider(provider.id).await?;
         // Find the first enabled model with the lowest ID
-        Ok(models.into_iter().find(|m| !m.disabled).map(|m| m.id))
+        Ok(models
+            .into_iter()
+            .find(|m| !m.disabled && provider.includes_model(&m.model))
+            .map(|m| m.id))
     } else {
         // No providers with API keys, try to find any available model
-        let all_models = database.get_all_models().await?;
+        let all_models = database.get_all_models(false).await?;
         let available_models: std::collections::HashMap<i64, crate::model::model::Model> =
             all_models
                 .into_iter()
@@ -154,9 +340,376 @@ async fn find_first_viable_model(database: &Database) -> Result<Option<i64>> {
     }
 }
 
+/// Split models into (available, all) maps keyed by model id. Available models need an api key for
+/// their provider, a provider that isn't marked down, and to pass the provider's include/exclude patterns.
+/// Models the provider no longer lists are never available
+fn index_models(
+    models: Vec<Model>,
+    providers: &HashMap<i64, Provider>,
+    provider_api_keys_set: &HashMap<i64, bool>,
+    providers_marked_down: &HashSet<i64>,
+) -> (HashMap<i64, Model>, HashMap<i64, Model>) {
+    let mut available_models = HashMap::new();
+    let mut all_models = HashMap::new();
+    for model in models {
+        info!("Model {}: {}", model.id, model.model);
+        all_models.insert(model.id, model.clone());
+        // models filtered out by the provider's patterns stay in all_models so existing chats still show their names
+        let passes_filter = providers
+            .get(&model.provider_id)
+            .is_some_and(|provider| provider.includes_model(&model.model));
+        if passes_filter
+            && !model.deprecated
+            && !providers_marked_down.contains(&model.provider_id)
+            && *provider_api_keys_set
+                .get(&model.provider_id)
+                .unwrap_or(&false)
+        {
+            available_models.insert(model.id, model);
+        }
+    }
+    (available_models, all_models)
+}
+
+/// Sync every given provider's models with its models endpoint. Returns (added, removed, answered provider ids,
+/// failed provider ids). Providers without a models endpoint keep the models added by hand and are in neither
+/// list, since they weren't checked. Without `write_models` the providers are only asked, which is enough to
+/// tell if they're up
+pub async fn refresh_models_with_provider_api(
+    database: &Database,
+    providers: Vec<(Provider, Arc<dyn ProviderClient>)>,
+    write_models: bool,
+) -> (usize, usize, Vec<i64>, Vec<i64>) {
+    let mut added = 0;
+    let mut removed = 0;
+    let mut answered_provider_ids = Vec::new();
+    let mut failed_provider_ids = Vec::new();
+    for (provider, client) in providers {
+        if provider.no_models_endpoint {
+            continue;
+        }
+        let listed_models = match client.list_models().await {
+            Ok(listed_models) => listed_models,
+            Err(e) => {
+                error!("Failed to list models for provider {}: {}", provider.name, e);
+                failed_provider_ids.push(provider.id);
+                continue;
+            }
+        };
+        answered_provider_ids.push(provider.id);
+        if !write_models {
+            continue;
+        }
+
+        // only models passing the provider's patterns make it into the db
+        let listed_models: Vec<ListedModel> = listed_models
+            .into_iter()
+            .filter(|model| provider.includes_model(&model.id))
+            .collect();
+        match database.sync_provider_models(provider.id, &listed_models).await {
+            Ok((provider_added, provider_removed)) => {
+                info!(
+                    "Refreshed models for provider {}: {} added, {} removed",
+                    provider.name, provider_added, provider_removed
+                );
+                added += provider_added;
+                removed += provider_removed;
+            }
+            Err(e) => error!("Failed to sync models for provider {}: {}", provider.name, e),
+        }
+    }
+    (added, removed, answered_provider_ids, failed_provider_ids)
+}
+
+/// Generated titles are cut to this many words, the title prompt asks for no more than this
+const TITLE_MAX_WORDS: usize = 6;
+
+/// Clean up a generated title. Models like to quote it, format it as markdown, label it or add an
+/// explanation after it, so only the first line is kept without any of that. None if nothing is
+/// left
+fn sanitize_title(raw: &str) -> Option<String> {
+    const QUOTES: &[char] = &['"', '\'', '`', '“', '”', '‘', '’', '«', '»'];
+    // reasoning models may put their thinking inline before the answer
+    let raw = raw.rsplit_once("</think>").map_or(raw, |(_, after)| after);
+    let line = raw.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line.trim_start_matches(['#', '>', '-', '*', ' ']);
+    let line = match line.split_once(':') {
+        Some((label, rest)) if label.trim().eq_ignore_ascii_case("title") => rest,
+        _ => line,
+    };
+    let line = line.replace("**", "").replace("__", "");
+    let words: Vec<&str> = line
+        .trim()
+        .trim_matches(QUOTES)
+        .split_whitespace()
+        .take(TITLE_MAX_WORDS)
+        .collect();
+    let title = words
+        .join(" ")
+        .trim_matches(QUOTES)
+        .trim_end_matches(['.', ',', ':', ';', '-'])
+        .trim()
+        .to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+/// The chat's own system prompt, or the default one. OpenAI rejects json_object requests that
+/// don't mention JSON, so JSON mode asks for it
+fn system_prompt_for(chat: &Chat, json_mode: bool) -> String {
+    let system_prompt = chat
+        .system_prompt
+        .as_deref()
+        .unwrap_or("You are a helpful assistant.");
+    if json_mode {
+        format!("{} Respond with a single valid JSON object.", system_prompt)
+    } else {
+        system_prompt.to_string()
+    }
+}
+
+fn generation_options_for(json_mode: bool, chat: &Chat) -> GenerationOptions {
+    GenerationOptions {
+        response_format: if json_mode {
+            ResponseFormat::JsonObject
+        } else {
+            ResponseFormat::Text
+        },
+        stop: chat.stop_sequence_list(),
+        n: None,
+    }
+}
+
+/// Send a conversation to a model. JSON mode responses are kept as the model sent them and only
+/// pretty printed when shown
+async fn generate_response(
+    provider_client: &dyn ProviderClient,
+    model: &Model,
+    system_prompt: &str,
+    conversation: &[ChatMessage],
+    remove_think_tokens: bool,
+    options: &GenerationOptions,
+) -> Result<GenerationResult> {
+    provider_client
+        .run(
+            &model.model,
+            system_prompt,
+            conversation,
+            vec![], // No tools for now
+            remove_think_tokens,
+            options,
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!("Inference failed: {}", e))
+}
+
+/// Whether a request got a reply worth keeping. An empty one is shown as an error, so the
+/// fallback chain treats it like a failure too
+fn has_reply(result: &Result<GenerationResult>) -> bool {
+    result.as_ref().is_ok_and(|result| {
+        result.content.as_ref().is_some_and(|content| !content.trim().is_empty())
+    })
+}
+
+/// Write a finished request to the request log. A failure to write is only logged since the
+/// request itself went through
+async fn log_request(
+    database: &Database,
+    chat_id: i64,
+    model: &Model,
+    message_count: usize,
+    request_started: Instant,
+    result: Result<&GenerationResult, String>,
+) {
+    let latency_ms = request_started.elapsed().as_millis() as i64;
+    let usage = result.as_ref().ok();
+    let entry = RequestLogEntry {
+        id: 0, // Will be set by database
+        dt: chrono::Utc::now().timestamp_millis() - latency_ms,
+        chat_id: Some(chat_id),
+        model_id: model.id,
+        model: model.model.clone(),
+        message_count: message_count as i64,
+        latency_ms,
+        prompt_tokens: usage.and_then(|usage| usage.prompt_tokens),
+        completion_tokens: usage.and_then(|usage| usage.completion_tokens),
+        error: result.err(),
+    };
+    if let Err(e) = database.add_request_log_entry(&entry).await {
+        info!("Couldn't write request log entry: {}", e);
+    }
+}
+
+/// A wait shown while it's happening, like "8s" or "2m 05s"
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// Stop sequences are edited as one comma separated line, with \n and \t standing in for newlines and tabs
+fn format_stop_sequences(stop_sequences: &[String]) -> String {
+    stop_sequences
+        .iter()
+        .map(|stop| stop.replace('\n', "\\n").replace('\t', "\\t"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn parse_stop_sequences(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|stop| stop.trim().replace("\\n", "\n").replace("\\t", "\t"))
+        .filter(|stop| !stop.is_empty())
+        .collect()
+}
+
+/// Move `steps` chunks forward (or backward when negative) from a (message, chunk) position,
+/// crossing into neighboring messages as needed. Moving back into a message lands on its last chunk
+fn step_chunks(
+    position: (usize, usize),
+    steps: isize,
+    total_messages: usize,
+    chunk_count: impl Fn(usize) -> usize,
+) -> (usize, usize) {
+    if total_messages == 0 {
+        return (0, 0);
+    }
+    let mut msg_idx = position.0.min(total_messages - 1);
+    let mut chunks = chunk_count(msg_idx).max(1);
+    // G leaves the chunk at usize::MAX for render to clamp, so clamp it here too
+    let mut chunk_idx = position.1.min(chunks - 1);
+
+    for _ in 0..steps.unsigned_abs() {
+        if steps > 0 {
+            if chunk_idx + 1 < chunks {
+                chunk_idx += 1;
+            } else if msg_idx + 1 < total_messages {
+                msg_idx += 1;
+                chunks = chunk_count(msg_idx).max(1);
+                chunk_idx = 0;
+            } else {
+                break;
+            }
+        } else if chunk_idx > 0 {
+            chunk_idx -= 1;
+        } else if msg_idx > 0 {
+            msg_idx -= 1;
+            chunks = chunk_count(msg_idx).max(1);
+            chunk_idx = chunks - 1;
+        } else {
+            break;
+        }
+    }
+    (msg_idx, chunk_idx)
+}
+
+/// Index of the user message `steps` prompts forward (or backward when negative) from `msg_idx`,
+/// skipping everything in between. Stops at the last prompt found when there are fewer left
+fn step_prompts(messages: &[ChatMessage], msg_idx: usize, steps: isize) -> usize {
+    let prompts = messages
+        .iter()
+        .enumerate()
+        .filter(|(_, message)| message.chat_role == ChatRole::User)
+        .map(|(idx, _)| idx);
+    let skipped: Vec<usize> = if steps > 0 {
+        prompts.filter(|&idx| idx > msg_idx).take(steps.unsigned_abs()).collect()
+    } else {
+        prompts.rev().filter(|&idx| idx < msg_idx).take(steps.unsigned_abs()).collect()
+    };
+    skipped.last().copied().unwrap_or(msg_idx)
+}
+
+/// Where the next older page of a chat's messages ends, None when this page already reached the
+/// first prompt. A page holds `page_size` prompts, so a short one was the last
+fn older_page_before_dt(page: &[ChatMessage], page_size: usize) -> Option<i64> {
+    let mut dts: Vec<i64> = page.iter().map(|message| message.dt).collect();
+    dts.dedup();
+    (dts.len() >= page_size).then(|| dts.first().copied()).flatten()
+}
+
+/// Send one prompt to the default profile's first model and return the response, without starting the TUI.
+/// Nothing is written to the database
+pub async fn run_single_prompt(
+    database: &Database,
+    prompt: &str,
+    json_mode: bool,
+    stop_sequences: Vec<String>,
+) -> Result<String> {
+    if !database.chat_profile_exists(DEFAULT_PROFILE_ID).await? {
+        anyhow::bail!("No default profile yet, run shore once to set one up");
+    }
+    let default_profile = database.get_chat_profile(DEFAULT_PROFILE_ID).await?;
+    let model_id = *default_profile
+        .model_ids
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("The default profile has no models"))?;
+    let model = database
+        .get_all_models(false)
+        .await?
+        .into_iter()
+        .find(|model| model.id == model_id)
+        .ok_or_else(|| anyhow::anyhow!("Model id {} not found", model_id))?;
+    let provider = database
+        .get_providers()
+        .await?
+        .into_iter()
+        .find(|provider| provider.id == model.provider_id)
+        .ok_or_else(|| anyhow::anyhow!("Provider for model id {} not found", model_id))?;
+    if std::env::var(&provider.api_key_env_var).is_err() {
+        anyhow::bail!("{} is not set for provider {}", provider.api_key_env_var, provider.name);
+    }
+
+    info!("Running single prompt with model {}", model.model);
+    let remove_think_tokens = Settings::load(database)
+        .await?
+        .hides_think_tokens(&provider.name, &model.model);
+    let provider_client = OpenAIProvider::new(provider);
+
+    // the prompt is sent like the first one of a new chat with these settings
+    let chat = Chat {
+        json_mode,
+        stop_sequences: (!stop_sequences.is_empty())
+            .then(|| serde_json::to_string(&stop_sequences))
+            .transpose()?,
+        ..Chat::default()
+    };
+    let json_mode = chat.json_mode && model.supports_json_mode;
+    if chat.json_mode && !json_mode {
+        info!("Model {} doesn't support JSON mode, asking for text instead", model.model);
+    }
+    let options = generation_options_for(json_mode, &chat);
+    let system_prompt = system_prompt_for(&chat, json_mode);
+    let mut conversation = vec![ChatMessage::new_user_message(0, prompt.to_string())];
+    let truncated_message_count = model.truncate_to_context_window(&mut conversation, &system_prompt);
+    if truncated_message_count > 0 {
+        info!(
+            "Dropped {} messages to fit the context window of model id: {}",
+            truncated_message_count, model.id
+        );
+    }
+
+    let result = generate_response(
+        &provider_client,
+        &model,
+        &system_prompt,
+        &conversation,
+        remove_think_tokens,
+        &options,
+    )
+    .await?;
+
+    Ok(result.content.unwrap_or_default())
+}
+
 impl App {
     pub async fn new(
         database: Database,
+        db_path: PathBuf,
+        read_only: bool,
+        log_buffer: Option<LogBuffer>,
     ) -> Result<(Self, mpsc::UnboundedReceiver<InferenceEvent>)> {
         // Initialize providers from database
         let provider_records = database.get_providers().await?;
@@ -164,6 +717,7 @@ impl App {
         let mut provider_api_keys_set = HashMap::new();
         let mut cached_provider_data = Vec::new();
         let mut provider_names = HashMap::new();
+        let mut providers_by_id = HashMap::new();
         for provider_record in provider_records {
             let api_key_set = std::env::var(&provider_record.api_key_env_var).is_ok();
             if api_key_set {
@@ -178,6 +732,7 @@ impl App {
             }
             provider_api_keys_set.insert(provider_record.id, api_key_set);
             provider_names.insert(provider_record.id, provider_record.name.clone());
+            providers_by_id.insert(provider_record.id, provider_record.clone());
             cached_provider_data.push((
                 provider_record.name,
                 provider_record.api_key_env_var,
@@ -186,71 +741,82 @@ impl App {
         }
 
         // Load all available models into HashMap
-        let models = database.get_all_models().await?;
-        let mut available_models = HashMap::new();
-        let mut all_models = HashMap::new();
-        for model in models {
-            info!("Model {}: {}", model.id, model.model);
-            all_models.insert(model.id, model.clone());
-            if *provider_api_keys_set
-                .get(&model.provider_id)
-                .unwrap_or(&false)
-            {
-                available_models.insert(model.id, model);
-            }
-        }
-
-        // Check if default chat profile (ID 1) exists and create it if necessary
-        if !database.chat_profile_exists(0).await? {
-            info!("Default chat profile (ID 0) does not exist. Creating it...");
-
-            let chosen_model_id = find_first_viable_model(&database).await?;
-
-            if let Some(model_id) = chosen_model_id {
-                database.create_default_chat_profile(model_id).await?;
-            } else {
-                info!(
-                    "Warning: No suitable model found for default chat profile. Please configure providers and models first."
-                );
-            }
-        } else {
-            // remove any models in the default profile that rely on providers for which an API key is not set
-            let default_profile = database.get_chat_profile(0).await?;
-            let default_models = default_profile.model_ids.clone();
-            let mut models_retained = 0;
-            for model_id in default_models {
-                if !available_models.contains_key(&model_id) {
-                    database.remove_chat_profile_model(0, model_id).await?;
-                } else {
-                    models_retained += 1;
-                }
-            }
+        let models = database.get_all_models(true).await?;
+        let (available_models, all_models) = index_models(
+            models,
+            &providers_by_id,
+            &provider_api_keys_set,
+            &HashSet::new(),
+        );
 
-            // if we had to remove all the default models, run through the same "first viable model search" we do if default profile doesnt exist
-            if models_retained == 0 {
+        // Check if default chat profile exists and create it if necessary, read-only mode leaves
+        // it alone and only drops unavailable models in memory below
+        if !read_only {
+            if !database.chat_profile_exists(DEFAULT_PROFILE_ID).await? {
                 info!(
-                    "Default chat profile became empty after removing models without API keys. Finding first viable model..."
+                    "Default chat profile (ID {}) does not exist. Creating it...",
+                    DEFAULT_PROFILE_ID
                 );
 
                 let chosen_model_id = find_first_viable_model(&database).await?;
 
                 if let Some(model_id) = chosen_model_id {
-                    database.set_chat_profile_models(0, vec![model_id]).await?;
-                    info!("Added model {} to default chat profile.", model_id);
+                    database.create_default_chat_profile(model_id).await?;
                 } else {
                     info!(
                         "Warning: No suitable model found for default chat profile. Please configure providers and models first."
                     );
                 }
             } else {
-                info!("Default chat profile (ID 1) exists and has valid models.");
+                // remove any models in the default profile that rely on providers for which an API key is not set
+                let default_profile = database.get_chat_profile(DEFAULT_PROFILE_ID).await?;
+                let default_models = default_profile.model_ids.clone();
+                let mut models_retained = 0;
+                for model_id in default_models {
+                    if !available_models.contains_key(&model_id) {
+                        database.remove_chat_profile_model(DEFAULT_PROFILE_ID, model_id).await?;
+                    } else {
+                        models_retained += 1;
+                    }
+                }
+
+                // if we had to remove all the default models, run through the same "first viable model search" we do if default profile doesnt exist
+                if models_retained == 0 {
+                    info!(
+                        "Default chat profile became empty after removing models without API keys. Finding first viable model..."
+                    );
+
+                    let chosen_model_id = find_first_viable_model(&database).await?;
+
+                    if let Some(model_id) = chosen_model_id {
+                        database.set_chat_profile_models(DEFAULT_PROFILE_ID, vec![model_id]).await?;
+                        info!("Added model {} to default chat profile.", model_id);
+                    } else {
+                        info!(
+                            "Warning: No suitable model found for default chat profile. Please configure providers and models first."
+                        );
+                    }
+                } else {
+                    info!(
+                        "Default chat profile (ID {}) exists and has valid models.",
+                        DEFAULT_PROFILE_ID
+                    );
+                }
             }
         }
 
         // Load default chat profile
-        let default_profile = database.get_chat_profile(0).await?;
+        let mut default_profile = database.get_chat_profile(DEFAULT_PROFILE_ID).await?;
+        default_profile.model_ids.retain(|model_id| available_models.contains_key(model_id));
         let current_chat_profile = default_profile.clone();
 
+        let bookmarked_message_ids = database
+            .get_bookmarks()
+            .await?
+            .into_iter()
+            .map(|bookmark| bookmark.message_id)
+            .collect();
+
         let (user_event_tx, user_event_rx) = mpsc::unbounded_channel();
 
         // start with the provider dialog open if no api keys are set
@@ -260,10 +826,21 @@ impl App {
             AppState::Normal
         };
 
-        let chat_history = database.get_all_chats().await?;
+        let settings = Settings::load(&database).await?;
+        let chat_history = database
+            .get_chats(false, settings.chat_sort, settings.chat_history_page_size as i64, 0)
+            .await?;
+        let chat_history_exhausted = chat_history.len() < settings.chat_history_page_size;
+        let inference_semaphore = Arc::new(Semaphore::new(settings.max_concurrent_inferences));
         let mut app = Self {
             clear_last_key_press: false,
+            external_edit_requested: false,
+            prompt_preview: false,
             database: Arc::new(database),
+            data_dir: db_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            db_path,
+            read_only,
+            clipboard_unavailable: false,
             state,
             default_profile,
             current_chat: Chat::default(),
@@ -271,12 +848,17 @@ impl App {
             current_chat_profile,
             chat_history,
             current_messages: HashMap::new(),
+            older_messages_before_dt: None,
             chat_history_index: 0,
+            chat_history_visual_start: None,
+            chat_history_pages: 1,
+            chat_history_exhausted,
             current_message_index: HashMap::new(),
             current_chunk_idx: HashMap::new(),
-            current_message_chunks_length: HashMap::new(),
+            chat_viewport: (0, 0),
             chat_item_selections: HashMap::new(),
             chat_history_collapsed: false,
+            show_archived: false,
             textarea: EditorState::default(),
             title_textarea: EditorState::default(),
             search_textarea: EditorState::default(),
@@ -285,6 +867,10 @@ impl App {
             user_event_tx,
             title_inference_in_progress_by_chat: HashSet::new(),
             inference_in_progress_by_message_and_model: HashSet::new(),
+            inference_running_by_message_and_model: HashMap::new(),
+            inference_semaphore,
+            settings,
+            context_truncated_by_message_and_model: HashMap::new(),
             inference_handles_by_chat_and_model: HashMap::new(),
             provider_clients,
             provider_api_keys_set,
@@ -292,25 +878,79 @@ impl App {
             available_models,
             all_models,
             provider_names,
+            providers: providers_by_id,
+            providers_marked_down: HashSet::new(),
+            provider_failure_streaks: HashMap::new(),
+            model_refresh_provider_count: None,
+            last_provider_recheck: Instant::now(),
+            status_message: None,
             model_select_modal: None,
+            named_profiles: Vec::new(),
+            profile_picker_index: 0,
+            pending_profile_model_ids: Vec::new(),
+            alias_model_id: None,
+            library_prompts: Vec::new(),
+            prompt_library_index: 0,
+            editing_library_prompt_id: None,
+            pending_library_prompt_name: String::new(),
+            library_prompt_textarea: EditorState::default(),
+            bookmarks: Vec::new(),
+            bookmark_list_index: 0,
+            request_log: Vec::new(),
+            request_log_index: 0,
+            global_search: None,
+            bookmarked_message_ids,
             spinner_frame: 0,
             last_spinner_update: Instant::now(),
             numeric_prefix: None,
+            wrap_messages: true,
+            horizontal_offset_by_model: HashMap::new(),
+            following_latest: HashSet::new(),
+            collapsed_message_ids: HashSet::new(),
+            raw_message_ids: HashSet::new(),
+            continuing_message_ids: HashSet::new(),
+            response_candidates: HashMap::new(),
+            unified_view: false,
+            system_prompt_header: SystemPromptHeader::Hidden,
+            unified_scroll: 0,
+            unified_line_count: 0,
+            unified_scroll_from_end: None,
             current_selected_message_index: None,
+            current_selected_chunk_text: None,
             unavailable_models_info: Vec::new(),
+            error_detail: None,
+            error_detail_scroll: 0,
+            request_preview: None,
+            request_preview_scroll: 0,
+            log_buffer,
+            log_viewer_scroll: 0,
+            log_viewer_rows: 0,
             last_key_press: None,
             editor_event_handler: EditorEventHandler::default(),
+            prompt_history: VecDeque::new(),
+            prompt_history_index: None,
+            saved_prompt_draft: String::new(),
+            last_draft_autosave: Instant::now(),
         };
 
         // this feels a little wrong as it guarantees that we're going to
         // initialize the current_chat field at least twice. But the alternative
         // is refactoring create_new_chat and load_selected_chat to not rely on self
-        if let Some(_) = app.chat_history.first() {
+        if !app.chat_history.is_empty() {
             app.load_selected_chat().await?;
         } else {
             app.create_new_chat().await?;
         }
 
+        // pick up where the last session's prompt left off
+        if let Some(draft) = app.database.get_prompt_draft().await? {
+            set_editor_state_text(&mut app.textarea, draft.clone());
+            let last_row = app.textarea.lines.len().saturating_sub(1);
+            let last_col = app.textarea.lines.len_col(last_row).unwrap_or(0);
+            app.textarea.cursor = Index2::new(last_row, last_col);
+            app.saved_prompt_draft = draft;
+        }
+
         Ok((app, user_event_rx))
     }
 
@@ -337,30 +977,52 @@ impl App {
         Ok(())
     }
 
-    async fn run_app<B: Backend>(
+    async fn run_app<B: Backend + io::Write>(
         &mut self,
         terminal: &mut Terminal<B>,
         inference_event_rx: &mut mpsc::UnboundedReceiver<InferenceEvent>,
     ) -> Result<()> {
         let mut event_stream = EventStream::new();
 
+        if self.settings.refresh_models_on_startup {
+            self.spawn_model_refresh();
+        }
+
         loop {
             // Update spinner animation
             self.update_spinner();
+            self.recheck_marked_down_providers();
 
-            terminal.draw(|f| ui(f, self))?;
+            let mut hyperlinks = Vec::new();
+            terminal.draw(|f| {
+                ui(f, self);
+                if self.settings.hyperlinks {
+                    hyperlinks = find_hyperlinks(f.buffer_mut(), self.settings.theme.link);
+                }
+            })?;
+            draw_hyperlinks(terminal.backend_mut(), &hyperlinks)?;
 
             if self.should_quit {
+                self.autosave_prompt_draft(true).await;
                 break;
             }
 
+            if self.external_edit_requested {
+                self.external_edit_requested = false;
+                // the event stream reads stdin in the background, so it can't be around while the editor has the terminal
+                drop(event_stream);
+                self.edit_prompt_in_external_editor(terminal)?;
+                event_stream = EventStream::new();
+                continue;
+            }
+
             tokio::select! {
                 maybe_event = event_stream.next() => {
                     match maybe_event {
-                        Some(Ok(Event::Key(key))) => {
-                            if key.kind == KeyEventKind::Press || key.kind == KeyEventKind::Repeat {
-                                self.handle_key_event(key).await?;
-                            }
+                        Some(Ok(Event::Key(key)))
+                            if key.kind == KeyEventKind::Press || key.kind == KeyEventKind::Repeat =>
+                        {
+                            self.handle_key_event(key).await?;
                         }
                         Some(Err(e)) => {
                             error!("Error reading terminal event: {:?}", e);
@@ -381,11 +1043,68 @@ impl App {
                     // Timeout to ensure spinner updates even without user input
                 }
             }
+
+            self.autosave_prompt_draft(false).await;
         }
 
         Ok(())
     }
 
+    /// Hand the prompt to $VISUAL or $EDITOR (vi if neither is set) through a temp file and read it back
+    /// once the editor exits. The TUI is suspended in the meantime
+    fn edit_prompt_in_external_editor<B: Backend + io::Write>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+    ) -> Result<()> {
+        // a fresh file with an unguessable name, so nothing else can plant or read the prompt
+        let mut file = tempfile::Builder::new()
+            .prefix("shore-prompt-")
+            .suffix(".md")
+            .tempfile()?;
+        io::Write::write_all(&mut file, editor_state_to_string(&self.textarea).as_bytes())?;
+        let path = file.into_temp_path();
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .ok()
+            .filter(|editor| !editor.trim().is_empty())
+            .unwrap_or_else(|| "vi".to_string());
+
+        disable_raw_mode()?;
+        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        // the editor command can carry its own arguments, e.g. "code --wait"
+        let mut command = editor.split_whitespace();
+        let status = std::process::Command::new(command.next().unwrap_or("vi"))
+            .args(command)
+            .arg(&path)
+            .status();
+        enable_raw_mode()?;
+        execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+        terminal.clear()?;
+
+        match status {
+            Ok(status) if status.success() => {
+                let content = std::fs::read_to_string(&path)?;
+                set_editor_state_text(
+                    &mut self.textarea,
+                    content.trim_end_matches('\n').to_string(),
+                );
+            }
+            Ok(status) => {
+                self.set_status_message(format!(
+                    "{} exited with {}, prompt unchanged",
+                    editor, status
+                ));
+            }
+            Err(e) => {
+                self.set_status_message(format!("Couldn't start {}: {}", editor, e));
+            }
+        }
+        if let Err(e) = path.close() {
+            info!("Couldn't remove the prompt's temp file: {}", e);
+        }
+        Ok(())
+    }
+
     async fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
         match self.state {
             AppState::Normal => {
@@ -405,10 +1124,33 @@ impl App {
             AppState::DatabaseSelection => self.handle_database_selection_key(key).await?,
             AppState::ProviderDialog => self.handle_provider_dialog_key(key).await?,
             AppState::DeleteConfirmation => self.handle_delete_confirmation_key(key).await?,
+            AppState::ClearConfirmation => self.handle_clear_confirmation_key(key).await?,
+            AppState::ClearAllChatsConfirmation => {
+                self.handle_clear_all_chats_confirmation_key(key).await?
+            }
+            AppState::QuitConfirmation => self.handle_quit_confirmation_key(key).await?,
             AppState::TitleEdit => self.handle_title_edit_key(key).await?,
+            AppState::ProfileNameEdit => self.handle_profile_name_edit_key(key).await?,
+            AppState::ModelAliasEdit => self.handle_model_alias_edit_key(key).await?,
+            AppState::StopSequencesEdit => self.handle_stop_sequences_edit_key(key).await?,
+            AppState::ProfilePicker => self.handle_profile_picker_key(key).await?,
+            AppState::PromptLibrary => self.handle_prompt_library_key(key).await?,
+            AppState::PromptLibraryNameEdit => self.handle_library_prompt_name_edit_key(key),
+            AppState::PromptLibraryContentEdit => {
+                self.handle_library_prompt_content_edit_key(key).await?
+            }
+            AppState::PromptLibraryDeleteConfirmation => {
+                self.handle_library_prompt_delete_confirmation_key(key).await?
+            }
+            AppState::BookmarkList => self.handle_bookmark_list_key(key).await?,
+            AppState::GlobalSearch => self.handle_global_search_key(key).await?,
             AppState::UnavailableModelsError => {
                 self.handle_unavailable_models_error_key(key).await?
             }
+            AppState::ErrorDetail => self.handle_error_detail_key(key).await?,
+            AppState::RequestPreview => self.handle_request_preview_key(key).await?,
+            AppState::LogViewer => self.handle_log_viewer_key(key).await?,
+            AppState::RequestLog => self.handle_request_log_key(key),
         }
 
         Ok(())
@@ -422,8 +1164,10 @@ impl App {
                 modifiers,
                 ..
             } if modifiers.contains(KeyModifiers::SHIFT | KeyModifiers::CONTROL) => {
-                self.open_model_selection_dialog(ModelSelectionMode::DefaultModels)
-                    .await?;
+                if !self.refuse_if_read_only("Changing the default models") {
+                    self.open_model_selection_dialog(ModelSelectionMode::DefaultModels)
+                        .await?;
+                }
                 self.numeric_prefix = None;
                 return Ok(());
             }
@@ -454,9 +1198,25 @@ impl App {
                 self.chat_history_collapsed = !self.chat_history_collapsed;
                 return Ok(());
             }
+            KeyEvent {
+                code: KeyCode::Char('o'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => {
+                self.prompt_preview = !self.prompt_preview;
+                return Ok(());
+            }
             _ => {}
         }
 
+        if matches!(key.code, KeyCode::Up | KeyCode::Down)
+            && key.modifiers.is_empty()
+            && self.recall_prompt(key.code == KeyCode::Up)
+        {
+            self.numeric_prefix = None;
+            return Ok(());
+        }
+
         // if the prompt editor is in insert mode, all events go to the prompt editor
         // unless it is the enter key, which will submit the message
         // Shift-Enter should be sent to the editor though
@@ -476,15 +1236,52 @@ impl App {
         // Get the count to use for navigation (default to 1 if no prefix)
         let count = self.numeric_prefix.unwrap_or(1);
 
+        // the unified view scrolls by line since its messages come from several models
+        if is_prompt_empty && self.unified_view {
+            let handled = match key.code {
+                KeyCode::Char('g') if self.last_key_press == Some(KeyCode::Char('g')) => {
+                    while self.load_older_messages().await? {}
+                    self.unified_scroll = 0;
+                    self.clear_last_key_press = true;
+                    true
+                }
+                KeyCode::Char('j') if key.modifiers.is_empty() => {
+                    self.unified_scroll = self.unified_scroll.saturating_add(count);
+                    true
+                }
+                KeyCode::Char('k') if key.modifiers.is_empty() => {
+                    // scrolling up past the top of a long chat loads the messages before it, the
+                    // lines in view stay put as they move down
+                    if self.unified_scroll < count && self.load_older_messages().await? {
+                        self.unified_scroll_from_end =
+                            Some(self.unified_line_count + count - self.unified_scroll);
+                    }
+                    self.unified_scroll = self.unified_scroll.saturating_sub(count);
+                    true
+                }
+                KeyCode::Char('G') => {
+                    self.unified_scroll = usize::MAX;
+                    true
+                }
+                _ => false,
+            };
+            if handled {
+                self.numeric_prefix = None;
+                return Ok(());
+            }
+        }
+
         // When prompt is empty, we repurpose editor bindings for other stuff
         if is_prompt_empty {
             match key.code {
                 KeyCode::Char('g') if self.last_key_press == Some(KeyCode::Char('g')) => {
+                    while self.load_older_messages().await? {}
                     if let Some(current_model_id) = self
                         .current_chat_profile
                         .model_ids
                         .get(self.current_model_idx)
                     {
+                        self.following_latest.remove(current_model_id);
                         let message_idx = self.current_message_index.get_mut(current_model_id);
                         if let Some(message_idx) = message_idx {
                             *message_idx = 0;
@@ -543,96 +1340,142 @@ impl App {
                     self.numeric_prefix = None;
                     return Ok(());
                 }
-                KeyCode::Char('h') => {
-                    // Decrement current_model_idx
-                    if self.current_model_idx > 0 {
-                        self.current_model_idx = self.current_model_idx.saturating_sub(1);
+                KeyCode::Char('h') if self.is_panning_messages() => {
+                    self.pan_messages(-(count as isize));
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('l') if self.is_panning_messages() => {
+                    self.pan_messages(count as isize);
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('<') | KeyCode::Char('>') => {
+                    self.cycle_response_candidate(key.code == KeyCode::Char('>')).await?;
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('O') => {
+                    self.settings.chat_sort = self.settings.chat_sort.next();
+                    self.save_setting("chat_sort", self.settings.chat_sort.as_setting()).await?;
+                    self.reload_chat_history().await?;
+                    self.set_status_message(format!("Chats sorted by {}", self.settings.chat_sort));
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('U') => {
+                    self.unified_view = !self.unified_view;
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('H') => {
+                    self.system_prompt_header = self.system_prompt_header.next();
+                    if self.current_chat.system_prompt.is_none() {
+                        self.set_status_message(
+                            "This chat uses the default system prompt, there's nothing to pin"
+                                .to_string(),
+                        );
                     }
                     self.numeric_prefix = None;
                     return Ok(());
                 }
+                KeyCode::Char('W') => {
+                    self.wrap_messages = !self.wrap_messages;
+                    self.horizontal_offset_by_model.clear();
+                    self.set_status_message(if self.wrap_messages {
+                        "Wrapping on".to_string()
+                    } else {
+                        "Wrapping off, select a message to pan it with h/l".to_string()
+                    });
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('h') => {
+                    // Decrement current_model_idx by count, stopping at the first model
+                    self.current_model_idx = self.current_model_idx.saturating_sub(count);
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
                 KeyCode::Char('l') => {
-                    // Increment current_model_idx
+                    // Increment current_model_idx by count, stopping at the last model
                     if !self.current_chat_profile.model_ids.is_empty() {
                         let max_idx = self.current_chat_profile.model_ids.len() - 1;
-                        self.current_model_idx = (self.current_model_idx + 1).min(max_idx);
+                        self.current_model_idx = (self.current_model_idx + count).min(max_idx);
                     }
                     self.numeric_prefix = None;
                     return Ok(());
                 }
-                KeyCode::Char('j') => {
-                    // Navigate down through message chunks
+                KeyCode::Char('j') | KeyCode::Char('k') if key.modifiers.is_empty() => {
+                    if key.code == KeyCode::Char('k') {
+                        self.load_older_messages_within(count).await?;
+                    }
+                    // Navigate through message chunks
                     if let Some(&model_id) = self
                         .current_chat_profile
                         .model_ids
                         .get(self.current_model_idx)
+                        && let Some(messages) = self.current_messages.get(&model_id)
                     {
-                        let current_chunk_idx =
-                            self.current_chunk_idx.get(&model_id).copied().unwrap_or(0);
-                        let chunks_length = self
-                            .current_message_chunks_length
-                            .get(&model_id)
-                            .copied()
-                            .unwrap_or(1);
-                        let current_msg_idx = self
-                            .current_message_index
-                            .get(&model_id)
-                            .copied()
-                            .unwrap_or(0);
-                        let total_messages = self
-                            .current_messages
-                            .get(&model_id)
-                            .map(|msgs| msgs.len())
-                            .unwrap_or(0);
-
-                        // Try to increment chunk_idx first
-                        if current_chunk_idx + 1 < chunks_length {
-                            self.current_chunk_idx
-                                .insert(model_id, current_chunk_idx + 1);
-                        } else if current_msg_idx + 1 < total_messages {
-                            // At last chunk, move to next message
-                            self.current_message_index
-                                .insert(model_id, current_msg_idx + 1);
-                            self.current_chunk_idx.insert(model_id, 0);
+                        let position = (
+                            self.current_message_index.get(&model_id).copied().unwrap_or(0),
+                            self.current_chunk_idx.get(&model_id).copied().unwrap_or(0),
+                        );
+                        let steps = if key.code == KeyCode::Char('j') {
+                            count as isize
+                        } else {
+                            -(count as isize)
+                        };
+                        let (msg_idx, chunk_idx) =
+                            step_chunks(position, steps, messages.len(), |msg_idx| {
+                                crate::ui::message_chunk_count(self, model_id, &messages[msg_idx])
+                            });
+                        // scrolling up stops following new responses, reaching the last message resumes it
+                        if msg_idx + 1 == messages.len() {
+                            self.following_latest.insert(model_id);
+                        } else if steps < 0 {
+                            self.following_latest.remove(&model_id);
                         }
+                        self.current_message_index.insert(model_id, msg_idx);
+                        self.current_chunk_idx.insert(model_id, chunk_idx);
 
-                        self.chat_item_selections.get_mut(&model_id).map(|x| {
+                        if let Some(x) = self.chat_item_selections.get_mut(&model_id) {
                             *x = None;
-                        });
+                        }
                     }
                     self.numeric_prefix = None;
                     return Ok(());
                 }
-                KeyCode::Char('k') => {
-                    // Navigate up through message chunks
+                KeyCode::Char('(') | KeyCode::Char(')') => {
+                    if key.code == KeyCode::Char('(') {
+                        self.load_older_messages_within(count).await?;
+                    }
+                    // like j/k, but only stops on prompts
                     if let Some(&model_id) = self
                         .current_chat_profile
                         .model_ids
                         .get(self.current_model_idx)
+                        && let Some(messages) = self.current_messages.get(&model_id)
                     {
-                        let current_chunk_idx =
-                            self.current_chunk_idx.get(&model_id).copied().unwrap_or(0);
-                        let current_msg_idx = self
-                            .current_message_index
-                            .get(&model_id)
-                            .copied()
-                            .unwrap_or(0);
-
-                        if current_chunk_idx > 0 {
-                            // Move to previous chunk in same message
-                            self.current_chunk_idx
-                                .insert(model_id, current_chunk_idx - 1);
-                        } else if current_msg_idx > 0 {
-                            // At first chunk, move to previous message (render will set chunk to last)
-                            self.current_message_index
-                                .insert(model_id, current_msg_idx - 1);
-                            // Set to large number; render will clamp to last chunk of previous message
-                            self.current_chunk_idx.insert(model_id, usize::MAX);
+                        let msg_idx =
+                            self.current_message_index.get(&model_id).copied().unwrap_or(0);
+                        let steps = if key.code == KeyCode::Char(')') {
+                            count as isize
+                        } else {
+                            -(count as isize)
+                        };
+                        let prompt_idx = step_prompts(messages, msg_idx, steps);
+                        if prompt_idx != msg_idx {
+                            if prompt_idx + 1 == messages.len() {
+                                self.following_latest.insert(model_id);
+                            } else {
+                                self.following_latest.remove(&model_id);
+                            }
+                            self.current_message_index.insert(model_id, prompt_idx);
+                            self.current_chunk_idx.insert(model_id, 0);
+                            if let Some(x) = self.chat_item_selections.get_mut(&model_id) {
+                                *x = None;
+                            }
                         }
-
-                        self.chat_item_selections.get_mut(&model_id).map(|x| {
-                            *x = None;
-                        });
                     }
                     self.numeric_prefix = None;
                     return Ok(());
@@ -649,6 +1492,7 @@ impl App {
                         .model_ids
                         .get(self.current_model_idx);
                     if let Some(current_model_id) = current_model_id {
+                        self.following_latest.insert(*current_model_id);
                         let last_message_idx = self
                             .current_messages
                             .get(current_model_id)
@@ -667,76 +1511,304 @@ impl App {
                     }
                     return Ok(());
                 }
-                KeyCode::Char('x') | KeyCode::Char('d') => {
-                    // If search is active, clear it and keep the selected entry
-                    if !self.search_query.is_empty() {
-                        self.clear_search_filter().await?;
-                    } else {
-                        let text = editor_state_to_string(&self.textarea);
-                        if !text.trim().is_empty() {
-                            let mut event_handler = EditorEventHandler::default();
-                            event_handler.on_key_event(key, &mut self.textarea);
-                        } else if !self.chat_history.is_empty() {
-                            // Only allow deleting if we have a valid chat and it's not the only chat
-                            if self.current_chat.id == 0 {
-                                self.chat_history.remove(self.chat_history_index);
-                                self.load_selected_chat().await?;
-                            } else {
-                                // Open delete confirmation dialog if this chat is actually written in the db
-                                self.state = AppState::DeleteConfirmation;
-                            }
-                        }
+                KeyCode::Char('X') => {
+                    // only chats that have been written to the db can be archived
+                    if self.current_chat.id != 0 && !self.refuse_if_read_only("Archiving chats") {
+                        self.toggle_current_chat_archived().await?;
                     }
                     self.numeric_prefix = None;
-                    return Ok(())
+                    return Ok(());
                 }
-                _ => {}
-            }
-        }
+                KeyCode::Char('B') => {
+                    // branch off a full copy of the chat, only chats written to the db have anything to copy
+                    if self.current_chat.id != 0 && !self.refuse_if_read_only("Duplicating chats") {
+                        self.duplicate_current_chat().await?;
+                    }
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('E') => {
+                    if self.current_chat.id != 0 {
+                        match self.export_current_chat_json().await {
+                            Ok(path) => self.set_status_message(format!(
+                                "Exported chat to {}",
+                                path.display()
+                            )),
+                            Err(e) => self.set_status_message(format!("Export failed: {}", e)),
+                        }
+                    }
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('S') => {
+                    self.numeric_prefix = None;
+                    if self.refuse_if_read_only("Editing stop sequences") {
+                        return Ok(());
+                    }
+                    let stop_sequences = format_stop_sequences(&self.current_chat.stop_sequence_list());
+                    set_editor_state_text(&mut self.title_textarea, stop_sequences);
+                    self.title_textarea.mode = EditorMode::Insert;
+                    self.state = AppState::StopSequencesEdit;
+                    return Ok(());
+                }
+                KeyCode::Char('V') => {
+                    // mark a range of chats, it follows q/z until they're deleted or it's cancelled
+                    self.chat_history_visual_start = match self.chat_history_visual_start {
+                        Some(_) => None,
+                        None => Some(self.chat_history_index),
+                    };
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('F') => {
+                    self.open_global_search().await?;
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('\'') => {
+                    self.open_bookmark_list().await?;
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('M') => {
+                    self.open_prompt_library().await?;
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('P') => {
+                    self.open_profile_picker().await?;
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('R') => {
+                    // re-sync models from every provider, this also re-checks providers marked down
+                    if !self.refuse_if_read_only("Refreshing models") {
+                        self.spawn_model_refresh();
+                    }
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('T') => {
+                    self.settings.show_timestamps = !self.settings.show_timestamps;
+                    self.save_setting("show_timestamps", &self.settings.show_timestamps.to_string())
+                        .await?;
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('J') => {
+                    if !self.refuse_if_read_only("Changing JSON mode") {
+                        self.toggle_current_chat_json_mode().await?;
+                    }
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('I') => {
+                    self.copy_current_model_identifier();
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('Z') => {
+                    // wipes the whole database, so the user has to type its name to go ahead
+                    if !self.refuse_if_read_only("Deleting chats") {
+                        self.title_textarea = EditorState::default();
+                        self.title_textarea.mode = EditorMode::Insert;
+                        self.state = AppState::ClearAllChatsConfirmation;
+                    }
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('C') => {
+                    // wipe the messages but keep the chat, only worth confirming if something is written
+                    if !self.refuse_if_read_only("Clearing chats")
+                        && self.current_chat.id != 0
+                        && self.current_messages.values().any(|messages| !messages.is_empty())
+                    {
+                        self.state = AppState::ClearConfirmation;
+                    }
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('L') => {
+                    if self.log_buffer.is_some() {
+                        self.log_viewer_scroll = 0;
+                        self.state = AppState::LogViewer;
+                    } else {
+                        self.set_status_message(
+                            "The log viewer is off, it was started with --no-log-viewer".to_string(),
+                        );
+                    }
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('K') => {
+                    self.open_request_log().await?;
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('D') => {
+                    self.open_request_preview_dialog().await?;
+                    self.numeric_prefix = None;
+                    return Ok(());
+                }
+                KeyCode::Char('x') | KeyCode::Char('d') => {
+                    if self.chat_history_visual_start.is_some() {
+                        if !self.refuse_if_read_only("Deleting chats") {
+                            self.state = AppState::DeleteConfirmation;
+                        }
+                    // If search is active, clear it and keep the selected entry
+                    } else if !self.search_query.is_empty() {
+                        self.clear_search_filter().await?;
+                    } else {
+                        let text = editor_state_to_string(&self.textarea);
+                        if !text.trim().is_empty() {
+                            let mut event_handler = EditorEventHandler::default();
+                            event_handler.on_key_event(key, &mut self.textarea);
+                        } else if !self.chat_history.is_empty()
+                            && !self.refuse_if_read_only("Deleting chats")
+                        {
+                            // Only allow deleting if we have a valid chat and it's not the only chat
+                            if self.current_chat.id == 0 {
+                                self.chat_history.remove(self.chat_history_index);
+                                self.load_selected_chat().await?;
+                            } else {
+                                // Open delete confirmation dialog if this chat is actually written in the db
+                                self.state = AppState::DeleteConfirmation;
+                            }
+                        }
+                    }
+                    self.numeric_prefix = None;
+                    return Ok(())
+                }
+                _ => {}
+            }
+        }
 
         // selected message yanking support
-        // currently we yank the entire message, not just the selected chunk
+        // y yanks the entire message, not just the selected chunk
         // copying "too much" in some scenarios seems preferable to making the user have to yank multiple chunks
-        // in other scenarios
+        // in other scenarios. Y is there for when only the visible chunk is wanted
+        let mut yanked = None;
         if let Some(selection_idx_opt) = self
             .chat_item_selections
             .get_mut(&self.current_chat_profile.model_ids[self.current_model_idx])
+            && selection_idx_opt.is_some()
+            && let KeyCode::Char('Y') = key.code
         {
-            if let Some(selection_idx) = selection_idx_opt {
-                match key.code {
-                    KeyCode::Char('y') => {
-                        let messages = self
-                            .current_messages
-                            .get(&self.current_chat_profile.model_ids[self.current_model_idx]);
-                        let message = messages
-                            .and_then(|messages| messages.get(*selection_idx as usize))
-                            .and_then(|message| {
-                                message
-                                    .content
-                                    .clone()
-                                    .or(message.error.clone()) // if there was no content, copy the error
-                            })
-                            .unwrap_or_default();
+            if let Some(chunk_text) = self.current_selected_chunk_text.clone()
+                && !chunk_text.is_empty()
+            {
+                yanked = Some(chunk_text);
+            }
 
-                        // Copy message content to clipboard
-                        if !message.is_empty() {
-                            match ClipboardContext::new() {
-                                Ok(mut ctx) => {
-                                    if let Err(e) = ctx.set_contents(message) {
-                                        error!("Failed to copy to clipboard: {}", e);
-                                    }
-                                }
-                                Err(e) => {
-                                    error!("Failed to create clipboard context: {}", e);
-                                }
-                            }
-                        }
+            *selection_idx_opt = None;
+        }
 
-                        *selection_idx_opt = None;
-                    }
-                    _ => {}
-                }
+        if let Some(selection_idx_opt) = self
+            .chat_item_selections
+            .get_mut(&self.current_chat_profile.model_ids[self.current_model_idx])
+            && let Some(selection_idx) = selection_idx_opt
+            && let KeyCode::Char('y') = key.code
+        {
+            let messages = self
+                .current_messages
+                .get(&self.current_chat_profile.model_ids[self.current_model_idx]);
+            let message = messages
+                .and_then(|messages| messages.get(*selection_idx as usize))
+                .and_then(|message| {
+                    message
+                        .content
+                        .clone()
+                        .or(message.error.clone()) // if there was no content, copy the error
+                })
+                .unwrap_or_default();
+
+            // Copy message content to clipboard
+            if !message.is_empty() {
+                yanked = Some(message);
+            }
+
+            *selection_idx_opt = None;
+        }
+        if let Some(text) = yanked {
+            self.copy_to_clipboard(text);
+        }
+
+        // collapse the selected message to a preview, or expand it again
+        if key.code == KeyCode::Char('f')
+            && key.modifiers.is_empty()
+            && self
+                .chat_item_selections
+                .get(&self.current_chat_profile.model_ids[self.current_model_idx])
+                .is_some_and(|selection| selection.is_some())
+        {
+            self.toggle_selected_message_in(|app| &mut app.collapsed_message_ids);
+            return Ok(());
+        }
+
+        // show the selected message as its markdown source, or render it again
+        if key.code == KeyCode::Char('v')
+            && key.modifiers.is_empty()
+            && self
+                .chat_item_selections
+                .get(&self.current_chat_profile.model_ids[self.current_model_idx])
+                .is_some_and(|selection| selection.is_some())
+        {
+            self.toggle_selected_message_in(|app| &mut app.raw_message_ids);
+            return Ok(());
+        }
+
+        // bookmark the selected message, or remove its bookmark
+        if key.code == KeyCode::Char('m')
+            && let Some(selection_idx_opt) = self
+                .chat_item_selections
+                .get_mut(&self.current_chat_profile.model_ids[self.current_model_idx])
+            && selection_idx_opt.is_some()
+        {
+            *selection_idx_opt = None;
+            if let Some(message_id) = self
+                .current_selected_message_index
+                .and_then(|idx| self.get_current_messages().and_then(|messages| messages.get(idx)))
+                .map(|message| message.id)
+            {
+                self.toggle_bookmark(message_id).await?;
+            }
+            return Ok(());
+        }
+
+        // run the prompt behind the selected failed response again
+        if key.code == KeyCode::Char('r')
+            && let Some(Some(_)) = self
+                .chat_item_selections
+                .get(&self.current_chat_profile.model_ids[self.current_model_idx])
+        {
+            if !self.refuse_if_read_only("Retrying responses") {
+                self.retry_selected_message().await?;
+            }
+            return Ok(());
+        }
+
+        // pick the selected response back up where it was cut off
+        if key.code == KeyCode::Char('a')
+            && key.modifiers.is_empty()
+            && let Some(Some(_)) = self
+                .chat_item_selections
+                .get(&self.current_chat_profile.model_ids[self.current_model_idx])
+        {
+            if !self.refuse_if_read_only("Continuing responses") {
+                self.continue_selected_message().await?;
             }
+            return Ok(());
+        }
+
+        // show the full error for the selected message, if it has one
+        if key.code == KeyCode::Char('e')
+            && !key.modifiers.contains(KeyModifiers::CONTROL)
+            && let Some(Some(_)) = self
+                .chat_item_selections
+                .get(&self.current_chat_profile.model_ids[self.current_model_idx])
+        {
+            self.open_error_detail_dialog();
+            return Ok(());
         }
 
         match key {
@@ -745,7 +1817,12 @@ impl App {
                 modifiers: KeyModifiers::SHIFT,
                 ..
             } => {
-                self.should_quit = true;
+                // losing a long prompt or a running response to a stray keypress is worth a confirmation
+                if self.has_unsaved_draft() || !self.inference_in_progress_by_message_and_model.is_empty() {
+                    self.state = AppState::QuitConfirmation;
+                } else {
+                    self.should_quit = true;
+                }
                 self.numeric_prefix = None;
             }
             KeyEvent {
@@ -756,22 +1833,78 @@ impl App {
                 self.create_new_chat().await?;
                 self.numeric_prefix = None;
             }
+            KeyEvent {
+                code: KeyCode::Char('N'),
+                ..
+            } => {
+                // new chat that keeps the models of the current one instead of the defaults
+                let model_ids = self.current_chat_profile.model_ids.clone();
+                self.create_new_chat_with_models(model_ids).await?;
+                self.numeric_prefix = None;
+            }
+            KeyEvent {
+                code: KeyCode::Char('a'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => {
+                self.show_archived = !self.show_archived;
+                self.reload_chat_history().await?;
+                self.numeric_prefix = None;
+            }
             KeyEvent {
                 code: KeyCode::Char('t'),
                 modifiers: KeyModifiers::CONTROL,
                 ..
             } => {
                 // Only allow editing title for existing chats (id != 0)
-                if self.current_chat.id != 0 {
+                if self.current_chat.id != 0 && !self.refuse_if_read_only("Editing titles") {
                     self.open_title_edit_dialog();
                 }
                 self.numeric_prefix = None;
             }
+            KeyEvent {
+                code: KeyCode::Char('e'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => {
+                // the terminal is handed over in run_app, which owns it
+                self.external_edit_requested = true;
+                self.numeric_prefix = None;
+            }
+            KeyEvent {
+                code: KeyCode::Char('k'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => {
+                self.settings.keep_prompt_after_submit = !self.settings.keep_prompt_after_submit;
+                self.save_setting(
+                    "keep_prompt_after_submit",
+                    &self.settings.keep_prompt_after_submit.to_string(),
+                )
+                .await?;
+                self.set_status_message(if self.settings.keep_prompt_after_submit {
+                    "Prompts are kept after sending".to_string()
+                } else {
+                    "Prompts are cleared after sending".to_string()
+                });
+                self.numeric_prefix = None;
+            }
+            KeyEvent {
+                code: KeyCode::Char(ch @ ('q' | 'z')),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => {
+                self.move_selected_chat(ch == 'q', count).await?;
+                self.numeric_prefix = None;
+            }
             // Chat history navigation
             KeyEvent {
                 code: KeyCode::Char('z'),
                 ..
             } => {
+                while self.chat_history_index + count >= self.chat_history.len()
+                    && self.load_more_chat_history().await?
+                {}
                 let max_index = self.chat_history.len().saturating_sub(1);
                 self.chat_history_index = (self.chat_history_index + count).min(max_index);
                 self.load_selected_chat().await?;
@@ -794,10 +1927,9 @@ impl App {
                     .current_chat_profile
                     .model_ids
                     .get(self.current_model_idx)
+                    && let Some(x) = self.chat_item_selections.get_mut(&model_id)
                 {
-                    self.chat_item_selections.get_mut(&model_id).map(|x| {
-                        *x = Some(x.map(|x| x + 1).unwrap_or(0));
-                    });
+                    *x = Some(x.map(|x| x + 1).unwrap_or(0));
                 }
                 self.numeric_prefix = None;
             }
@@ -809,10 +1941,30 @@ impl App {
                     .current_chat_profile
                     .model_ids
                     .get(self.current_model_idx)
+                    && let Some(x) = self.chat_item_selections.get_mut(&model_id)
                 {
-                    self.chat_item_selections.get_mut(&model_id).map(|x| {
-                        *x = Some(x.map(|x| x - 1).unwrap_or(-1));
-                    });
+                    *x = Some(x.map(|x| x - 1).unwrap_or(-1));
+                }
+                self.numeric_prefix = None;
+            }
+            // Model reordering, swaps the current model with its neighbor
+            KeyEvent {
+                code: KeyCode::Char('{'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            } => {
+                if !self.refuse_if_read_only("Reordering models") {
+                    self.move_current_model(false).await?;
+                }
+                self.numeric_prefix = None;
+            }
+            KeyEvent {
+                code: KeyCode::Char('}'),
+                modifiers: KeyModifiers::ALT,
+                ..
+            } => {
+                if !self.refuse_if_read_only("Reordering models") {
+                    self.move_current_model(true).await?;
                 }
                 self.numeric_prefix = None;
             }
@@ -841,6 +1993,18 @@ impl App {
                 }
                 self.numeric_prefix = None;
             }
+            KeyEvent {
+                code: KeyCode::Char('|'),
+                ..
+            } => {
+                // Jump to the model given by the numeric prefix (1-based, like vim's column jump), the first without one
+                if !self.current_chat_profile.model_ids.is_empty() {
+                    let model_number = self.numeric_prefix.unwrap_or(1).max(1);
+                    self.current_model_idx =
+                        (model_number - 1).min(self.current_chat_profile.model_ids.len() - 1);
+                }
+                self.numeric_prefix = None;
+            }
             KeyEvent {
                 code: KeyCode::Char('/'),
                 modifiers: KeyModifiers::NONE,
@@ -861,7 +2025,9 @@ impl App {
                 code: KeyCode::Esc, ..
             } => {
                 // TODO do we really want to allow the user to prompt while viewing search results?
-                if !self.search_query.is_empty() {
+                if self.chat_history_visual_start.is_some() {
+                    self.chat_history_visual_start = None;
+                } else if !self.search_query.is_empty() {
                     self.numeric_prefix = None;
                     self.clear_search_filter().await?;
                 } else if let Some(model_id) = self
@@ -875,6 +2041,13 @@ impl App {
                     event_handler.on_key_event(key, &mut self.textarea);
                 }
             }
+            KeyEvent {
+                code: KeyCode::Enter,
+                modifiers: KeyModifiers::ALT,
+                ..
+            } => {
+                self.add_aside().await?;
+            }
             KeyEvent {
                 code: KeyCode::Enter,
                 modifiers: KeyModifiers::NONE,
@@ -893,9 +2066,7 @@ impl App {
             } if self.last_key_press == Some(KeyCode::Char('c')) => {
                 // clear the textarea and place the user in insert mode
                 self.textarea = EditorState::default();
-                self.textarea.mode = EditorMode::Insert;
-                self.numeric_prefix = None;
-                self.clear_last_key_press = true;
+                self.enter_prompt_insert_mode();
             }
             _ => {
                 // Clear numeric prefix on any other key
@@ -913,9 +2084,10 @@ impl App {
             KeyCode::Esc => {
                 // Exit search mode and clear the search
                 self.state = AppState::Normal;
+                self.chat_history_visual_start = None;
                 self.search_query.clear();
                 self.search_textarea = EditorState::default();
-                self.chat_history = self.database.get_all_chats().await?;
+                self.chat_history = self.load_chat_history().await?;
                 // Adjust index if needed
                 if self.chat_history_index >= self.chat_history.len()
                     && !self.chat_history.is_empty()
@@ -938,9 +2110,11 @@ impl App {
 
                 // Perform the search and update chat_history
                 if self.search_query.is_empty() {
-                    self.chat_history = self.database.get_all_chats().await?;
+                    self.chat_history = self.load_chat_history().await?;
                 } else {
-                    self.chat_history = self.database.search_all(&self.search_query, 1000).await?;
+                    self.chat_history = self.database
+                        .search_all(&self.search_query, 1000, self.show_archived)
+                        .await?;
                 }
 
                 // Reset chat history index to the first result
@@ -969,6 +2143,29 @@ impl App {
                     self.model_select_modal = None;
                     self.state = AppState::Normal;
                 }
+                ModalResult::SaveAsProfile(selected_models) => {
+                    if self.refuse_if_read_only("Saving profiles") {
+                        return Ok(());
+                    }
+                    // ask for a name, the modal stays open underneath
+                    self.pending_profile_model_ids = selected_models;
+                    self.title_textarea = EditorState::default();
+                    self.title_textarea.mode = EditorMode::Insert;
+                    self.state = AppState::ProfileNameEdit;
+                }
+                ModalResult::EditAlias(model_id) => {
+                    if !self.refuse_if_read_only("Editing aliases") {
+                        let alias = self
+                            .all_models
+                            .get(&model_id)
+                            .and_then(|model| model.alias.clone())
+                            .unwrap_or_default();
+                        self.alias_model_id = Some(model_id);
+                        set_editor_state_text(&mut self.title_textarea, alias);
+                        self.title_textarea.mode = EditorMode::Insert;
+                        self.state = AppState::ModelAliasEdit;
+                    }
+                }
             }
         }
         Ok(())
@@ -994,7 +2191,11 @@ impl App {
                 self.state = AppState::Normal;
             }
             KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
-                self.delete_current_chat().await?;
+                if self.chat_history_visual_range().is_some() {
+                    self.delete_marked_chats().await?;
+                } else {
+                    self.delete_current_chat().await?;
+                }
                 self.state = AppState::Normal;
             }
             _ => {}
@@ -1002,71 +2203,474 @@ impl App {
         Ok(())
     }
 
-    async fn create_new_chat(&mut self) -> Result<()> {
-        let new_chat = Chat {
-            id: 0,
-            dt: chrono::Utc::now().timestamp(),
-            title: None,
-        };
-        self.current_chat = new_chat.clone(); // this will be created when the first message is submitted
-        self.current_messages.clear();
-        self.state = AppState::Normal;
-        self.current_chat_profile = self.default_profile.clone();
-        self.current_model_idx = 0;
-
-        // Initialize navigation state and item selections for all models in current chat profile
-        self.current_message_index.clear();
-        self.current_chunk_idx.clear();
-        self.current_message_chunks_length.clear();
-        self.chat_item_selections.clear();
-        for &model_id in &self.current_chat_profile.model_ids {
-            self.current_message_index.insert(model_id, 0);
-            self.current_chunk_idx.insert(model_id, 0);
-            self.current_message_chunks_length.insert(model_id, 1);
-            self.chat_item_selections.insert(model_id, None);
+    async fn handle_clear_confirmation_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                self.clear_current_chat_messages().await?;
+                self.state = AppState::Normal;
+            }
+            _ => {}
         }
-
-        // this doesnt do a db insert, that wont happen until the first message is submitted
-        self.chat_history.insert(0, new_chat);
-        self.chat_history_index = 0;
-        self.textarea.mode = EditorMode::Insert;
-
         Ok(())
     }
 
-    async fn load_selected_chat(&mut self) -> Result<()> {
-        if let Some(chat) = self.chat_history.get(self.chat_history_index) {
-            self.current_chat = chat.clone();
-            self.current_messages.clear();
-
-            if chat.id != 0 {
-                // these can be done concurrently, but does this actually provide a speedup?
-                let (model_ids, tool_ids) = tokio::join!(
-                    self.database.get_chat_models_ids(chat.id),
-                    self.database.get_chat_tool_ids(chat.id)
+    async fn handle_clear_all_chats_confirmation_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Enter => {
+                self.state = AppState::Normal;
+                let typed = editor_state_to_string(&self.title_textarea);
+                if typed.trim() == self.database_name() {
+                    self.delete_all_chats().await?;
+                } else {
+                    self.set_status_message(
+                        "That isn't the database's name, no chats were deleted".to_string(),
+                    );
+                }
+            }
+            _ => {
+                let mut event_handler = EditorEventHandler::default();
+                event_handler.on_key_event(key, &mut self.title_textarea);
+            }
+        }
+        Ok(())
+    }
+
+    /// The name the clear all confirmation asks for, the database file without its extension
+    pub fn database_name(&self) -> String {
+        self.db_path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+    }
+
+    /// Delete every chat in the database and start over with a new one. Like switching databases
+    /// this waits until nothing is being generated, responses would be written to deleted chats
+    async fn delete_all_chats(&mut self) -> Result<()> {
+        if !self.inference_in_progress_by_message_and_model.is_empty()
+            || !self.title_inference_in_progress_by_chat.is_empty()
+            || !self.continuing_message_ids.is_empty()
+        {
+            self.set_status_message(
+                "Wait for running requests to finish before deleting all chats".to_string(),
+            );
+            return Ok(());
+        }
+
+        let deleted = self.database.delete_all_chats().await?;
+        self.search_query.clear();
+        self.search_textarea = EditorState::default();
+        self.chat_history.clear();
+        self.chat_history_exhausted = true;
+        self.bookmarks.clear();
+        self.bookmarked_message_ids.clear();
+        self.create_new_chat().await?;
+        self.set_status_message(format!(
+            "Deleted {} chat{}",
+            deleted,
+            if deleted == 1 { "" } else { "s" }
+        ));
+        Ok(())
+    }
+
+    pub fn has_unsaved_draft(&self) -> bool {
+        // an auto-saved draft gets saved once more on quit and comes back on the next start
+        !self.autosaves_prompt_draft() && !editor_state_to_string(&self.textarea).trim().is_empty()
+    }
+
+    fn autosaves_prompt_draft(&self) -> bool {
+        self.settings.draft_autosave_interval_secs > 0 && !self.read_only
+    }
+
+    /// Save the prompt draft if it changed and the auto-save interval has passed since the last
+    /// save, so typing doesn't write to the db on every keystroke. `force` skips the wait
+    async fn autosave_prompt_draft(&mut self, force: bool) {
+        let interval = Duration::from_secs(self.settings.draft_autosave_interval_secs);
+        if !self.autosaves_prompt_draft()
+            || (!force && self.last_draft_autosave.elapsed() < interval)
+        {
+            return;
+        }
+
+        let mut draft = editor_state_to_string(&self.textarea);
+        if draft.trim().is_empty() {
+            draft.clear();
+        }
+        if draft == self.saved_prompt_draft {
+            return;
+        }
+
+        self.last_draft_autosave = Instant::now();
+        match self.database.save_prompt_draft(&draft).await {
+            Ok(()) => self.saved_prompt_draft = draft,
+            Err(e) => error!("Couldn't save the prompt draft: {:?}", e),
+        }
+    }
+
+    async fn handle_quit_confirmation_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                self.should_quit = true;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Add a prompt to the ones Up can recall, unless it repeats the last one. The oldest is
+    /// forgotten past PROMPT_HISTORY_CAPACITY. Any recall in progress is ended
+    fn remember_prompt(&mut self, prompt: String) {
+        self.prompt_history_index = None;
+        if self.prompt_history.back() == Some(&prompt) {
+            return;
+        }
+        if self.prompt_history.len() == PROMPT_HISTORY_CAPACITY {
+            self.prompt_history.pop_front();
+        }
+        self.prompt_history.push_back(prompt);
+    }
+
+    /// Shell style recall of earlier prompts into the editor. Starts from an empty prompt and
+    /// keeps going as long as the recalled prompt hasn't been edited. Returns whether the key was
+    /// used, otherwise it goes on to the editor or navigation as usual
+    fn recall_prompt(&mut self, older: bool) -> bool {
+        let text = editor_state_to_string(&self.textarea);
+        let recalling = self
+            .prompt_history_index
+            .filter(|idx| self.prompt_history.get(*idx) == Some(&text));
+        let next_idx = match (recalling, older) {
+            (None, true) if text.trim().is_empty() => self.prompt_history.len().checked_sub(1),
+            (None, _) => None,
+            (Some(idx), true) => Some(idx.saturating_sub(1)),
+            (Some(idx), false) => {
+                // going past the newest prompt leaves an empty prompt again
+                if idx + 1 == self.prompt_history.len() {
+                    set_editor_state_text(&mut self.textarea, String::new());
+                    self.textarea.mode = EditorMode::Insert;
+                    self.prompt_history_index = None;
+                    return true;
+                }
+                Some(idx + 1)
+            }
+        };
+        let Some(next_idx) = next_idx else {
+            self.prompt_history_index = None;
+            return false;
+        };
+
+        let prompt = self.prompt_history[next_idx].clone();
+        set_editor_state_text(&mut self.textarea, prompt);
+        // leave the cursor at the end, ready to be edited or sent
+        self.textarea.mode = EditorMode::Insert;
+        let last_row = self.textarea.lines.len().saturating_sub(1);
+        let last_col = self.textarea.lines.len_col(last_row).unwrap_or(0);
+        self.textarea.cursor = Index2::new(last_row, last_col);
+        self.prompt_history_index = Some(next_idx);
+        true
+    }
+
+    /// Show the next or previous candidate of the current model's response at the selected message,
+    /// or right under it since a followed response leaves its prompt selected. The shown candidate
+    /// is saved right away
+    async fn cycle_response_candidate(&mut self, forward: bool) -> Result<()> {
+        let Some(&model_id) = self.current_chat_profile.model_ids.get(self.current_model_idx) else {
+            return Ok(());
+        };
+        let msg_idx = self.current_message_index.get(&model_id).copied().unwrap_or(0);
+        let Some(message) = self.current_messages.get_mut(&model_id).and_then(|messages| {
+            messages
+                .iter_mut()
+                .skip(msg_idx)
+                .take(2)
+                .find(|message| self.response_candidates.contains_key(&message.id))
+        }) else {
+            self.set_status_message("This response has no other candidates".to_string());
+            return Ok(());
+        };
+
+        let Some((candidates, shown)) = self.response_candidates.get_mut(&message.id) else {
+            return Ok(());
+        };
+        *shown = if forward {
+            (*shown + 1) % candidates.len()
+        } else {
+            (*shown + candidates.len() - 1) % candidates.len()
+        };
+        message.content = Some(candidates[*shown].clone());
+        let status = format!("Candidate {} of {}", *shown + 1, candidates.len());
+        let message = message.clone();
+
+        // in read-only mode the choice only lasts for the session
+        if !self.read_only {
+            self.database.complete_chat_message(message.id, &message).await?;
+        }
+        self.set_status_message(status);
+        Ok(())
+    }
+
+    /// Add the selected message to the set of ids `message_ids` picks out, or take it out again.
+    /// That changes how many items the message renders as, which the selection counts in, so the
+    /// view moves to the message's start and selects it there, ready to be toggled back
+    fn toggle_selected_message_in(&mut self, message_ids: fn(&mut Self) -> &mut HashSet<i64>) {
+        let Some(&model_id) = self.current_chat_profile.model_ids.get(self.current_model_idx) else {
+            return;
+        };
+        let Some((msg_idx, message_id, message_count)) =
+            self.current_selected_message_index.and_then(|msg_idx| {
+                let messages = self.get_current_messages()?;
+                Some((msg_idx, messages.get(msg_idx)?.id, messages.len()))
+            })
+        else {
+            return;
+        };
+
+        let message_ids = message_ids(self);
+        if !message_ids.remove(&message_id) {
+            message_ids.insert(message_id);
+        }
+        self.current_message_index.insert(model_id, msg_idx);
+        self.current_chunk_idx.insert(model_id, 0);
+        self.chat_item_selections.insert(model_id, Some(0));
+        if msg_idx + 1 == message_count {
+            self.following_latest.insert(model_id);
+        } else {
+            self.following_latest.remove(&model_id);
+        }
+    }
+
+    /// Put the prompt editor in insert mode from whatever state it was left in. Half typed commands
+    /// (a pending c or g, a count, a visual selection) are dropped so they can't fire later
+    fn enter_prompt_insert_mode(&mut self) {
+        self.textarea.mode = EditorMode::Insert;
+        self.textarea.selection = None;
+        self.editor_event_handler = EditorEventHandler::default();
+        self.numeric_prefix = None;
+        // handle_key_event only updates these outside insert mode, so they'd otherwise go stale
+        self.last_key_press = None;
+        self.clear_last_key_press = false;
+    }
+
+    async fn create_new_chat(&mut self) -> Result<()> {
+        let model_ids = self.default_profile.model_ids.clone();
+        self.create_new_chat_with_models(model_ids).await
+    }
+
+    async fn create_new_chat_with_models(&mut self, model_ids: Vec<i64>) -> Result<()> {
+        let new_chat = Chat {
+            id: 0,
+            dt: chrono::Utc::now().timestamp(),
+            title: None,
+            archived: false,
+            json_mode: false,
+            stop_sequences: None,
+            system_prompt: None,
+        };
+        self.current_chat = new_chat.clone(); // this will be created when the first message is submitted
+        self.current_messages.clear();
+        self.older_messages_before_dt = None;
+        self.response_candidates.clear();
+        self.state = AppState::Normal;
+        self.current_chat_profile = ChatProfile {
+            model_ids,
+            ..self.default_profile.clone()
+        };
+        self.current_model_idx = 0;
+
+        // Initialize navigation state and item selections for all models in current chat profile
+        self.current_message_index.clear();
+        self.current_chunk_idx.clear();
+        self.chat_item_selections.clear();
+        self.horizontal_offset_by_model.clear();
+        self.following_latest.clear();
+        self.unified_scroll = 0;
+        self.prompt_history.clear();
+        self.prompt_history_index = None;
+        for &model_id in &self.current_chat_profile.model_ids {
+            self.current_message_index.insert(model_id, 0);
+            self.current_chunk_idx.insert(model_id, 0);
+            self.chat_item_selections.insert(model_id, None);
+        }
+
+        // this doesnt do a db insert, that wont happen until the first message is submitted
+        self.chat_history.insert(0, new_chat);
+        self.chat_history_visual_start = None;
+        self.chat_history_index = 0;
+        self.enter_prompt_insert_mode();
+
+        Ok(())
+    }
+
+    fn removes_think_tokens(&self, model: &Model) -> bool {
+        let provider_name = self
+            .provider_names
+            .get(&model.provider_id)
+            .map(String::as_str)
+            .unwrap_or_default();
+        self.settings.hides_think_tokens(provider_name, &model.model)
+    }
+
+    /// The model and client from the title_model setting, None if it isn't set or the model isn't available
+    fn configured_title_model(&self) -> Option<(Model, Arc<dyn ProviderClient>)> {
+        let (provider_name, model_name) = self.settings.title_model.as_ref()?;
+        let model = self.available_models.values().find(|model| {
+            &model.model == model_name
+                && self.provider_names.get(&model.provider_id) == Some(provider_name)
+        });
+        let Some(model) = model else {
+            info!(
+                "Title model {}/{} isn't available, using the chat's first model",
+                provider_name, model_name
+            );
+            return None;
+        };
+        let provider_client = self.provider_clients.get(&model.provider_id)?.clone();
+        Some((model.clone(), provider_client))
+    }
+
+    /// The models from the fallback_models setting a failed request to `model` is retried on, in
+    /// order, with whether each one has its think tokens removed. When the model is in the list
+    /// itself only the ones after it are tried, so the chain doesn't loop back. Unavailable ones
+    /// are skipped
+    fn configured_fallback_models(
+        &self,
+        model: &Model,
+    ) -> Vec<(Model, Arc<dyn ProviderClient>, bool)> {
+        let provider_name = self.provider_names.get(&model.provider_id);
+        let chain = &self.settings.fallback_models;
+        let start = chain
+            .iter()
+            .position(|(provider, name)| Some(provider) == provider_name && name == &model.model)
+            .map_or(0, |idx| idx + 1);
+        chain[start..]
+            .iter()
+            .filter_map(|(provider_name, model_name)| {
+                let fallback = self.available_models.values().find(|fallback| {
+                    &fallback.model == model_name
+                        && self.provider_names.get(&fallback.provider_id) == Some(provider_name)
+                });
+                let Some(fallback) = fallback else {
+                    info!(
+                        "Fallback model {}/{} isn't available, skipping it",
+                        provider_name, model_name
+                    );
+                    return None;
+                };
+                if fallback.id == model.id {
+                    return None;
+                }
+                let provider_client = self.provider_clients.get(&fallback.provider_id)?.clone();
+                Some((fallback.clone(), provider_client, self.removes_think_tokens(fallback)))
+            })
+            .collect()
+    }
+
+    /// With wrapping off and a message selected, h/l pan the view instead of switching models
+    fn is_panning_messages(&self) -> bool {
+        !self.wrap_messages
+            && self
+                .current_chat_profile
+                .model_ids
+                .get(self.current_model_idx)
+                .and_then(|model_id| self.chat_item_selections.get(model_id))
+                .is_some_and(|selection| selection.is_some())
+    }
+
+    /// Move the current model's horizontal offset by `steps`, never past the longest line
+    fn pan_messages(&mut self, steps: isize) {
+        let Some(&model_id) = self
+            .current_chat_profile
+            .model_ids
+            .get(self.current_model_idx)
+        else {
+            return;
+        };
+        let longest_line = self
+            .current_messages
+            .get(&model_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|message| message.content.as_deref())
+            .flat_map(|content| content.lines())
+            .map(|line| line.width())
+            .max()
+            .unwrap_or(0);
+        let offset = self.horizontal_offset_by_model.entry(model_id).or_insert(0);
+        let delta = steps.unsigned_abs() * HORIZONTAL_SCROLL_STEP;
+        *offset = if steps < 0 {
+            offset.saturating_sub(delta)
+        } else {
+            (*offset + delta).min(longest_line.saturating_sub(HORIZONTAL_SCROLL_STEP))
+        };
+    }
+
+    async fn duplicate_current_chat(&mut self) -> Result<()> {
+        let duplicate = self.database.duplicate_chat(self.current_chat.id).await?;
+        self.chat_history.insert(0, duplicate);
+        self.chat_history_index = 0;
+        self.load_selected_chat().await?;
+        self.set_status_message("Duplicated chat".to_string());
+        Ok(())
+    }
+
+    /// Write the current chat, its models and all of its messages to <data dir>/exports/<id>.json
+    async fn export_current_chat_json(&self) -> Result<std::path::PathBuf> {
+        let chat_id = self.current_chat.id;
+        let model_ids = self.database.get_chat_models_ids(chat_id).await?;
+        let models: Vec<Model> = model_ids
+            .iter()
+            .filter_map(|model_id| self.all_models.get(model_id).cloned())
+            .collect();
+        let provider_names = models
+            .iter()
+            .filter_map(|model| {
+                let name = self.provider_names.get(&model.provider_id)?;
+                Some((model.provider_id, name.clone()))
+            })
+            .collect();
+        let export = ChatExport {
+            chat: self.current_chat.clone(),
+            models,
+            provider_names,
+            messages: self.database.get_chat_messages(chat_id).await?,
+            exported_dt: chrono::Utc::now().timestamp(),
+        };
+
+        let export_dir = self.data_dir.join("exports");
+        std::fs::create_dir_all(&export_dir)?;
+        let path = export_dir.join(format!("{}.json", chat_id));
+        std::fs::write(&path, serde_json::to_string_pretty(&export)?)?;
+        info!("Exported chat {} to {}", chat_id, path.display());
+        Ok(path)
+    }
+
+    async fn load_selected_chat(&mut self) -> Result<()> {
+        if let Some(chat) = self.chat_history.get(self.chat_history_index) {
+            self.current_chat = chat.clone();
+            self.current_messages.clear();
+            self.older_messages_before_dt = None;
+            self.response_candidates.clear();
+
+            if chat.id != 0 {
+                // these can be done concurrently, but does this actually provide a speedup?
+                let (model_ids, tool_ids) = tokio::join!(
+                    self.database.get_chat_models_ids(chat.id),
+                    self.database.get_chat_tool_ids(chat.id)
                 );
                 let model_ids = model_ids?;
                 let tool_ids = tool_ids?;
-                let mut all_chat_messages = self.database.get_chat_messages(chat.id).await?;
-                for model_id in &model_ids {
-                    let mut model_messages = Vec::new();
-                    // this loop belongs in a museum, but we need to do it this way for optimal efficiency
-                    let mut idx = 0;
-                    while idx < all_chat_messages.len() {
-                        let curr_message = &all_chat_messages[idx];
-                        if let Some(curr_model_id) = curr_message.model_id
-                            && &curr_model_id == model_id
-                        {
-                            model_messages.push(all_chat_messages.remove(idx));
-                        } else if curr_message.model_id.is_none() {
-                            model_messages.push(curr_message.clone());
-                            idx += 1;
-                        } else {
-                            idx += 1;
-                        }
-                    }
-                    self.current_messages.insert(*model_id, model_messages);
-                }
+                // long chats only load their latest prompts, older ones come in when scrolled to
+                let page_size = self.settings.chat_message_page_size;
+                let chat_messages = self
+                    .database
+                    .get_chat_messages_paged(chat.id, None, page_size as i64)
+                    .await?;
+                self.older_messages_before_dt = older_page_before_dt(&chat_messages, page_size);
+                self.current_messages = self.split_messages_by_model(chat_messages, &model_ids);
 
                 self.current_chat_profile = ChatProfile {
                     chat_id: chat.id,
@@ -1082,29 +2686,199 @@ impl App {
             // Initialize navigation state and item selections for all models in current chat profile
             self.current_message_index.clear();
             self.current_chunk_idx.clear();
-            self.current_message_chunks_length.clear();
             self.chat_item_selections.clear();
+            self.horizontal_offset_by_model.clear();
+            self.following_latest.clear();
+            self.unified_scroll = 0;
+            self.prompt_history.clear();
+            self.prompt_history_index = None;
+            // long chats only have their latest page loaded, recall reaches back past it
+            if self.current_chat.id != 0 {
+                let prompts = self
+                    .database
+                    .get_chat_prompts(self.current_chat.id, PROMPT_HISTORY_CAPACITY as i64)
+                    .await?;
+                for prompt in prompts {
+                    self.remember_prompt(prompt);
+                }
+            }
             for &model_id in &self.current_chat_profile.model_ids {
                 self.current_message_index.insert(model_id, 0);
                 self.current_chunk_idx.insert(model_id, 0);
-                self.current_message_chunks_length.insert(model_id, 1);
                 self.chat_item_selections.insert(model_id, None);
             }
         }
         Ok(())
     }
 
+    /// Split a chat's messages into what each model sees: every prompt, but only its own responses
+    fn split_messages_by_model(
+        &self,
+        mut all_chat_messages: Vec<ChatMessage>,
+        model_ids: &[i64],
+    ) -> HashMap<i64, Vec<ChatMessage>> {
+        let mut messages_by_model = HashMap::new();
+        for model_id in model_ids {
+            let mut model_messages = Vec::new();
+            let mut last_user_message_id = None;
+            // this loop belongs in a museum, but we need to do it this way for optimal efficiency
+            let mut idx = 0;
+            while idx < all_chat_messages.len() {
+                let curr_message = &all_chat_messages[idx];
+                if let Some(curr_model_id) = curr_message.model_id
+                    && &curr_model_id == model_id
+                {
+                    let message = all_chat_messages.remove(idx);
+                    // a placeholder for a request that's still running gets replaced when it completes
+                    let still_running = message.incomplete
+                        && last_user_message_id.is_some_and(|user_message_id| {
+                            self.is_message_loading(*model_id, user_message_id)
+                        });
+                    if !still_running {
+                        model_messages.push(message);
+                    }
+                } else if curr_message.model_id.is_none() {
+                    last_user_message_id = Some(curr_message.id);
+                    model_messages.push(curr_message.clone());
+                    idx += 1;
+                } else {
+                    idx += 1;
+                }
+            }
+            messages_by_model.insert(*model_id, model_messages);
+        }
+        messages_by_model
+    }
+
+    /// Load the page before the loaded messages of a long chat. Returns whether there was one
+    async fn load_older_messages(&mut self) -> Result<bool> {
+        let Some(before_dt) = self.older_messages_before_dt else {
+            return Ok(false);
+        };
+        let page_size = self.settings.chat_message_page_size;
+        let older_messages = self
+            .database
+            .get_chat_messages_paged(self.current_chat.id, Some(before_dt), page_size as i64)
+            .await?;
+        self.older_messages_before_dt = older_page_before_dt(&older_messages, page_size);
+        if older_messages.is_empty() {
+            return Ok(false);
+        }
+
+        let model_ids = self.current_chat_profile.model_ids.clone();
+        let mut older_by_model = self.split_messages_by_model(older_messages, &model_ids);
+        for model_id in model_ids {
+            let older = older_by_model.remove(&model_id).unwrap_or_default();
+            // the view stays on the message it was on, which moved down
+            if let Some(current_idx) = self.current_message_index.get_mut(&model_id) {
+                *current_idx += older.len();
+            }
+            if let Some(selection) = self.chat_item_selections.get_mut(&model_id) {
+                *selection = None;
+            }
+            if let Some(messages) = self.current_messages.get_mut(&model_id) {
+                messages.splice(0..0, older);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Load older pages until the current model's view can move `steps` messages up, or there
+    /// are none left
+    async fn load_older_messages_within(&mut self, steps: usize) -> Result<()> {
+        while let Some(model_id) = self.current_chat_profile.model_ids.get(self.current_model_idx)
+            && self.current_message_index.get(model_id).copied().unwrap_or(0) < steps
+            && self.load_older_messages().await?
+        {}
+        Ok(())
+    }
+
+    /// A model's loaded messages with everything before them in a long chat, which is what gets
+    /// sent to the model
+    async fn with_unloaded_messages(
+        &self,
+        model_id: i64,
+        loaded: Vec<ChatMessage>,
+    ) -> Result<Vec<ChatMessage>> {
+        let Some(before_dt) = self.older_messages_before_dt else {
+            return Ok(loaded);
+        };
+        let older_messages = self
+            .database
+            .get_chat_messages_paged(self.current_chat.id, Some(before_dt), i64::MAX)
+            .await?;
+        let mut conversation = self
+            .split_messages_by_model(older_messages, &[model_id])
+            .remove(&model_id)
+            .unwrap_or_default();
+        conversation.extend(loaded);
+        Ok(conversation)
+    }
+
     #[instrument(skip_all)]
     async fn submit_message(&mut self) -> Result<()> {
+        self.submit_message_to_models(false).await
+    }
+
+    /// Add the prompt to the chat as an aside, a note every model sees with the next prompt that
+    /// doesn't get a response of its own
+    async fn add_aside(&mut self) -> Result<()> {
+        let content = editor_state_to_string(&self.textarea);
+        if content.trim().is_empty() || self.refuse_if_read_only("Adding asides") {
+            return Ok(());
+        }
+        if self.current_chat.id == 0 {
+            self.set_status_message("Send a prompt before adding an aside".to_string());
+            return Ok(());
+        }
+
+        let mut aside = ChatMessage::new_aside_message(self.current_chat.id, content);
+        aside.id = self.database.add_chat_message(&aside).await?;
+        for (model_id, messages) in self.current_messages.iter_mut() {
+            messages.push(aside.clone());
+            self.following_latest.insert(*model_id);
+            if let Some(current_idx) = self.current_message_index.get_mut(model_id) {
+                *current_idx = messages.len() - 1;
+                if let Some(current_chunk_idx) = self.current_chunk_idx.get_mut(model_id) {
+                    *current_chunk_idx = 0;
+                }
+            }
+        }
+
+        self.prompt_preview = false;
+        if self.settings.keep_prompt_after_submit {
+            self.enter_prompt_insert_mode();
+        } else {
+            self.textarea = EditorState::default();
+        }
+        self.set_status_message("Aside added, the models see it with the next prompt".to_string());
+
+        Ok(())
+    }
+
+    /// Whether at least one of the current chat's models can be sent to
+    pub fn has_available_chat_model(&self) -> bool {
+        self.current_chat_profile
+            .model_ids
+            .iter()
+            .any(|model_id| self.available_models.contains_key(model_id))
+    }
+
+    /// Send the prompt to the chat's models. Unless `skip_unavailable` is set, any unavailable
+    /// model stops the send and opens the unavailable models dialog. With it set, unavailable
+    /// models get an error reply saying they were skipped and the rest are sent the prompt as usual
+    async fn submit_message_to_models(&mut self, skip_unavailable: bool) -> Result<()> {
         let content = editor_state_to_string(&self.textarea);
-        if content.trim().is_empty() {
+        if content.trim().is_empty() || self.refuse_if_read_only("Sending messages") {
             return Ok(());
         }
 
         // Check if all models in the chat profile are available
         let mut unavailable_models = Vec::new();
+        let mut unavailable_model_ids = HashSet::new();
         for &model_id in &self.current_chat_profile.model_ids {
             if !self.available_models.contains_key(&model_id) {
+                unavailable_model_ids.insert(model_id);
                 // Model is not available, get model info
                 if let Some(model) = self.all_models.get(&model_id) {
                     let provider_name = self
@@ -1112,7 +2886,12 @@ impl App {
                         .get(&model.provider_id)
                         .cloned()
                         .unwrap_or_else(|| "Unknown Provider".to_string());
-                    unavailable_models.push((model.model.clone(), provider_name));
+                    let model_name = if model.deprecated {
+                        format!("{} (removed)", model.display_name())
+                    } else {
+                        model.display_name().to_string()
+                    };
+                    unavailable_models.push((model_name, provider_name));
                 } else {
                     unavailable_models.push((
                         format!("Unknown Model (ID: {})", model_id),
@@ -1123,18 +2902,36 @@ impl App {
         }
 
         // If there are unavailable models, show error dialog
-        if !unavailable_models.is_empty() {
+        if !unavailable_models.is_empty()
+            && (!skip_unavailable || !self.has_available_chat_model())
+        {
             self.unavailable_models_info = unavailable_models;
             self.state = AppState::UnavailableModelsError;
             return Ok(());
         }
 
+        // whichever candidate is shown has been picked
+        self.response_candidates.clear();
+
         let (chat_id, generate_title) = if self.current_chat.id != 0 {
             (self.current_chat.id, false)
         } else {
             // this is the first message of the chat, so we need to create one
             let chat_id = self.database.create_chat(None).await?;
             self.current_chat.id = chat_id;
+            if self.current_chat.json_mode {
+                self.database.set_chat_json_mode(chat_id, true).await?;
+            }
+            if self.current_chat.stop_sequences.is_some() {
+                self.database
+                    .set_chat_stop_sequences(chat_id, self.current_chat.stop_sequences.clone())
+                    .await?;
+            }
+            if self.current_chat.system_prompt.is_some() {
+                self.database
+                    .set_chat_system_prompt(chat_id, self.current_chat.system_prompt.clone())
+                    .await?;
+            }
             // we also need to update the element in chat history
             self.chat_history[self.chat_history_index].id = chat_id;
 
@@ -1157,6 +2954,7 @@ impl App {
             (chat_id, true)
         };
 
+        self.remember_prompt(content.clone());
         let mut user_message = ChatMessage::new_user_message(chat_id, content.clone());
         // write the user message to the database here because we only need to do this once
         let user_message_id = self.database.add_chat_message(&user_message).await?;
@@ -1164,10 +2962,12 @@ impl App {
         // Update the message with the actual ID from the database
         user_message.id = user_message_id;
 
+        // skipped models can't generate the title, it falls to the first one that's sent the prompt
         let model_id_for_title_compute = self
             .current_chat_profile
             .model_ids
-            .get(0)
+            .iter()
+            .find(|model_id| !unavailable_model_ids.contains(model_id))
             .cloned()
             .ok_or(anyhow::anyhow!("No model id found for title computation"))?;
 
@@ -1179,9 +2979,10 @@ impl App {
             messages.push(user_message.clone());
             // auto scroll the user to the message they just submitted
             // for all models
-            if let Some(current_idx) = self.current_message_index.get_mut(&model_id) {
+            self.following_latest.insert(*model_id);
+            if let Some(current_idx) = self.current_message_index.get_mut(model_id) {
                 *current_idx = messages.len() - 1;
-                if let Some(current_chunk_idx) = self.current_chunk_idx.get_mut(&model_id) {
+                if let Some(current_chunk_idx) = self.current_chunk_idx.get_mut(model_id) {
                     *current_chunk_idx = 0;
                 }
             }
@@ -1190,22 +2991,51 @@ impl App {
         // todo maybe eliminate this clone? might not be possible
         let curr_messages = self.current_messages.clone();
         for (model_id, messages) in curr_messages.iter() {
+            if unavailable_model_ids.contains(model_id) {
+                let error = match self.all_models.get(model_id) {
+                    Some(model) if model.deprecated => {
+                        format!("Skipped, {} is no longer offered by its provider", model.model)
+                    }
+                    Some(model) => {
+                        format!("Skipped, {} was unavailable when this was sent", model.model)
+                    }
+                    None => format!("Skipped, model id {} doesn't exist anymore", model_id),
+                };
+                let mut skipped_message = ChatMessage::new_assistant_message_with_error(
+                    chat_id,
+                    *model_id,
+                    error,
+                    user_message.dt,
+                );
+                skipped_message.id = self.database.add_chat_message(&skipped_message).await?;
+                if let Some(messages) = self.current_messages.get_mut(model_id) {
+                    messages.push(skipped_message);
+                }
+                continue;
+            }
+            // in cases where there is already a joinhandle, we actually only need the most recent message
+            // instead of cloning the entire conversation. this is an area of future optimization
+            let conversation = self.with_unloaded_messages(*model_id, messages.clone()).await?;
             // these could be done concurrently, but the task spawning shouldnt take long enough to warrant that
             info!("Spawning inference task for model id: {}", model_id);
             self.spawn_inference_task(
-                model_id.clone(),
+                *model_id,
                 user_message_id,
                 user_message.dt,
                 chat_id,
-                // in cases where there is already a joinhandle, we actually only need the most recent message
-                // instead of cloning the entire conversation. this is an area of future optimization
-                messages.clone(), 
+                conversation,
                 model_id == &model_id_for_title_compute && generate_title, // only generate title if chat is new and with the first model
             )
             .await;
         }
 
-        self.textarea = EditorState::default();
+        self.prompt_preview = false;
+        if self.settings.keep_prompt_after_submit {
+            // keep the text around to tweak and send again
+            self.enter_prompt_insert_mode();
+        } else {
+            self.textarea = EditorState::default();
+        }
         self.state = AppState::Normal;
 
         Ok(())
@@ -1214,15 +3044,123 @@ impl App {
     #[tracing::instrument(skip_all)]
     async fn handle_inference_event(&mut self, event: InferenceEvent) -> Result<()> {
         match event {
-            InferenceEvent::InferenceComplete {
-                chat_id,
-                model_id,
-                origin_message_id,
-                result,
+            InferenceEvent::ModelsRefreshed {
+                models,
+                added,
+                removed,
+                answered_provider_ids,
+                failed_provider_ids,
+                recheck_only,
             } => {
-                // Remove the completed join handle
+                self.model_refresh_provider_count = None;
+                let mut recovered_names: Vec<&str> = self
+                    .providers_marked_down
+                    .iter()
+                    .filter(|id| answered_provider_ids.contains(id))
+                    .filter_map(|id| self.provider_names.get(id).map(String::as_str))
+                    .collect();
+                recovered_names.sort();
+                let recovered_message = (!recovered_names.is_empty())
+                    .then(|| format!("Back online: {}", recovered_names.join(", ")));
+                self.providers_marked_down
+                    .retain(|id| !answered_provider_ids.contains(id));
+                self.providers_marked_down.extend(failed_provider_ids.iter().copied());
+                // a provider without a models endpoint can't be checked, so a re-check only gives it
+                // another try without announcing it as back online
+                if recheck_only {
+                    let providers = &self.providers;
+                    self.providers_marked_down.retain(|id| {
+                        !providers.get(id).is_some_and(|provider| provider.no_models_endpoint)
+                    });
+                }
+                // a provider that answered the sync gets a clean slate. the ones that weren't asked
+                // keep their streak, a re-check only covers those marked down
+                self.provider_failure_streaks
+                    .retain(|provider_id, _| !answered_provider_ids.contains(provider_id));
+                // an empty list means the reload failed, keep what we have rather than dropping every model
+                if !models.is_empty() {
+                    let (available_models, all_models) = index_models(
+                        models,
+                        &self.providers,
+                        &self.provider_api_keys_set,
+                        &self.providers_marked_down,
+                    );
+                    self.available_models = available_models;
+                    // keep names around for models that disappeared so existing chats can still show them
+                    self.all_models.extend(all_models);
+                }
+
+                // background re-checks only speak up when a provider comes back
+                if recheck_only {
+                    if let Some(recovered_message) = recovered_message {
+                        self.set_status_message(recovered_message);
+                    }
+                    return Ok(());
+                }
+
+                let mut message = format!("Models refreshed: {} added, {} removed", added, removed);
+                if !failed_provider_ids.is_empty() {
+                    let failed_names: Vec<&str> = failed_provider_ids
+                        .iter()
+                        .filter_map(|id| self.provider_names.get(id).map(String::as_str))
+                        .collect();
+                    message.push_str(&format!(", unreachable: {}", failed_names.join(", ")));
+                }
+                self.set_status_message(message);
+            }
+            InferenceEvent::InferenceStarted {
+                model_id,
+                origin_message_id,
+                truncated_message_count,
+            } => {
+                self.inference_running_by_message_and_model
+                    .insert((origin_message_id, model_id), Instant::now());
+                if truncated_message_count > 0 {
+                    self.context_truncated_by_message_and_model
+                        .insert((origin_message_id, model_id), truncated_message_count);
+                }
+            }
+            InferenceEvent::ContinuationComplete {
+                chat_id,
+                model_id,
+                message,
+                error,
+            } => {
+                self.continuing_message_ids.remove(&message.id);
+                self.record_provider_result(model_id, error.is_some());
+                self.warn_if_near_rate_limit(model_id);
+                if let Some(error) = error {
+                    self.set_status_message(format!("Couldn't continue the response: {}", error));
+                } else if chat_id == self.current_chat.id
+                    && let Some(existing) = self
+                        .current_messages
+                        .get_mut(&model_id)
+                        .and_then(|messages| messages.iter_mut().find(|m| m.id == message.id))
+                {
+                    // the next prompt sends this copy rather than the cut off one its queued
+                    // request hands on
+                    *existing = *message;
+                }
+            }
+            InferenceEvent::InferenceComplete {
+                chat_id,
+                model_id,
+                origin_message_id,
+                result,
+                candidates,
+                request_failed,
+                fallback_results,
+            } => {
+                // Remove the completed join handle
                 self.inference_in_progress_by_message_and_model
                     .remove(&(origin_message_id, model_id));
+                self.inference_running_by_message_and_model
+                    .remove(&(origin_message_id, model_id));
+                self.record_provider_result(model_id, request_failed);
+                self.warn_if_near_rate_limit(model_id);
+                for (fallback_model_id, fallback_failed) in fallback_results {
+                    self.record_provider_result(fallback_model_id, fallback_failed);
+                }
 
                 // This serves only to update the messages in memory for the current chat
                 // The DB writes were already done by the tokio task that did the infernece
@@ -1232,7 +3170,7 @@ impl App {
                     let messages = self
                         .current_messages
                         .entry(model_id)
-                        .or_insert_with(Vec::new); // this should never be necesary
+                        .or_default(); // this should never be necesary
 
                     // this is O(n) so we are banking on chats being relatively small.
                     // with chats less than 100 messages, it is probably faster than a map lookup approach
@@ -1250,7 +3188,10 @@ impl App {
                         messages.len()
                     };
 
-                    messages.insert(insert_idx, result);
+                    if !candidates.is_empty() && result.id != 0 {
+                        self.response_candidates.insert(result.id, (candidates, 0));
+                    }
+                    messages.insert(insert_idx, *result);
 
                     // if the current message index <= the insert position, we need to increment it so
                     // the user isn't suddenly taken to a different message. This would only happen
@@ -1258,15 +3199,28 @@ impl App {
                     if let Some(curr_index) = self.current_message_index.get_mut(&model_id)
                         && *curr_index >= insert_idx
                     {
-                        *curr_index = *curr_index + 1;
+                        *curr_index += 1;
+                    }
+
+                    // when following, show the newest response under the message it answers
+                    if self.following_latest.contains(&model_id)
+                        && insert_idx + 1 == messages.len()
+                    {
+                        self.current_message_index.insert(model_id, insert_idx.saturating_sub(1));
+                        self.current_chunk_idx.insert(model_id, 0);
                     }
                 }
             }
             InferenceEvent::TitleInferenceComplete { chat_id, title } => {
                 info!(
-                    "Title inference completed for chat id: {}, title: {}",
+                    "Title inference completed for chat id: {}, title: {:?}",
                     chat_id, title
                 );
+                let Some(title) = title.as_deref().and_then(sanitize_title) else {
+                    // leave the chat untitled rather than saving an empty one
+                    self.title_inference_in_progress_by_chat.remove(&chat_id);
+                    return Ok(());
+                };
                 // TODO make this more efficient
                 for chat in &mut self.chat_history {
                     if chat.id == chat_id {
@@ -1293,8 +3247,64 @@ impl App {
 
     pub fn is_message_loading(&self, model_id: i64, message_id: i64) -> bool {
         self.inference_in_progress_by_message_and_model
+            .contains(&(message_id, model_id))
+    }
+
+    /// True once a loading message has acquired a semaphore permit, false while it is still queued
+    pub fn is_message_running(&self, model_id: i64, message_id: i64) -> bool {
+        self.inference_running_by_message_and_model
+            .contains_key(&(message_id, model_id))
+    }
+
+    /// What a loading message is waiting on, shown in place of its response. Responses arrive
+    /// whole, so a running request only says how long it has been waiting
+    pub fn loading_status(&self, model_id: i64, message_id: i64) -> String {
+        match self
+            .inference_running_by_message_and_model
+            .get(&(message_id, model_id))
+        {
+            Some(started) => format!(
+                "{} waiting for response {}",
+                self.get_spinner_char(),
+                format_elapsed(started.elapsed())
+            ),
+            None => "queued".to_string(),
+        }
+    }
+
+    /// How many older messages were left out of the request for this message to fit the model's context window
+    pub fn context_truncated_count(&self, model_id: i64, message_id: i64) -> Option<usize> {
+        self.context_truncated_by_message_and_model
             .get(&(message_id, model_id))
-            .is_some()
+            .copied()
+    }
+
+    /// Every model's messages merged for the unified view: each prompt followed by each model's
+    /// responses to it, in profile order
+    pub fn unified_messages(&self) -> Vec<&ChatMessage> {
+        let model_ids = &self.current_chat_profile.model_ids;
+        let mut merged = Vec::new();
+        // prompts are shared by every model, so any model's list has all of them
+        let Some(first_messages) = model_ids.first().and_then(|id| self.current_messages.get(id))
+        else {
+            return merged;
+        };
+
+        for prompt in first_messages.iter().filter(|m| m.chat_role == ChatRole::User) {
+            merged.push(prompt);
+            for model_id in model_ids {
+                if let Some(messages) = self.current_messages.get(model_id)
+                    && let Some(prompt_idx) = messages.iter().position(|m| m.id == prompt.id)
+                {
+                    merged.extend(
+                        messages[prompt_idx + 1..]
+                            .iter()
+                            .take_while(|m| m.chat_role != ChatRole::User),
+                    );
+                }
+            }
+        }
+        merged
     }
 
     pub fn get_current_messages(&self) -> Option<&Vec<ChatMessage>> {
@@ -1320,14 +3330,9 @@ impl App {
         let tx = self.user_event_tx.clone();
 
         // if there's an existing handle for this chat/model combo, we need to wait for that to complete first
-        let prereq_handle = if let Some(handle) = self
+        let prereq_handle = self
             .inference_handles_by_chat_and_model
-            .remove(&(chat_id, model_id))
-        {
-            Some(handle)
-        } else {
-            None
-        };
+            .remove(&(chat_id, model_id));
 
         let model = match self.available_models.get(&model_id) {
             Some(model) => model.clone(), // Clone the model to avoid borrowing from self
@@ -1365,6 +3370,17 @@ impl App {
             }
         };
         let database = self.database.clone();
+        let semaphore = self.inference_semaphore.clone();
+        let wait_for_rate_limit_reset = self.settings.wait_for_rate_limit_reset;
+        let remove_think_tokens = self.removes_think_tokens(&model);
+        let request_log = self.settings.request_log;
+
+        // json mode is silently skipped for models that don't support it so the rest of the carousel still works
+        let json_mode = self.current_chat.json_mode && model.supports_json_mode;
+        let mut options = generation_options_for(json_mode, &self.current_chat);
+        let response_candidates = self.settings.response_candidates;
+        options.n = (response_candidates > 1).then_some(response_candidates as i64);
+        let system_prompt = system_prompt_for(&self.current_chat, json_mode);
 
         self.inference_in_progress_by_message_and_model
             .insert((user_message_id, model_id));
@@ -1372,6 +3388,9 @@ impl App {
         if generate_title {
             self.title_inference_in_progress_by_chat.insert(chat_id);
         }
+        let title_model = if generate_title { self.configured_title_model() } else { None };
+        let fallback_models = self.configured_fallback_models(&model);
+        let chat = self.current_chat.clone();
         // Spawn the inference task
         let handle = tokio::spawn(async move {
             // Wait for all existing tasks for this model to complete
@@ -1380,14 +3399,22 @@ impl App {
             {
                 match existing_handle.await {
                     Ok(mut joinhandle_conversation) => { 
-                        if let Some(recent_prompt_message) = conversation.into_iter().last() {
-                            // the joinhandle returns the conversation up to the most recent user message, we need to add it here
-                            joinhandle_conversation.push(recent_prompt_message);
-                            joinhandle_conversation
-                        } else {
-                            error!("couldnt get lat message of conversation, this should never happen");
-                            joinhandle_conversation
+                        // the joinhandle returns the conversation up to its own response, which
+                        // shares its prompt's dt. what was sent since goes after it, the new
+                        // prompt and any asides before it
+                        let answered_dt = joinhandle_conversation.last().map(|message| message.dt);
+                        let (sent_since, mut known): (Vec<_>, Vec<_>) = conversation
+                            .into_iter()
+                            .partition(|message| answered_dt.is_none_or(|dt| message.dt > dt));
+                        // responses can change after they arrive, another candidate picked or a cut
+                        // off one continued, so the app's copy of a message wins over the handle's
+                        for message in joinhandle_conversation.iter_mut().filter(|m| m.id != 0) {
+                            if let Some(idx) = known.iter().position(|known| known.id == message.id) {
+                                *message = known.swap_remove(idx);
+                            }
                         }
+                        joinhandle_conversation.extend(sent_since);
+                        joinhandle_conversation
                     },
                     Err(_) => {
                         // if the prerequisite handle fails, just ignore it because we cant get the prompt or prior conversation
@@ -1401,29 +3428,182 @@ impl App {
                 conversation
             };
 
-            let result = provider_client
-                .run(
-                    &model.model,
-                    "You are a helpful assistant.", // Default system prompt for now
-                    &current_conversation,
-                    vec![], // No tools for now
-                    false,  // Don't remove think tokens
-                )
-                .await
-                .map(|generation_result| {
-                    generation_result
-                        .content
-                        .unwrap_or_else(|| "No response generated".to_string())
-                })
-                .map_err(|e| anyhow::anyhow!("Inference failed: {}", e));
+            // only the request is truncated, the full conversation is still handed to the next turn
+            let mut request_conversation = current_conversation.clone();
+            let truncated_message_count =
+                model.truncate_to_context_window(&mut request_conversation, &system_prompt);
+            if truncated_message_count > 0 {
+                info!(
+                    "Dropped {} messages to fit the context window of model id: {}",
+                    truncated_message_count, model_id
+                );
+            }
+
+            // hold off until the provider's limit resets rather than send a request bound to get a 429
+            if wait_for_rate_limit_reset
+                && let Some(wait) = provider_client
+                    .rate_limit()
+                    .and_then(|rate_limit| rate_limit.wait_before_next_request())
+            {
+                info!("Waiting {:?} for the rate limit of model id {} to reset", wait, model_id);
+                tokio::time::sleep(wait).await;
+            }
+
+            // queue behind other in-flight requests if we're at the concurrency limit.
+            // the semaphore is never closed so acquiring can't fail
+            let permit = semaphore.clone().acquire_owned().await.ok();
+            let _ = tx.send(InferenceEvent::InferenceStarted {
+                model_id,
+                origin_message_id: user_message_id,
+                truncated_message_count,
+            });
+
+            // write a placeholder before the request goes out so a response that never finishes still leaves a trace
+            let mut placeholder =
+                ChatMessage::new_assistant_message(chat_id, model_id, String::new(), user_message_dt);
+            placeholder.content = None;
+            placeholder.incomplete = true;
+            let placeholder_id = match database.add_chat_message(&placeholder).await {
+                Ok(placeholder_id) => Some(placeholder_id),
+                Err(e) => {
+                    info!("Couldn't write placeholder message to database: {}", e);
+                    None
+                }
+            };
 
-            let new_assistant_message = match &result {
-                Ok(result_content) => ChatMessage::new_assistant_message(
+            let request_started = Instant::now();
+            let mut result = generate_response(
+                provider_client.as_ref(),
+                &model,
+                &system_prompt,
+                &request_conversation,
+                remove_think_tokens,
+                &options,
+            )
+            .await;
+            drop(permit);
+            if request_log {
+                let logged_result = result.as_ref().map_err(|e| e.to_string());
+                log_request(
+                    &database,
                     chat_id,
-                    model_id,
-                    result_content.clone(),
-                    user_message_dt,
-                ),
+                    &model,
+                    request_conversation.len(),
+                    request_started,
+                    logged_result,
+                )
+                .await;
+            }
+
+            // a failed or empty request goes down the fallback chain until one of the models
+            // answers. the response still belongs to this model's column, it only notes who
+            // answered. each provider's own outcome is kept for its failure streak
+            let request_failed = result.is_err();
+            let mut fallback_results = Vec::new();
+            let mut fallback_answered = None;
+            let mut failed_fallbacks = Vec::new();
+            for (fallback_model, fallback_client, fallback_remove_think_tokens) in fallback_models {
+                if has_reply(&result) {
+                    break;
+                }
+                info!(
+                    "Model id {} failed, falling back to model id {}",
+                    model_id, fallback_model.id
+                );
+                let fallback_json_mode = chat.json_mode && fallback_model.supports_json_mode;
+                let mut fallback_options = generation_options_for(fallback_json_mode, &chat);
+                fallback_options.n = options.n;
+                let fallback_system_prompt = system_prompt_for(&chat, fallback_json_mode);
+                let mut fallback_conversation = current_conversation.clone();
+                fallback_model.truncate_to_context_window(
+                    &mut fallback_conversation,
+                    &fallback_system_prompt,
+                );
+                if wait_for_rate_limit_reset
+                    && let Some(wait) = fallback_client
+                        .rate_limit()
+                        .and_then(|rate_limit| rate_limit.wait_before_next_request())
+                {
+                    tokio::time::sleep(wait).await;
+                }
+
+                let permit = semaphore.clone().acquire_owned().await.ok();
+                let request_started = Instant::now();
+                let fallback_result = generate_response(
+                    fallback_client.as_ref(),
+                    &fallback_model,
+                    &fallback_system_prompt,
+                    &fallback_conversation,
+                    fallback_remove_think_tokens,
+                    &fallback_options,
+                )
+                .await;
+                drop(permit);
+                if request_log {
+                    let logged_result = fallback_result.as_ref().map_err(|e| e.to_string());
+                    log_request(
+                        &database,
+                        chat_id,
+                        &fallback_model,
+                        fallback_conversation.len(),
+                        request_started,
+                        logged_result,
+                    )
+                    .await;
+                }
+                fallback_results.push((fallback_model.id, fallback_result.is_err()));
+                if has_reply(&fallback_result) {
+                    result = fallback_result;
+                    fallback_answered = Some((fallback_model, fallback_client));
+                } else {
+                    match fallback_result {
+                        Ok(_) => error!("Fallback model id {} returned nothing", fallback_model.id),
+                        Err(e) => error!("Fallback model id {} failed: {}", fallback_model.id, e),
+                    }
+                    failed_fallbacks.push(fallback_model.display_name().to_string());
+                }
+            }
+            if !has_reply(&result) && !failed_fallbacks.is_empty() {
+                let failure = match &result {
+                    Ok(_) => "Model returned an empty response".to_string(),
+                    Err(e) => e.to_string(),
+                };
+                result = Err(anyhow::anyhow!(
+                    "{}\n\nFallback models failed too: {}",
+                    failure,
+                    failed_fallbacks.join(", ")
+                ));
+            }
+
+            let mut new_assistant_message = match &result {
+                Ok(generation_result) => {
+                    // an empty reply is flagged like an error so it can't pass for a real one
+                    let mut message = match generation_result
+                        .content
+                        .clone()
+                        .filter(|content| !content.trim().is_empty())
+                    {
+                        Some(content) => ChatMessage::new_assistant_message(
+                            chat_id,
+                            model_id,
+                            content,
+                            user_message_dt,
+                        ),
+                        None => ChatMessage::new_assistant_message_with_error(
+                            chat_id,
+                            model_id,
+                            "Model returned an empty response".to_string(),
+                            user_message_dt,
+                        ),
+                    };
+                    message.reasoning_content = generation_result.reasoning_content.clone();
+                    message.reasoning_tokens = generation_result.reasoning_tokens;
+                    message.reasoning_tokens_estimated = generation_result.reasoning_tokens_estimated;
+                    message.prompt_tokens = generation_result.prompt_tokens;
+                    message.completion_tokens = generation_result.completion_tokens;
+                    message.finish_reason = generation_result.finish_reason.clone();
+                    message
+                }
                 Err(error) => {
                     error!("Inference failed: {}", error);
                     ChatMessage::new_assistant_message_with_error(
@@ -1435,22 +3615,67 @@ impl App {
                 }
             };
 
+            new_assistant_message.answered_by_model_id =
+                fallback_answered.as_ref().map(|(fallback_model, _)| fallback_model.id);
+            if let Some(placeholder_id) = placeholder_id {
+                new_assistant_message.id = placeholder_id;
+            }
+
+            let candidates = match (&result, &new_assistant_message.content) {
+                (Ok(generation_result), Some(content))
+                    if !generation_result.other_choices.is_empty() =>
+                {
+                    std::iter::once(content.clone())
+                        .chain(generation_result.other_choices.iter().cloned())
+                        .collect()
+                }
+                _ => Vec::new(),
+            };
+
             let _ = tx.send(InferenceEvent::InferenceComplete {
                 chat_id,
                 model_id,
                 origin_message_id: user_message_id,
-                result: new_assistant_message.clone(), // possible skill issue clone
+                result: Box::new(new_assistant_message.clone()), // possible skill issue clone
+                candidates,
+                request_failed,
+                fallback_results,
             });
 
             // now write the assistant message to the database
-            if let Err(e) = database.add_chat_message(&new_assistant_message).await {
+            let write_result = match placeholder_id {
+                Some(placeholder_id) => {
+                    database
+                        .complete_chat_message(placeholder_id, &new_assistant_message)
+                        .await
+                }
+                None => database
+                    .add_chat_message(&new_assistant_message)
+                    .await
+                    .map(|_| ()),
+            };
+            if let Err(e) = write_result {
                 info!("Couldn't write chat message to database: {}", e);
             }
             current_conversation.push(new_assistant_message);
 
             if generate_title {
+                // the chat's model just failed if a fallback had to answer
+                let (model, provider_client) = title_model
+                    .or(fallback_answered)
+                    .unwrap_or((model, provider_client));
                 let mut current_conversation_clone = current_conversation.clone();
-                current_conversation_clone.push(ChatMessage::new_user_message(chat_id, "Generate a concise title for the above conversation. It should be no more than 6 words.".to_string()));
+                current_conversation_clone.push(ChatMessage::new_user_message(
+                    chat_id,
+                    format!(
+                        "Generate a concise title for the above conversation. It should be no more than {} words.",
+                        TITLE_MAX_WORDS
+                    ),
+                ));
+                model.truncate_to_context_window(
+                    &mut current_conversation_clone,
+                    "You are a conversation title generator.",
+                );
                 tokio::spawn(async move {
                     info!("Spawning title inference task for model id: {}", model_id);
                     let title_result = provider_client
@@ -1460,12 +3685,14 @@ impl App {
                             &current_conversation_clone,
                             vec![], // No tools for now
                             false,  // Don't remove think tokens
+                            &GenerationOptions::default(),
                         )
                         .await
                         .map(|generation_result| {
                             generation_result
                                 .content
-                                .unwrap_or_else(|| "No response generated".to_string())
+                                .map(|title| title.trim().to_string())
+                                .filter(|title| !title.is_empty())
                         })
                         .map_err(|e| anyhow::anyhow!("Inference failed: {}", e));
 
@@ -1473,9 +3700,14 @@ impl App {
                     // we don't do the db write here because
                     // we want to wait until the last possible moment to make
                     // sure the user hasn't manually set the title
-                    if let Ok(title) = title_result {
-                        let _ = tx.send(InferenceEvent::TitleInferenceComplete { chat_id, title });
-                    }
+                    let title = match title_result {
+                        Ok(title) => title,
+                        Err(e) => {
+                            info!("Title inference failed for chat id {}: {}", chat_id, e);
+                            None
+                        }
+                    };
+                    let _ = tx.send(InferenceEvent::TitleInferenceComplete { chat_id, title });
                 });
             }
             current_conversation
@@ -1491,25 +3723,36 @@ impl App {
     }
 
     async fn open_model_selection_dialog(&mut self, mode: ModelSelectionMode) -> Result<()> {
-        // Check if we can modify current chat models (only if chat has no messages)
-        if mode == ModelSelectionMode::CurrentChatModels {
-            // no changing models if there are messages in the chat
-            if !self.current_messages.is_empty() {
+        // once the chat has messages its models can't be swapped out, but more can join
+        let mode = if mode == ModelSelectionMode::CurrentChatModels
+            && !self.current_messages.is_empty()
+        {
+            if self.refuse_if_read_only("Adding models") {
                 return Ok(());
             }
-        }
+            ModelSelectionMode::AddToCurrentChat
+        } else {
+            mode
+        };
 
         // Get the current model IDs based on mode
+        let no_models = Vec::new();
         let current_models = match mode {
             ModelSelectionMode::DefaultModels => &self.default_profile.model_ids,
             ModelSelectionMode::CurrentChatModels => &self.current_chat_profile.model_ids,
+            ModelSelectionMode::AddToCurrentChat => &no_models,
         };
+        let mut available_models = self.available_models.clone();
+        if mode == ModelSelectionMode::AddToCurrentChat {
+            available_models
+                .retain(|model_id, _| !self.current_chat_profile.model_ids.contains(model_id));
+        }
 
         // Create the modal with clones of the data it needs
         let modal = ModelSelectModal::new(
             mode,
             current_models,
-            self.available_models.clone(),
+            available_models,
             self.provider_names.clone(),
         );
 
@@ -1519,6 +3762,14 @@ impl App {
     }
 
     async fn apply_model_selection(&mut self, selected_models: Vec<i64>) -> Result<()> {
+        // a profile without models can't be prompted, so an empty selection keeps the previous one
+        if selected_models.is_empty() {
+            self.set_status_message(
+                "No models were selected, keeping the previous selection".to_string(),
+            );
+            return Ok(());
+        }
+
         // Get the mode from the modal before we apply
         let mode = self
             .model_select_modal
@@ -1530,12 +3781,12 @@ impl App {
             ModelSelectionMode::DefaultModels => {
                 // Remove all existing models for the default profile
                 for &model_id in &self.default_profile.model_ids {
-                    self.database.remove_chat_profile_model(0, model_id).await?;
+                    self.database.remove_chat_profile_model(DEFAULT_PROFILE_ID, model_id).await?;
                 }
 
                 // Set the selected models with their order preserved
                 self.database
-                    .set_chat_profile_models(0, selected_models.clone())
+                    .set_chat_profile_models(DEFAULT_PROFILE_ID, selected_models.clone())
                     .await?;
 
                 self.default_profile.model_ids = selected_models.clone();
@@ -1550,11 +3801,203 @@ impl App {
                 // until the first prompt happens
                 self.current_chat_profile.model_ids = selected_models;
             }
+            ModelSelectionMode::AddToCurrentChat => {
+                self.add_models_to_current_chat(selected_models).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add models to a chat that already has messages. They answer from the next prompt on, the
+    /// earlier prompts show up in their history without a reply
+    async fn add_models_to_current_chat(&mut self, model_ids: Vec<i64>) -> Result<()> {
+        let new_model_ids: Vec<i64> = model_ids
+            .into_iter()
+            .filter(|model_id| !self.current_chat_profile.model_ids.contains(model_id))
+            .collect();
+        if new_model_ids.is_empty() {
+            return Ok(());
+        }
+
+        self.current_chat_profile.model_ids.extend(&new_model_ids);
+        if self.current_chat.id != 0 {
+            self.database
+                .set_chat_models(self.current_chat.id, self.current_chat_profile.model_ids.clone())
+                .await?;
+        }
+
+        // the first model has every prompt, they're shared by all of them
+        let prompts: Vec<ChatMessage> = self
+            .current_chat_profile
+            .model_ids
+            .first()
+            .and_then(|model_id| self.current_messages.get(model_id))
+            .map(|messages| {
+                messages
+                    .iter()
+                    .filter(|message| message.chat_role == ChatRole::User)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        for &model_id in &new_model_ids {
+            self.current_messages.insert(model_id, prompts.clone());
+            self.current_message_index.insert(model_id, 0);
+            self.current_chunk_idx.insert(model_id, 0);
+            self.chat_item_selections.insert(model_id, None);
+            self.following_latest.insert(model_id);
+        }
+
+        self.set_status_message(format!(
+            "Added {} model{} to the chat, replies start with the next prompt",
+            new_model_ids.len(),
+            if new_model_ids.len() == 1 { "" } else { "s" }
+        ));
+        Ok(())
+    }
+
+    /// Refresh models for every provider with a client in the background, the result arrives as `ModelsRefreshed`
+    pub fn spawn_model_refresh(&mut self) {
+        self.spawn_provider_model_sync(false);
+    }
+
+    /// Every so often sync the providers marked down again, so a transient outage heals without a restart
+    fn recheck_marked_down_providers(&mut self) {
+        if self.providers_marked_down.is_empty()
+            || self.last_provider_recheck.elapsed() < PROVIDER_RECHECK_INTERVAL
+        {
+            return;
+        }
+        self.last_provider_recheck = Instant::now();
+        self.spawn_provider_model_sync(true);
+    }
+
+    fn spawn_provider_model_sync(&mut self, recheck_only: bool) {
+        // a refresh writes the models it finds to the database, a re-check can get by without that
+        if (self.read_only && !recheck_only) || self.model_refresh_provider_count.is_some() {
+            return;
+        }
+
+        let providers: Vec<(Provider, Arc<dyn ProviderClient>)> = self
+            .provider_clients
+            .iter()
+            .filter(|(provider_id, _)| {
+                !recheck_only || self.providers_marked_down.contains(provider_id)
+            })
+            .filter_map(|(provider_id, client)| {
+                self.providers
+                    .get(provider_id)
+                    .map(|provider| (provider.clone(), client.clone()))
+            })
+            .collect();
+        if providers.is_empty() {
+            return;
+        }
+
+        self.model_refresh_provider_count = Some(providers.len());
+        let database = self.database.clone();
+        let tx = self.user_event_tx.clone();
+        let write_models = !self.read_only;
+        tokio::spawn(async move {
+            let (added, removed, answered_provider_ids, failed_provider_ids) =
+                refresh_models_with_provider_api(&database, providers, write_models).await;
+            let models = match database.get_all_models(true).await {
+                Ok(models) => models,
+                Err(e) => {
+                    error!("Failed to reload models after refresh: {}", e);
+                    Vec::new()
+                }
+            };
+            let _ = tx.send(InferenceEvent::ModelsRefreshed {
+                models,
+                added,
+                removed,
+                answered_provider_ids,
+                failed_provider_ids,
+                recheck_only,
+            });
+        });
+    }
+
+    /// Copy to the system clipboard. Without one (over SSH or headless) the text is written to
+    /// last_yank.txt in the data directory instead, and the clipboard isn't tried again this session
+    fn copy_to_clipboard(&mut self, text: String) {
+        if !self.clipboard_unavailable {
+            match ClipboardContext::new().and_then(|mut ctx| ctx.set_contents(text.clone())) {
+                Ok(()) => return,
+                Err(e) => {
+                    error!("Clipboard unavailable, yanking to a file instead: {}", e);
+                    self.clipboard_unavailable = true;
+                }
+            }
         }
 
+        let path = self.data_dir.join("last_yank.txt");
+        match std::fs::write(&path, text) {
+            Ok(()) => self.set_status_message(format!("No clipboard, yanked to {}", path.display())),
+            Err(e) => self.set_status_message(format!("No clipboard, and writing {} failed: {}", path.display(), e)),
+        }
+    }
+
+    /// Copy "<provider name>/<model>" for the model in view, which tells exactly what answered
+    fn copy_current_model_identifier(&mut self) {
+        let Some(model) = self
+            .current_chat_profile
+            .model_ids
+            .get(self.current_model_idx)
+            .and_then(|model_id| self.all_models.get(model_id))
+        else {
+            return;
+        };
+        let provider_name = self
+            .provider_names
+            .get(&model.provider_id)
+            .cloned()
+            .unwrap_or_else(|| "Unknown Provider".to_string());
+        let identifier = format!("{}/{}", provider_name, model.model);
+        // a missing clipboard replaces this with where the text went instead
+        self.set_status_message(format!("Copied {}", identifier));
+        self.copy_to_clipboard(identifier);
+    }
+
+    /// In read-only mode, say that `action` is turned off. Returns whether it was refused
+    fn refuse_if_read_only(&mut self, action: &str) -> bool {
+        if self.read_only {
+            self.set_status_message(format!("{} is disabled in read-only mode", action));
+        }
+        self.read_only
+    }
+
+    /// Persist a setting. In read-only mode a change only lasts for the session
+    async fn save_setting(&self, key: &str, value: &str) -> Result<()> {
+        if !self.read_only {
+            self.database.set_setting(key, value).await?;
+        }
         Ok(())
     }
 
+    pub fn set_status_message(&mut self, message: String) {
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    /// The text for the status line under the prompt, if there is anything to show
+    pub fn get_status_line(&self) -> Option<String> {
+        if let Some(provider_count) = self.model_refresh_provider_count {
+            return Some(format!(
+                "{} Refreshing models from {} provider{}...",
+                self.get_spinner_char(),
+                provider_count,
+                if provider_count == 1 { "" } else { "s" }
+            ));
+        }
+
+        self.status_message
+            .as_ref()
+            .filter(|(_, set_at)| set_at.elapsed() < STATUS_MESSAGE_DURATION)
+            .map(|(message, _)| message.clone())
+    }
+
     pub fn update_spinner(&mut self) {
         let now = Instant::now();
         if now.duration_since(self.last_spinner_update) >= Duration::from_millis(150) {
@@ -1592,59 +4035,1409 @@ impl App {
         Ok(())
     }
 
-    fn open_title_edit_dialog(&mut self) {
-        // Initialize the title textarea with the current title (or empty string for new title)
-        let current_title = self.current_chat.title.clone().unwrap_or_default();
-        let mut title_textarea = EditorState::default();
-        set_editor_state_text(&mut title_textarea, current_title);
-        self.title_textarea = title_textarea;
-        self.state = AppState::TitleEdit;
+    /// The marked chats as (first, last) positions in chat_history, both inclusive
+    pub fn chat_history_visual_range(&self) -> Option<(usize, usize)> {
+        let start = self.chat_history_visual_start?;
+        Some((
+            start.min(self.chat_history_index),
+            start.max(self.chat_history_index),
+        ))
     }
 
-    async fn handle_title_edit_key(&mut self, key: KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Esc => {
-                self.state = AppState::Normal;
-            }
-            KeyCode::Enter => {
-                let new_title = editor_state_to_string(&self.title_textarea)
-                    .trim()
-                    .to_string();
-                if !new_title.is_empty() {
-                    // Update the title in the database
-                    self.database
-                        .update_chat_title(self.current_chat.id, &new_title)
-                        .await?;
+    async fn delete_marked_chats(&mut self) -> Result<()> {
+        let Some((first, last)) = self.chat_history_visual_range() else {
+            return Ok(());
+        };
+        self.chat_history_visual_start = None;
+        let last = last.min(self.chat_history.len().saturating_sub(1));
+
+        // a new chat that was never written only has to be dropped from the list
+        let chat_ids: Vec<i64> = self.chat_history[first..=last]
+            .iter()
+            .map(|chat| chat.id)
+            .filter(|&chat_id| chat_id != 0)
+            .collect();
+        self.database.delete_chats(&chat_ids).await?;
+        self.chat_history.drain(first..=last);
+
+        // the chat after the deleted ones moves up into their place
+        self.chat_history_index = first.min(self.chat_history.len().saturating_sub(1));
+        if self.chat_history.is_empty() {
+            self.create_new_chat().await?;
+        } else {
+            self.load_selected_chat().await?;
+        }
+        self.set_status_message(format!(
+            "Deleted {} chat{}",
+            last - first + 1,
+            if last == first { "" } else { "s" }
+        ));
 
-                    // Update the in-memory chat title
-                    self.current_chat.title = Some(new_title.clone());
+        Ok(())
+    }
 
-                    // Update the chat history
-                    if let Some(chat) = self.chat_history.get_mut(self.chat_history_index) {
-                        chat.title = Some(new_title);
-                    }
-                }
-                self.state = AppState::Normal;
+    /// Delete every message in the current chat, keeping the chat itself and its models
+    async fn clear_current_chat_messages(&mut self) -> Result<()> {
+        let chat_id = self.current_chat.id;
+
+        // anything still generating would write its response back into the cleared chat
+        for &model_id in &self.current_chat_profile.model_ids {
+            if let Some(handle) = self
+                .inference_handles_by_chat_and_model
+                .remove(&(chat_id, model_id))
+            {
+                handle.abort();
             }
-            _ => {
-                let mut event_handler = EditorEventHandler::default();
-                event_handler.on_key_event(key, &mut self.title_textarea);
+            for message in self.current_messages.get(&model_id).into_iter().flatten() {
+                self.inference_in_progress_by_message_and_model
+                    .remove(&(message.id, model_id));
+                self.inference_running_by_message_and_model
+                    .remove(&(message.id, model_id));
             }
         }
-        Ok(())
-    }
+        self.title_inference_in_progress_by_chat.remove(&chat_id);
 
-    async fn handle_unavailable_models_error_key(&mut self, _key: KeyEvent) -> Result<()> {
-        // Any key press dismisses the error dialog and goes back to chat history
-        self.state = AppState::Normal;
-        self.unavailable_models_info.clear();
+        self.database.delete_chat_messages(chat_id).await?;
+
+        // reset navigation the same way a new chat starts out
+        for &model_id in &self.current_chat_profile.model_ids {
+            self.current_messages.insert(model_id, Vec::new());
+            self.current_message_index.insert(model_id, 0);
+            self.current_chunk_idx.insert(model_id, 0);
+            self.chat_item_selections.insert(model_id, None);
+        }
+        self.enter_prompt_insert_mode();
 
-        // Try to find a chat that has all available models
-        // If the current chat is invalid, we stay on it but the user can navigate away
         Ok(())
     }
 
-    async fn clear_search_filter(&mut self) -> Result<()> {
+    /// Swap the current model with the one before or after it in the carousel, keeping it selected
+    async fn move_current_model(&mut self, forward: bool) -> Result<()> {
+        let model_count = self.current_chat_profile.model_ids.len();
+        let new_idx = if forward {
+            self.current_model_idx + 1
+        } else {
+            self.current_model_idx.wrapping_sub(1)
+        };
+        if new_idx >= model_count {
+            return Ok(());
+        }
+
+        self.current_chat_profile
+            .model_ids
+            .swap(self.current_model_idx, new_idx);
+        self.current_model_idx = new_idx;
+
+        // chats that haven't been written yet get their models saved with the first message
+        if self.current_chat.id != 0 {
+            self.database
+                .set_chat_models(
+                    self.current_chat.id,
+                    self.current_chat_profile.model_ids.clone(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn toggle_current_chat_json_mode(&mut self) -> Result<()> {
+        self.current_chat.json_mode = !self.current_chat.json_mode;
+        if let Some(chat) = self.chat_history.get_mut(self.chat_history_index) {
+            chat.json_mode = self.current_chat.json_mode;
+        }
+
+        // unsaved chats write the flag when they are created
+        if self.current_chat.id != 0 {
+            self.database
+                .set_chat_json_mode(self.current_chat.id, self.current_chat.json_mode)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn handle_stop_sequences_edit_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Enter => {
+                let stop_sequences = parse_stop_sequences(&editor_state_to_string(&self.title_textarea));
+                let stop_sequences = if stop_sequences.is_empty() {
+                    None
+                } else {
+                    Some(serde_json::to_string(&stop_sequences)?)
+                };
+                self.current_chat.stop_sequences = stop_sequences.clone();
+                if let Some(chat) = self.chat_history.get_mut(self.chat_history_index) {
+                    chat.stop_sequences = stop_sequences.clone();
+                }
+
+                // unsaved chats write them when they are created
+                if self.current_chat.id != 0 {
+                    self.database
+                        .set_chat_stop_sequences(self.current_chat.id, stop_sequences)
+                        .await?;
+                }
+                self.state = AppState::Normal;
+            }
+            _ => {
+                let mut event_handler = EditorEventHandler::default();
+                event_handler.on_key_event(key, &mut self.title_textarea);
+            }
+        }
+        Ok(())
+    }
+
+    async fn toggle_current_chat_archived(&mut self) -> Result<()> {
+        let chat_id = self.current_chat.id;
+        let archived = !self.current_chat.archived;
+        self.database.set_chat_archived(chat_id, archived).await?;
+        self.current_chat.archived = archived;
+
+        if archived && !self.show_archived {
+            // the chat is now hidden, so drop it from the history like we do on deletion
+            self.chat_history.retain(|chat| chat.id != chat_id);
+            if self.chat_history_index >= self.chat_history.len() && self.chat_history_index > 0 {
+                self.chat_history_index = self.chat_history.len() - 1;
+            }
+
+            if self.chat_history.is_empty() {
+                self.create_new_chat().await?;
+            } else {
+                self.load_selected_chat().await?;
+            }
+        } else if let Some(chat) = self.chat_history.iter_mut().find(|chat| chat.id == chat_id) {
+            chat.archived = archived;
+        }
+
+        Ok(())
+    }
+
+    /// Move the selected chat up or down the history list, swapping places with the chats it
+    /// passes. The new place is kept in the db, so it only works in the date order without a search
+    async fn move_selected_chat(&mut self, up: bool, count: usize) -> Result<()> {
+        if self.refuse_if_read_only("Moving chats") {
+            return Ok(());
+        }
+        if self.settings.chat_sort != ChatSort::Date || !self.search_query.is_empty() {
+            self.set_status_message(
+                "Chats can only be moved when sorted by date and not searching".to_string(),
+            );
+            return Ok(());
+        }
+
+        for _ in 0..count {
+            let index = self.chat_history_index;
+            if !up {
+                while index + 1 >= self.chat_history.len()
+                    && self.load_more_chat_history().await?
+                {}
+            }
+            let neighbor = if up { index.checked_sub(1) } else { Some(index + 1) };
+            let Some(neighbor) = neighbor.filter(|&neighbor| neighbor < self.chat_history.len())
+            else {
+                break;
+            };
+            // a new chat isn't in the db until its first message is sent
+            let chat_id = self.chat_history[index].id;
+            let neighbor_id = self.chat_history[neighbor].id;
+            if chat_id == 0 || neighbor_id == 0 {
+                break;
+            }
+            self.database.swap_chat_places(chat_id, neighbor_id).await?;
+            self.chat_history.swap(index, neighbor);
+            self.chat_history_index = neighbor;
+        }
+
+        Ok(())
+    }
+
+    /// The unfiltered chat list from the top, as many pages as have been loaded so far
+    /// so reloading doesn't drop the selected chat off the end
+    async fn load_chat_history(&mut self) -> Result<Vec<Chat>> {
+        let limit = self.settings.chat_history_page_size * self.chat_history_pages;
+        let chats = self
+            .database
+            .get_chats(self.show_archived, self.settings.chat_sort, limit as i64, 0)
+            .await?;
+        self.chat_history_exhausted = chats.len() < limit;
+        Ok(chats)
+    }
+
+    /// Append the next page of the unfiltered chat list, returns whether any chats were added
+    async fn load_more_chat_history(&mut self) -> Result<bool> {
+        if !self.search_query.is_empty() || self.chat_history_exhausted {
+            return Ok(false);
+        }
+        let page_size = self.settings.chat_history_page_size;
+        let chats = self
+            .database
+            .get_chats(
+                self.show_archived,
+                self.settings.chat_sort,
+                page_size as i64,
+                (page_size * self.chat_history_pages) as i64,
+            )
+            .await?;
+        self.chat_history_pages += 1;
+        self.chat_history_exhausted = chats.len() < page_size;
+
+        // chats created since the earlier pages were loaded shift the offsets, so skip any already listed
+        let listed: HashSet<i64> = self.chat_history.iter().map(|chat| chat.id).collect();
+        let before = self.chat_history.len();
+        self.chat_history
+            .extend(chats.into_iter().filter(|chat| !listed.contains(&chat.id)));
+        Ok(self.chat_history.len() > before)
+    }
+
+    /// Re-query the chat history, respecting the active search and archive filter,
+    /// keeping the current chat selected if it is still listed
+    async fn reload_chat_history(&mut self) -> Result<()> {
+        let selected_chat_id = self.current_chat.id;
+        self.chat_history_visual_start = None;
+        self.chat_history = if self.search_query.is_empty() {
+            self.load_chat_history().await?
+        } else {
+            self.database
+                .search_all(&self.search_query, 1000, self.show_archived)
+                .await?
+        };
+        // a new chat that hasn't been written to the db yet won't come back from the query
+        if selected_chat_id == 0 {
+            self.chat_history.insert(0, self.current_chat.clone());
+        }
+
+        if let Some(pos) = self.chat_history.iter().position(|c| c.id == selected_chat_id) {
+            self.chat_history_index = pos;
+        } else {
+            self.chat_history_index = 0;
+            if self.chat_history.is_empty() {
+                self.create_new_chat().await?;
+            } else {
+                self.load_selected_chat().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn open_title_edit_dialog(&mut self) {
+        // Initialize the title textarea with the current title (or empty string for new title)
+        let current_title = self.current_chat.title.clone().unwrap_or_default();
+        let mut title_textarea = EditorState::default();
+        set_editor_state_text(&mut title_textarea, current_title);
+        self.title_textarea = title_textarea;
+        self.state = AppState::TitleEdit;
+    }
+
+    async fn handle_title_edit_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Enter => {
+                let new_title = editor_state_to_string(&self.title_textarea)
+                    .trim()
+                    .to_string();
+                if !new_title.is_empty() {
+                    // Update the title in the database
+                    self.database
+                        .update_chat_title(self.current_chat.id, &new_title)
+                        .await?;
+
+                    // Update the in-memory chat title
+                    self.current_chat.title = Some(new_title.clone());
+
+                    // Update the chat history
+                    if let Some(chat) = self.chat_history.get_mut(self.chat_history_index) {
+                        chat.title = Some(new_title);
+                    }
+                }
+                self.state = AppState::Normal;
+            }
+            _ => {
+                let mut event_handler = EditorEventHandler::default();
+                event_handler.on_key_event(key, &mut self.title_textarea);
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_profile_name_edit_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.pending_profile_model_ids.clear();
+                self.state = AppState::ModelSelection;
+            }
+            KeyCode::Enter => {
+                let name = editor_state_to_string(&self.title_textarea)
+                    .trim()
+                    .to_string();
+                if !name.is_empty() {
+                    let model_ids = std::mem::take(&mut self.pending_profile_model_ids);
+                    self.database.save_named_profile(&name, model_ids).await?;
+                    self.set_status_message(format!("Saved profile \"{}\"", name));
+                }
+                self.state = AppState::ModelSelection;
+            }
+            _ => {
+                let mut event_handler = EditorEventHandler::default();
+                event_handler.on_key_event(key, &mut self.title_textarea);
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_model_alias_edit_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.alias_model_id = None;
+                self.state = AppState::ModelSelection;
+            }
+            KeyCode::Enter => {
+                if let Some(model_id) = self.alias_model_id.take() {
+                    let alias = editor_state_to_string(&self.title_textarea).trim().to_string();
+                    let alias = (!alias.is_empty()).then_some(alias);
+                    self.database.set_model_alias(model_id, alias.clone()).await?;
+                    // the modal has its own copy of the models
+                    let modal_models = self
+                        .model_select_modal
+                        .as_mut()
+                        .map(|modal| &mut modal.available_models);
+                    let models = [Some(&mut self.all_models), Some(&mut self.available_models)];
+                    for models in models.into_iter().chain([modal_models]).flatten() {
+                        if let Some(model) = models.get_mut(&model_id) {
+                            model.alias = alias.clone();
+                        }
+                    }
+                }
+                self.state = AppState::ModelSelection;
+            }
+            _ => {
+                let mut event_handler = EditorEventHandler::default();
+                event_handler.on_key_event(key, &mut self.title_textarea);
+            }
+        }
+        Ok(())
+    }
+
+    async fn open_profile_picker(&mut self) -> Result<()> {
+        self.named_profiles = self.database.get_named_profiles().await?;
+        self.profile_picker_index = 0;
+        self.state = AppState::ProfilePicker;
+        Ok(())
+    }
+
+    async fn handle_profile_picker_key(&mut self, key: KeyEvent) -> Result<()> {
+        // browsing is fine, applying or deleting a profile writes to the database
+        if matches!(key.code, KeyCode::Char('d' | 'x') | KeyCode::Enter)
+            && self.refuse_if_read_only("Changing profiles")
+        {
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.profile_picker_index = (self.profile_picker_index + 1)
+                    .min(self.named_profiles.len().saturating_sub(1));
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.profile_picker_index = self.profile_picker_index.saturating_sub(1);
+            }
+            KeyCode::Char('d') | KeyCode::Char('x') => {
+                if let Some(profile) = self.named_profiles.get(self.profile_picker_index) {
+                    self.database.delete_named_profile(profile.id).await?;
+                    self.named_profiles.remove(self.profile_picker_index);
+                    if self.profile_picker_index >= self.named_profiles.len() {
+                        self.profile_picker_index = self.named_profiles.len().saturating_sub(1);
+                    }
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(profile) = self.named_profiles.get(self.profile_picker_index).cloned() {
+                    self.apply_named_profile(profile).await?;
+                }
+                self.state = AppState::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn open_prompt_library(&mut self) -> Result<()> {
+        self.library_prompts = self.database.get_library_prompts().await?;
+        // start on the prompt the chat is using, if it came from the library
+        self.prompt_library_index = self
+            .library_prompts
+            .iter()
+            .position(|prompt| Some(&prompt.content) == self.current_chat.system_prompt.as_ref())
+            .unwrap_or(0);
+        self.state = AppState::PromptLibrary;
+        Ok(())
+    }
+
+    async fn handle_prompt_library_key(&mut self, key: KeyEvent) -> Result<()> {
+        // browsing is fine, everything else writes to the database
+        if matches!(key.code, KeyCode::Char('n' | 'e' | 'd' | 'x' | 'c') | KeyCode::Enter)
+            && self.refuse_if_read_only("Changing system prompts")
+        {
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.prompt_library_index = (self.prompt_library_index + 1)
+                    .min(self.library_prompts.len().saturating_sub(1));
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.prompt_library_index = self.prompt_library_index.saturating_sub(1);
+            }
+            KeyCode::Char('n') => {
+                self.editing_library_prompt_id = None;
+                self.title_textarea = EditorState::default();
+                self.title_textarea.mode = EditorMode::Insert;
+                self.state = AppState::PromptLibraryNameEdit;
+            }
+            KeyCode::Char('e') => {
+                if let Some(prompt) = self.library_prompts.get(self.prompt_library_index) {
+                    self.editing_library_prompt_id = Some(prompt.id);
+                    set_editor_state_text(&mut self.title_textarea, prompt.name.clone());
+                    self.title_textarea.mode = EditorMode::Insert;
+                    self.state = AppState::PromptLibraryNameEdit;
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Char('x')
+                if self.prompt_library_index < self.library_prompts.len() =>
+            {
+                self.state = AppState::PromptLibraryDeleteConfirmation;
+            }
+            KeyCode::Char('c') => {
+                self.set_current_chat_system_prompt(None).await?;
+                self.set_status_message("Using the default system prompt".to_string());
+                self.state = AppState::Normal;
+            }
+            KeyCode::Enter => {
+                if let Some(prompt) = self.library_prompts.get(self.prompt_library_index).cloned() {
+                    self.set_current_chat_system_prompt(Some(prompt.content)).await?;
+                    self.set_status_message(format!("Using system prompt \"{}\"", prompt.name));
+                }
+                self.state = AppState::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_library_prompt_delete_confirmation_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('n') | KeyCode::Char('N') => {
+                self.state = AppState::PromptLibrary;
+            }
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                self.state = AppState::PromptLibrary;
+                if self.refuse_if_read_only("Deleting system prompts") {
+                    return Ok(());
+                }
+                if let Some(prompt) = self.library_prompts.get(self.prompt_library_index) {
+                    self.database.delete_library_prompt(prompt.id).await?;
+                    self.library_prompts.remove(self.prompt_library_index);
+                    if self.prompt_library_index >= self.library_prompts.len() {
+                        self.prompt_library_index = self.library_prompts.len().saturating_sub(1);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_library_prompt_name_edit_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => {
+                self.state = AppState::PromptLibrary;
+            }
+            KeyCode::Enter => {
+                let name = editor_state_to_string(&self.title_textarea).trim().to_string();
+                if name.is_empty() {
+                    return;
+                }
+                self.pending_library_prompt_name = name;
+                let content = self
+                    .editing_library_prompt_id
+                    .and_then(|prompt_id| {
+                        self.library_prompts.iter().find(|prompt| prompt.id == prompt_id)
+                    })
+                    .map(|prompt| prompt.content.clone())
+                    .unwrap_or_default();
+                set_editor_state_text(&mut self.library_prompt_textarea, content);
+                self.library_prompt_textarea.mode = EditorMode::Insert;
+                self.state = AppState::PromptLibraryContentEdit;
+            }
+            _ => {
+                let mut event_handler = EditorEventHandler::default();
+                event_handler.on_key_event(key, &mut self.title_textarea);
+            }
+        }
+    }
+
+    /// The prompt is multi-line so Enter is left to the editor, Ctrl+s saves and Esc outside of insert mode cancels
+    async fn handle_library_prompt_content_edit_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key {
+            KeyEvent {
+                code: KeyCode::Char('s'),
+                modifiers: KeyModifiers::CONTROL,
+                ..
+            } => {
+                if self.refuse_if_read_only("Saving system prompts") {
+                    return Ok(());
+                }
+                let content = editor_state_to_string(&self.library_prompt_textarea);
+                let content = content.trim();
+                if content.is_empty() {
+                    self.set_status_message("A system prompt can't be empty".to_string());
+                    return Ok(());
+                }
+                let prompt_id = self
+                    .database
+                    .save_library_prompt(
+                        self.editing_library_prompt_id,
+                        &self.pending_library_prompt_name,
+                        content,
+                    )
+                    .await?;
+                self.library_prompts = self.database.get_library_prompts().await?;
+                self.prompt_library_index = self
+                    .library_prompts
+                    .iter()
+                    .position(|prompt| prompt.id == prompt_id)
+                    .unwrap_or(0);
+                self.state = AppState::PromptLibrary;
+            }
+            KeyEvent {
+                code: KeyCode::Esc, ..
+            } if self.library_prompt_textarea.mode == EditorMode::Normal => {
+                self.state = AppState::PromptLibrary;
+            }
+            _ => {
+                let mut event_handler = EditorEventHandler::default();
+                event_handler.on_key_event(key, &mut self.library_prompt_textarea);
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_current_chat_system_prompt(&mut self, system_prompt: Option<String>) -> Result<()> {
+        self.current_chat.system_prompt = system_prompt.clone();
+        if let Some(chat) = self.chat_history.get_mut(self.chat_history_index) {
+            chat.system_prompt = system_prompt.clone();
+        }
+        // unsaved chats write it when they are created
+        if self.current_chat.id != 0 {
+            self.database
+                .set_chat_system_prompt(self.current_chat.id, system_prompt)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn toggle_bookmark(&mut self, message_id: i64) -> Result<()> {
+        // messages of a chat that hasn't been written to the db yet have nothing to point at
+        if self.current_chat.id == 0 || self.refuse_if_read_only("Bookmarking") {
+            return Ok(());
+        }
+        if self.database.toggle_bookmark(message_id).await? {
+            self.bookmarked_message_ids.insert(message_id);
+            self.set_status_message("Bookmarked message".to_string());
+        } else {
+            self.bookmarked_message_ids.remove(&message_id);
+            self.set_status_message("Removed bookmark".to_string());
+        }
+        Ok(())
+    }
+
+    async fn open_request_log(&mut self) -> Result<()> {
+        self.request_log = self.database.get_request_log(REQUEST_LOG_LIMIT).await?;
+        self.request_log_index = 0;
+        self.state = AppState::RequestLog;
+        Ok(())
+    }
+
+    fn handle_request_log_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('K') => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.request_log_index =
+                    (self.request_log_index + 1).min(self.request_log.len().saturating_sub(1));
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.request_log_index = self.request_log_index.saturating_sub(1);
+            }
+            _ => {}
+        }
+    }
+
+    async fn open_bookmark_list(&mut self) -> Result<()> {
+        self.bookmarks = self.database.get_bookmarks().await?;
+        self.bookmark_list_index = 0;
+        self.state = AppState::BookmarkList;
+        Ok(())
+    }
+
+    async fn handle_bookmark_list_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.bookmark_list_index =
+                    (self.bookmark_list_index + 1).min(self.bookmarks.len().saturating_sub(1));
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.bookmark_list_index = self.bookmark_list_index.saturating_sub(1);
+            }
+            KeyCode::Char('d') | KeyCode::Char('x') => {
+                if self.refuse_if_read_only("Removing bookmarks") {
+                    return Ok(());
+                }
+                if let Some(bookmark) = self.bookmarks.get(self.bookmark_list_index) {
+                    self.database.toggle_bookmark(bookmark.message_id).await?;
+                    self.bookmarked_message_ids.remove(&bookmark.message_id);
+                    self.bookmarks.remove(self.bookmark_list_index);
+                    if self.bookmark_list_index >= self.bookmarks.len() {
+                        self.bookmark_list_index = self.bookmarks.len().saturating_sub(1);
+                    }
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(bookmark) = self.bookmarks.get(self.bookmark_list_index).cloned() {
+                    self.jump_to_bookmark(&bookmark).await?;
+                }
+                self.state = AppState::Normal;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Open the bookmarked message's chat and show the message, on its model's tab for responses
+    async fn jump_to_bookmark(&mut self, bookmark: &Bookmark) -> Result<()> {
+        if !self.select_chat_by_id(bookmark.chat_id, bookmark.chat_archived).await? {
+            self.set_status_message("The bookmarked chat is no longer listed".to_string());
+            return Ok(());
+        }
+        // the user's own messages show up under every model, so those stay on the first one
+        if let Some(model_id) = bookmark.model_id
+            && let Some(model_idx) = self
+                .current_chat_profile
+                .model_ids
+                .iter()
+                .position(|id| *id == model_id)
+        {
+            self.current_model_idx = model_idx;
+        }
+        let Some(&model_id) = self.current_chat_profile.model_ids.get(self.current_model_idx) else {
+            return Ok(());
+        };
+        let find_message = |app: &Self| {
+            app.current_messages.get(&model_id).and_then(|messages| {
+                messages
+                    .iter()
+                    .position(|message| message.id == bookmark.message_id)
+            })
+        };
+        let mut message_idx = find_message(self);
+        // in a long chat it can be further back than what's loaded
+        while message_idx.is_none() && self.load_older_messages().await? {
+            message_idx = find_message(self);
+        }
+        match message_idx {
+            Some(message_idx) => {
+                self.current_message_index.insert(model_id, message_idx);
+                self.current_chunk_idx.insert(model_id, 0);
+                self.unified_view = false;
+            }
+            // the model it came from was taken off the chat since
+            None => self.set_status_message(
+                "The bookmarked response's model is no longer in this chat".to_string(),
+            ),
+        }
+        Ok(())
+    }
+
+    /// Open every database next to the current one for searching them all at once
+    async fn open_global_search(&mut self) -> Result<()> {
+        let current_path = std::fs::canonicalize(&self.db_path).unwrap_or(self.db_path.clone());
+        let data_dir = if self.data_dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            self.data_dir.as_path()
+        };
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(data_dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "db"))
+            .filter(|path| std::fs::canonicalize(path).unwrap_or(path.clone()) != current_path)
+            .collect();
+        paths.sort();
+
+        let mut databases = vec![(self.db_path.clone(), self.database.clone())];
+        for path in paths {
+            match Database::open_read_only(&path).await {
+                Ok(database) => databases.push((path, Arc::new(database))),
+                Err(e) => info!("Leaving {} out of the global search: {}", path.display(), e),
+            }
+        }
+
+        let mut textarea = EditorState::default();
+        textarea.mode = EditorMode::Insert;
+        self.global_search = Some(GlobalSearch {
+            databases,
+            textarea,
+            results: Vec::new(),
+            index: 0,
+            searched: false,
+        });
+        self.state = AppState::GlobalSearch;
+        Ok(())
+    }
+
+    async fn handle_global_search_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(search) = &mut self.global_search else {
+            self.state = AppState::Normal;
+            return Ok(());
+        };
+
+        // typing the query, Enter searches and moves over to the results
+        if search.textarea.mode == EditorMode::Insert {
+            match key.code {
+                KeyCode::Enter => self.run_global_search().await,
+                KeyCode::Esc if !search.searched => {
+                    self.global_search = None;
+                    self.state = AppState::Normal;
+                }
+                KeyCode::Esc => search.textarea.mode = EditorMode::Normal,
+                _ => self.editor_event_handler.on_key_event(key, &mut search.textarea),
+            }
+            return Ok(());
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.global_search = None;
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                search.index = (search.index + 1).min(search.results.len().saturating_sub(1));
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                search.index = search.index.saturating_sub(1);
+            }
+            KeyCode::Char('/') | KeyCode::Char('i') => {
+                search.textarea.mode = EditorMode::Insert;
+            }
+            KeyCode::Enter => self.open_global_search_result().await?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn run_global_search(&mut self) {
+        let Some(search) = &mut self.global_search else {
+            return;
+        };
+        let query = editor_state_to_string(&search.textarea);
+        let mut results = Vec::new();
+        for (path, database) in &search.databases {
+            match database.search_all(query.trim(), GLOBAL_SEARCH_LIMIT, true).await {
+                Ok(chats) => results.extend(chats.into_iter().map(|chat| (path.clone(), chat))),
+                Err(e) => info!("Global search failed for {}: {}", path.display(), e),
+            }
+        }
+        results.sort_by_key(|(_, chat)| std::cmp::Reverse(chat.dt));
+
+        search.results = results;
+        search.index = 0;
+        search.searched = true;
+        search.textarea.mode = EditorMode::Normal;
+    }
+
+    async fn open_global_search_result(&mut self) -> Result<()> {
+        let Some((path, chat)) = self
+            .global_search
+            .as_ref()
+            .and_then(|search| search.results.get(search.index))
+            .cloned()
+        else {
+            return Ok(());
+        };
+
+        // the current database comes first in the search, every other one has to be switched to
+        let in_current_database = self
+            .global_search
+            .as_ref()
+            .and_then(|search| search.databases.first())
+            .is_some_and(|(current_path, _)| *current_path == path);
+        if !in_current_database && !self.switch_database(&path).await? {
+            return Ok(());
+        }
+
+        self.global_search = None;
+        self.state = AppState::Normal;
+        if !self.select_chat_by_id(chat.id, chat.archived).await? {
+            self.set_status_message("That chat no longer exists".to_string());
+        }
+        Ok(())
+    }
+
+    /// Reopen the app on another database. Everything loaded from the current one is replaced,
+    /// so this waits until nothing is being generated or synced. Returns whether it switched
+    async fn switch_database(&mut self, db_path: &Path) -> Result<bool> {
+        if !self.inference_in_progress_by_message_and_model.is_empty()
+            || !self.title_inference_in_progress_by_chat.is_empty()
+            || !self.continuing_message_ids.is_empty()
+            || self.model_refresh_provider_count.is_some()
+        {
+            self.set_status_message(
+                "Wait for running requests to finish before switching databases".to_string(),
+            );
+            return Ok(false);
+        }
+
+        // the new database brings its own draft, this one's shouldn't be lost
+        self.autosave_prompt_draft(true).await;
+        info!("Switching to database {}", db_path.display());
+        let database = Database::new(db_path).await?;
+        let (mut app, _) =
+            App::new(database, db_path.to_path_buf(), self.read_only, self.log_buffer.clone())
+                .await?;
+        // the event loop keeps reading from this app's channel
+        app.user_event_tx = self.user_event_tx.clone();
+        app.clipboard_unavailable = self.clipboard_unavailable;
+        *self = app;
+
+        if self.settings.refresh_models_on_startup {
+            self.spawn_model_refresh();
+        }
+        let name = db_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        self.set_status_message(format!("Switched to the {} database", name));
+        Ok(true)
+    }
+
+    /// Select and load a chat by id, dropping the search filter and loading more of the history
+    /// as needed to list it. Returns whether the chat was found
+    async fn select_chat_by_id(&mut self, chat_id: i64, archived: bool) -> Result<bool> {
+        if archived && !self.show_archived {
+            self.show_archived = true;
+            self.reload_chat_history().await?;
+        }
+        if !self.search_query.is_empty()
+            && !self.chat_history.iter().any(|chat| chat.id == chat_id)
+        {
+            self.clear_search_filter().await?;
+        }
+        loop {
+            if let Some(pos) = self.chat_history.iter().position(|chat| chat.id == chat_id) {
+                if pos != self.chat_history_index || self.current_chat.id != chat_id {
+                    self.chat_history_index = pos;
+                    self.load_selected_chat().await?;
+                }
+                return Ok(true);
+            }
+            if !self.load_more_chat_history().await? {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Use a named profile's models for the current chat, or for a new chat if this one already has messages
+    async fn apply_named_profile(&mut self, profile: NamedProfile) -> Result<()> {
+        // models that went away or lost their api key since the profile was saved are skipped
+        let model_ids: Vec<i64> = profile
+            .model_ids
+            .into_iter()
+            .filter(|model_id| self.available_models.contains_key(model_id))
+            .collect();
+        if model_ids.is_empty() {
+            self.set_status_message(format!("No models in \"{}\" are available", profile.name));
+            return Ok(());
+        }
+
+        if self.current_messages.values().any(|messages| !messages.is_empty()) {
+            self.create_new_chat().await?;
+        }
+
+        // like the model selection modal, unsaved chats get these written when the first prompt happens
+        if self.current_chat.id != 0 {
+            self.database
+                .set_chat_models(self.current_chat.id, model_ids.clone())
+                .await?;
+        }
+        self.current_chat_profile.model_ids = model_ids;
+        self.current_model_idx = 0;
+        for &model_id in &self.current_chat_profile.model_ids {
+            self.current_message_index.insert(model_id, 0);
+            self.current_chunk_idx.insert(model_id, 0);
+            self.chat_item_selections.insert(model_id, None);
+        }
+        Ok(())
+    }
+
+    /// Replace the selected failed response with a new one from the same model, sent the
+    /// conversation up to the prompt it answered
+    async fn retry_selected_message(&mut self) -> Result<()> {
+        let Some(&model_id) = self.current_chat_profile.model_ids.get(self.current_model_idx) else {
+            return Ok(());
+        };
+        let Some(selected_idx) = self.current_selected_message_index else {
+            return Ok(());
+        };
+        let Some(messages) = self.current_messages.get(&model_id) else {
+            return Ok(());
+        };
+        let Some(failed_message) = messages.get(selected_idx) else {
+            return Ok(());
+        };
+        if failed_message.chat_role == ChatRole::User
+            || (failed_message.error.is_none() && !failed_message.incomplete)
+        {
+            self.set_status_message("Only failed responses can be retried".to_string());
+            return Ok(());
+        }
+        let Some(origin_idx) = messages[..selected_idx]
+            .iter()
+            .rposition(|message| message.chat_role == ChatRole::User && !message.is_aside())
+        else {
+            return Ok(());
+        };
+
+        // the queued request would hand its conversation to this one, which wants an earlier one
+        let chat_id = self.current_chat.id;
+        if self
+            .inference_handles_by_chat_and_model
+            .get(&(chat_id, model_id))
+            .is_some_and(|handle| !handle.is_finished())
+        {
+            self.set_status_message("Wait for this model's pending response first".to_string());
+            return Ok(());
+        }
+
+        let failed_message_id = failed_message.id;
+        let origin = messages[origin_idx].clone();
+        let conversation = self
+            .with_unloaded_messages(model_id, messages[..=origin_idx].to_vec())
+            .await?;
+        // later prompts build on the latest conversation, so only retries of the latest prompt
+        // should be waited on by them. asides aren't prompts, they go out with the next one
+        let is_latest_prompt = messages[origin_idx + 1..]
+            .iter()
+            .all(|message| message.chat_role != ChatRole::User || message.is_aside());
+
+        self.database.delete_chat_message(failed_message_id).await?;
+        if let Some(messages) = self.current_messages.get_mut(&model_id) {
+            messages.retain(|message| message.id != failed_message_id);
+        }
+        // viewing the failed response moves the view to its prompt, where the new one will show up
+        if let Some(current_idx) = self.current_message_index.get_mut(&model_id) {
+            if *current_idx == selected_idx {
+                *current_idx = origin_idx;
+                self.current_chunk_idx.insert(model_id, 0);
+            } else if *current_idx > selected_idx {
+                *current_idx -= 1;
+            }
+        }
+        if let Some(selection) = self.chat_item_selections.get_mut(&model_id) {
+            *selection = None;
+        }
+
+        self.inference_handles_by_chat_and_model
+            .remove(&(chat_id, model_id));
+        self.spawn_inference_task(model_id, origin.id, origin.dt, chat_id, conversation, false)
+            .await;
+        if !is_latest_prompt {
+            // dropping the handle lets the task run on without anything waiting on it
+            self.inference_handles_by_chat_and_model
+                .remove(&(chat_id, model_id));
+        }
+        Ok(())
+    }
+
+    /// Ask the model to go on with a response that was cut off at the token limit. The continuation
+    /// is appended to the same message rather than added as a new one
+    async fn continue_selected_message(&mut self) -> Result<()> {
+        let Some(&model_id) = self.current_chat_profile.model_ids.get(self.current_model_idx) else {
+            return Ok(());
+        };
+        let Some(selected_idx) = self.current_selected_message_index else {
+            return Ok(());
+        };
+        let Some(messages) = self.current_messages.get(&model_id) else {
+            return Ok(());
+        };
+        let Some(message) = messages.get(selected_idx) else {
+            return Ok(());
+        };
+        if !message.was_cut_off() {
+            self.set_status_message(
+                "Only responses cut off at the token limit can be continued".to_string(),
+            );
+            return Ok(());
+        }
+        if self.continuing_message_ids.contains(&message.id) {
+            return Ok(());
+        }
+        // a response from the fallback chain is finished by the model that started it
+        let answering_model_id = message.answered_by_model_id.unwrap_or(model_id);
+        let Some(model) = self.available_models.get(&answering_model_id).cloned() else {
+            self.set_status_message("This model is unavailable".to_string());
+            return Ok(());
+        };
+        let Some(provider_client) = self.provider_clients.get(&model.provider_id).cloned() else {
+            return Ok(());
+        };
+
+        let chat_id = self.current_chat.id;
+        let message = message.clone();
+        let mut conversation = self
+            .with_unloaded_messages(model_id, messages[..=selected_idx].to_vec())
+            .await?;
+        conversation.push(ChatMessage::new_user_message(chat_id, CONTINUE_PROMPT.to_string()));
+        // in JSON mode the model would start a new object instead of finishing this one
+        let options = generation_options_for(false, &self.current_chat);
+        let system_prompt = system_prompt_for(&self.current_chat, false);
+        model.truncate_to_context_window(&mut conversation, &system_prompt);
+
+        self.continuing_message_ids.insert(message.id);
+        let tx = self.user_event_tx.clone();
+        let database = self.database.clone();
+        let semaphore = self.inference_semaphore.clone();
+        let wait_for_rate_limit_reset = self.settings.wait_for_rate_limit_reset;
+        let remove_think_tokens = self.removes_think_tokens(&model);
+        let request_log = self.settings.request_log;
+        tokio::spawn(async move {
+            if wait_for_rate_limit_reset
+                && let Some(wait) = provider_client
+                    .rate_limit()
+                    .and_then(|rate_limit| rate_limit.wait_before_next_request())
+            {
+                tokio::time::sleep(wait).await;
+            }
+            let permit = semaphore.acquire_owned().await.ok();
+            let request_started = Instant::now();
+            let result = provider_client
+                .run(&model.model, &system_prompt, &conversation, vec![], remove_think_tokens, &options)
+                .await;
+            drop(permit);
+            if request_log {
+                let logged_result = result.as_ref().map_err(|e| e.to_string());
+                log_request(
+                    &database,
+                    chat_id,
+                    &model,
+                    conversation.len(),
+                    request_started,
+                    logged_result,
+                )
+                .await;
+            }
+
+            let (message, error) = match result {
+                Ok(generation_result) => {
+                    let mut continued = message;
+                    let continuation = generation_result.content.unwrap_or_default();
+                    continued.content =
+                        Some(continued.content.unwrap_or_default() + &continuation);
+                    continued.response_dt = Some(chrono::Utc::now().timestamp_millis());
+                    // usage covers every request that went into the message, unknown if any is
+                    let add = |a: Option<i64>, b: Option<i64>| a.zip(b).map(|(a, b)| a + b);
+                    continued.prompt_tokens =
+                        add(continued.prompt_tokens, generation_result.prompt_tokens);
+                    continued.completion_tokens =
+                        add(continued.completion_tokens, generation_result.completion_tokens);
+                    continued.finish_reason = generation_result.finish_reason;
+                    if let Err(e) = database.complete_chat_message(continued.id, &continued).await {
+                        info!("Couldn't write continued message to database: {}", e);
+                    }
+                    (continued, None)
+                }
+                Err(e) => {
+                    error!("Continuing message {} failed: {}", message.id, e);
+                    (message, Some(e.to_string()))
+                }
+            };
+            let _ = tx.send(InferenceEvent::ContinuationComplete {
+                chat_id,
+                model_id,
+                message: Box::new(message),
+                error,
+            });
+        });
+        Ok(())
+    }
+
+    fn open_error_detail_dialog(&mut self) {
+        let Some(message) = self
+            .current_selected_message_index
+            .and_then(|idx| self.get_current_messages().and_then(|messages| messages.get(idx)))
+        else {
+            return;
+        };
+        let Some(error) = message.error.clone() else {
+            self.set_status_message("No error on this message".to_string());
+            return;
+        };
+
+        let model = message.model_id.and_then(|model_id| self.all_models.get(&model_id));
+        let model_name = model
+            .map(|m| m.display_name().to_string())
+            .unwrap_or_else(|| "Unknown Model".to_string());
+        let provider_name = model
+            .and_then(|m| self.provider_names.get(&m.provider_id).cloned())
+            .unwrap_or_else(|| "Unknown Provider".to_string());
+
+        self.error_detail = Some((model_name, provider_name, error));
+        self.error_detail_scroll = 0;
+        self.state = AppState::ErrorDetail;
+    }
+
+    async fn handle_error_detail_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('e') => {
+                self.error_detail = None;
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.error_detail_scroll = self.error_detail_scroll.saturating_add(1);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.error_detail_scroll = self.error_detail_scroll.saturating_sub(1);
+            }
+            KeyCode::Char('g') => {
+                self.error_detail_scroll = 0;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Count the model's provider's failed requests in a row. Once there are
+    /// provider_failure_threshold of them the provider is marked down like an unreachable one, so
+    /// its models can't be sent to until the periodic re-check finds it back. A success resets the
+    /// count
+    fn record_provider_result(&mut self, model_id: i64, failed: bool) {
+        let Some(provider_id) = self.all_models.get(&model_id).map(|model| model.provider_id) else {
+            return;
+        };
+        if !failed {
+            self.provider_failure_streaks.remove(&provider_id);
+            return;
+        }
+
+        let streak = self.provider_failure_streaks.entry(provider_id).or_default();
+        *streak += 1;
+        let threshold = self.settings.provider_failure_threshold;
+        if threshold == 0
+            || *streak < threshold
+            || !self.providers_marked_down.insert(provider_id)
+        {
+            return;
+        }
+
+        self.available_models.retain(|_, model| model.provider_id != provider_id);
+        // give it a full interval before the first re-check
+        self.last_provider_recheck = Instant::now();
+        let provider_name = self
+            .provider_names
+            .get(&provider_id)
+            .cloned()
+            .unwrap_or_else(|| format!("provider {}", provider_id));
+        self.set_status_message(format!(
+            "{} failed {} requests in a row, holding off until it responds again",
+            provider_name, threshold
+        ));
+    }
+
+    /// Put up a status message when the model's provider reported it's close to a rate limit
+    fn warn_if_near_rate_limit(&mut self, model_id: i64) {
+        let Some(provider_id) = self.all_models.get(&model_id).map(|model| model.provider_id) else {
+            return;
+        };
+        let Some(rate_limit) = self
+            .provider_clients
+            .get(&provider_id)
+            .and_then(|client| client.rate_limit())
+            .filter(|rate_limit| rate_limit.is_near_limit(RATE_LIMIT_WARNING_FRACTION))
+        else {
+            return;
+        };
+
+        let provider_name = self
+            .provider_names
+            .get(&provider_id)
+            .cloned()
+            .unwrap_or_else(|| format!("provider {}", provider_id));
+        let mut remaining = Vec::new();
+        if let Some(requests) = rate_limit.remaining_requests {
+            remaining.push(format!("{} requests", requests));
+        }
+        if let Some(tokens) = rate_limit.remaining_tokens {
+            remaining.push(format!("{} tokens", tokens));
+        }
+        let mut message = format!("Close to the rate limit for {}", provider_name);
+        if !remaining.is_empty() {
+            message.push_str(&format!(": {} left", remaining.join(", ")));
+        }
+        if let Some(reset) = rate_limit.reset_requests.max(rate_limit.reset_tokens) {
+            message.push_str(&format!(", resets in {}s", reset.as_secs().max(1)));
+        }
+        self.set_status_message(message);
+    }
+
+    /// Show the request the current model would get for the conversation as it stands
+    async fn open_request_preview_dialog(&mut self) -> Result<()> {
+        let Some(model) = self
+            .current_chat_profile
+            .model_ids
+            .get(self.current_model_idx)
+            .and_then(|model_id| self.available_models.get(model_id))
+            .cloned()
+        else {
+            self.set_status_message("Current model is not available".to_string());
+            return Ok(());
+        };
+        let Some(provider_client) = self.provider_clients.get(&model.provider_id).cloned() else {
+            self.set_status_message("Current model's provider is not available".to_string());
+            return Ok(());
+        };
+
+        // mirror spawn_inference_task so the preview matches what actually gets sent
+        let json_mode = self.current_chat.json_mode && model.supports_json_mode;
+        let system_prompt = system_prompt_for(&self.current_chat, json_mode);
+        let loaded = self.get_current_messages().cloned().unwrap_or_default();
+        let mut conversation = self.with_unloaded_messages(model.id, loaded).await?;
+        model.truncate_to_context_window(&mut conversation, &system_prompt);
+
+        match provider_client.preview_request(
+            &model.model,
+            &system_prompt,
+            &conversation,
+            &generation_options_for(json_mode, &self.current_chat),
+        ) {
+            Ok(request) => {
+                self.request_preview = Some((model.model.clone(), request));
+                self.request_preview_scroll = 0;
+                self.state = AppState::RequestPreview;
+            }
+            Err(e) => {
+                error!("Failed to build request preview: {}", e);
+                self.set_status_message(format!("Failed to build request preview: {}", e));
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_request_preview_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('D') => {
+                self.request_preview = None;
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.request_preview_scroll = self.request_preview_scroll.saturating_add(1);
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.request_preview_scroll = self.request_preview_scroll.saturating_sub(1);
+            }
+            KeyCode::Char('d') | KeyCode::PageDown => {
+                self.request_preview_scroll = self.request_preview_scroll.saturating_add(10);
+            }
+            KeyCode::Char('u') | KeyCode::PageUp => {
+                self.request_preview_scroll = self.request_preview_scroll.saturating_sub(10);
+            }
+            KeyCode::Char('g') => {
+                self.request_preview_scroll = 0;
+            }
+            KeyCode::Char('y') => {
+                if let Some((_, request)) = self.request_preview.clone() {
+                    self.copy_to_clipboard(request);
+                }
+            }
+            KeyCode::Char('m') => {
+                // just the messages array, as pasted into a bug report or an API playground
+                let Some((_, request)) = &self.request_preview else {
+                    return Ok(());
+                };
+                match serde_json::from_str::<serde_json::Value>(request) {
+                    Ok(request) => match request.get("messages") {
+                        Some(messages) => {
+                            let messages = serde_json::to_string_pretty(messages)?;
+                            self.copy_to_clipboard(messages);
+                        }
+                        None => self.set_status_message(
+                            "This request has no messages to copy".to_string(),
+                        ),
+                    },
+                    Err(e) => self.set_status_message(format!(
+                        "Couldn't read the request as JSON to copy its messages: {}",
+                        e
+                    )),
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_log_viewer_key(&mut self, key: KeyEvent) -> Result<()> {
+        // scrolled this far the oldest line is on the top row
+        let line_count = self.log_buffer.as_ref().map_or(0, LogBuffer::line_count);
+        let oldest = line_count.saturating_sub(self.log_viewer_rows);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('L') => {
+                self.state = AppState::Normal;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.log_viewer_scroll = (self.log_viewer_scroll + 1).min(oldest);
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.log_viewer_scroll = self.log_viewer_scroll.saturating_sub(1);
+            }
+            KeyCode::Char('u') | KeyCode::PageUp => {
+                self.log_viewer_scroll = (self.log_viewer_scroll + 10).min(oldest);
+            }
+            KeyCode::Char('d') | KeyCode::PageDown => {
+                self.log_viewer_scroll = self.log_viewer_scroll.saturating_sub(10);
+            }
+            KeyCode::Char('g') => {
+                self.log_viewer_scroll = oldest;
+            }
+            KeyCode::Char('G') => {
+                self.log_viewer_scroll = 0;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_unavailable_models_error_key(&mut self, key: KeyEvent) -> Result<()> {
+        // s or Enter sends to the models that are available, any other key dismisses the dialog
+        self.state = AppState::Normal;
+        self.unavailable_models_info.clear();
+        if matches!(key.code, KeyCode::Char('s') | KeyCode::Enter)
+            && self.has_available_chat_model()
+        {
+            self.submit_message_to_models(true).await?;
+        }
+
+        // If the current chat is invalid, we stay on it but the user can navigate away
+        Ok(())
+    }
+
+    async fn clear_search_filter(&mut self) -> Result<()> {
         // Remember the currently selected chat ID
         let selected_chat_id = self.chat_history.get(self.chat_history_index).map(|c| c.id);
 
@@ -1653,7 +5446,7 @@ impl App {
         self.search_textarea = EditorState::default();
 
         // Reload all chats
-        self.chat_history = self.database.get_all_chats().await?;
+        self.chat_history = self.load_chat_history().await?;
 
         // Find and restore the selected chat
         if let Some(chat_id) = selected_chat_id {
@@ -1670,3 +5463,741 @@ impl App {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::tests::{TestDatabase, test_database};
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+    use std::io::Write;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    /// Answers each request with the next scripted reply, an error once they run out, and keeps
+    /// the conversations it was sent
+    #[derive(Default)]
+    struct ScriptedProvider {
+        replies: std::sync::Mutex<VecDeque<GenerationResult>>,
+        requests: std::sync::Mutex<Vec<Vec<ChatMessage>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ProviderClient for ScriptedProvider {
+        async fn run(
+            &self,
+            _model: &str,
+            _system_prompt: &str,
+            conversation: &[ChatMessage],
+            _available_tools: Vec<&dyn crate::model::tool::Tool>,
+            _remove_think_tokens: bool,
+            _options: &GenerationOptions,
+        ) -> eyre::Result<GenerationResult> {
+            self.requests.lock().unwrap().push(conversation.to_vec());
+            self.replies.lock().unwrap().pop_front().ok_or_else(|| eyre::eyre!("no reply scripted"))
+        }
+
+        async fn list_models(&self) -> eyre::Result<Vec<ListedModel>> {
+            Ok(Vec::new())
+        }
+
+        fn preview_request(
+            &self,
+            _model: &str,
+            _system_prompt: &str,
+            _conversation: &[ChatMessage],
+            _options: &GenerationOptions,
+        ) -> eyre::Result<String> {
+            Ok(String::new())
+        }
+
+        fn rate_limit(&self) -> Option<crate::provider::provider::RateLimitStatus> {
+            None
+        }
+    }
+
+    fn scripted_reply(content: &str, other_choices: &[&str]) -> GenerationResult {
+        GenerationResult {
+            content: Some(content.to_string()),
+            tool_calls: Vec::new(),
+            reasoning_content: None,
+            reasoning_tokens: None,
+            reasoning_tokens_estimated: false,
+            prompt_tokens: None,
+            completion_tokens: None,
+            finish_reason: Some("stop".to_string()),
+            other_choices: other_choices.iter().map(|choice| choice.to_string()).collect(),
+        }
+    }
+
+    /// Make `provider` answer for a model of a new chat that's open in the app. The events the
+    /// requests send come out of the returned receiver
+    async fn scripted_chat(
+        app: &mut App,
+        database: &Database,
+        provider: Arc<ScriptedProvider>,
+    ) -> (Model, mpsc::UnboundedReceiver<InferenceEvent>) {
+        let model = database.get_all_models(false).await.unwrap().remove(0);
+        app.provider_clients.insert(model.provider_id, provider);
+        app.available_models.insert(model.id, model.clone());
+        app.current_chat.id = database.create_chat(None).await.unwrap();
+        app.current_chat_profile.model_ids = vec![model.id];
+        app.current_model_idx = 0;
+        app.current_messages.insert(model.id, Vec::new());
+        let (tx, rx) = mpsc::unbounded_channel();
+        app.user_event_tx = tx;
+        (model, rx)
+    }
+
+    /// Make a model of another provider, answered by `provider`, the fallback for every model
+    async fn scripted_fallback(
+        app: &mut App,
+        database: &Database,
+        model: &Model,
+        provider: Arc<ScriptedProvider>,
+    ) -> Model {
+        let fallback_model = database
+            .get_all_models(false)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|fallback_model| fallback_model.provider_id != model.provider_id)
+            .unwrap();
+        app.provider_clients.insert(fallback_model.provider_id, provider);
+        app.available_models.insert(fallback_model.id, fallback_model.clone());
+        let provider_name = &app.provider_names[&fallback_model.provider_id];
+        let fallback_models = format!("{}/{}", provider_name, fallback_model.model);
+        app.settings.apply("fallback_models", &fallback_models).unwrap();
+        fallback_model
+    }
+
+    /// Send a prompt to the scripted model and hand the app the events until it's answered
+    async fn send_scripted_prompt(
+        app: &mut App,
+        database: &Database,
+        rx: &mut mpsc::UnboundedReceiver<InferenceEvent>,
+        model_id: i64,
+        content: &str,
+    ) {
+        let chat_id = app.current_chat.id;
+        let mut prompt = ChatMessage::new_user_message(chat_id, content.to_string());
+        prompt.id = database.add_chat_message(&prompt).await.unwrap();
+        let messages = app.current_messages.get_mut(&model_id).unwrap();
+        messages.push(prompt.clone());
+        let conversation = messages.clone();
+        app.spawn_inference_task(model_id, prompt.id, prompt.dt, chat_id, conversation, false)
+            .await;
+        answer_scripted_request(app, rx).await;
+    }
+
+    /// Hand the app the events of a scripted request until it's answered
+    async fn answer_scripted_request(
+        app: &mut App,
+        rx: &mut mpsc::UnboundedReceiver<InferenceEvent>,
+    ) {
+        loop {
+            let event = rx.recv().await.unwrap();
+            let answered = matches!(event, InferenceEvent::InferenceComplete { .. });
+            app.handle_inference_event(event).await.unwrap();
+            if answered {
+                break;
+            }
+        }
+    }
+
+    /// An app on a fresh database. The database is returned too, it's removed once dropped
+    async fn test_app() -> (App, TestDatabase) {
+        let database = test_database().await;
+        let (app, _) = App::new(
+            Database {
+                pool: database.pool.clone(),
+            },
+            PathBuf::from("test.db"),
+            false,
+            Some(LogBuffer::new(10)),
+        )
+        .await
+        .unwrap();
+        (app, database)
+    }
+
+    #[test]
+    fn test_step_chunks_crosses_multi_chunk_messages() {
+        let chunk_counts = [3, 1, 4, 2];
+        let chunk_count = |msg_idx: usize| chunk_counts[msg_idx];
+
+        // every position in order, so stepping one at a time should visit each of them
+        let positions: Vec<(usize, usize)> = chunk_counts
+            .iter()
+            .enumerate()
+            .flat_map(|(msg_idx, &count)| (0..count).map(move |chunk_idx| (msg_idx, chunk_idx)))
+            .collect();
+
+        for (from, &position) in positions.iter().enumerate() {
+            for steps in 0..positions.len() as isize + 2 {
+                let forward = (from + steps as usize).min(positions.len() - 1);
+                assert_eq!(step_chunks(position, steps, 4, chunk_count), positions[forward]);
+                let backward = from.saturating_sub(steps as usize);
+                assert_eq!(step_chunks(position, -steps, 4, chunk_count), positions[backward]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_step_chunks_clamps_unresolved_chunk() {
+        let chunk_count = |msg_idx: usize| [3, 2][msg_idx];
+        // after G the chunk index is usize::MAX until the next render
+        assert_eq!(step_chunks((1, usize::MAX), -1, 2, chunk_count), (1, 0));
+        assert_eq!(step_chunks((1, usize::MAX), -2, 2, chunk_count), (0, 2));
+        assert_eq!(step_chunks((1, usize::MAX), 1, 2, chunk_count), (1, 1));
+    }
+
+    #[test]
+    fn test_step_prompts_skips_responses() {
+        let user = |content: &str| ChatMessage::new_user_message(1, content.to_string());
+        let assistant =
+            |content: &str| ChatMessage::new_assistant_message(1, 1, content.to_string(), 0);
+        // a prompt can go without a reply, e.g. for a model added to the chat later
+        let messages = [user("a"), assistant("a"), user("b"), user("c"), assistant("c")];
+
+        assert_eq!(step_prompts(&messages, 0, 1), 2);
+        assert_eq!(step_prompts(&messages, 1, 1), 2);
+        assert_eq!(step_prompts(&messages, 0, 2), 3);
+        assert_eq!(step_prompts(&messages, 0, 5), 3);
+        assert_eq!(step_prompts(&messages, 3, 1), 3);
+        assert_eq!(step_prompts(&messages, 4, -1), 3);
+        assert_eq!(step_prompts(&messages, 4, -3), 0);
+        assert_eq!(step_prompts(&messages, 1, -1), 0);
+        assert_eq!(step_prompts(&messages, 0, -1), 0);
+    }
+
+    #[test]
+    fn test_sanitize_title_cleans_messy_model_output() {
+        assert_eq!(
+            sanitize_title("\"Rust Borrow Checker Basics\""),
+            Some("Rust Borrow Checker Basics".to_string())
+        );
+        assert_eq!(
+            sanitize_title("## **Planning a Trip to Japan**"),
+            Some("Planning a Trip to Japan".to_string())
+        );
+        assert_eq!(
+            sanitize_title("Title: Debugging Async Tasks.\n\nThis title captures the main topic."),
+            Some("Debugging Async Tasks".to_string())
+        );
+        assert_eq!(
+            sanitize_title("<think>Soup.</think>\n\n“Easy Weeknight Soup Recipes”"),
+            Some("Easy Weeknight Soup Recipes".to_string())
+        );
+        // longer titles are cut to the word limit
+        assert_eq!(
+            sanitize_title("A Very Long Title That Goes On And On"),
+            Some("A Very Long Title That Goes".to_string())
+        );
+        assert_eq!(sanitize_title("  \n \"\" \n"), None);
+        assert_eq!(sanitize_title("**"), None);
+    }
+
+    #[test]
+    fn test_stop_sequences_round_trip_through_the_edit_text() {
+        assert_eq!(parse_stop_sequences(" END ,\\n\\n, ,\\tx,"), ["END", "\n\n", "\tx"]);
+        assert!(parse_stop_sequences("").is_empty());
+
+        let stop_sequences = vec!["###".to_string(), "\nUser:".to_string(), "\t".to_string()];
+        let formatted = format_stop_sequences(&stop_sequences);
+        assert_eq!(formatted, "###, \\nUser:, \\t");
+        assert_eq!(parse_stop_sequences(&formatted), stop_sequences);
+    }
+
+    #[tokio::test]
+    async fn test_recall_prompt_walks_the_history_and_back_to_an_empty_prompt() {
+        let (mut app, _database) = test_app().await;
+        set_editor_state_text(&mut app.textarea, String::new());
+        app.remember_prompt("first".to_string());
+        app.remember_prompt("second".to_string());
+        app.remember_prompt("second".to_string()); // repeats aren't stored twice
+        let prompt = |app: &App| editor_state_to_string(&app.textarea);
+
+        assert!(app.recall_prompt(true));
+        assert_eq!(prompt(&app), "second");
+        assert!(app.recall_prompt(true));
+        assert_eq!(prompt(&app), "first");
+        // the oldest prompt stays put
+        assert!(app.recall_prompt(true));
+        assert_eq!(prompt(&app), "first");
+
+        assert!(app.recall_prompt(false));
+        assert_eq!(prompt(&app), "second");
+        // past the newest prompt the editor is empty again, and Down goes back to navigation
+        assert!(app.recall_prompt(false));
+        assert_eq!(prompt(&app), "");
+        assert!(!app.recall_prompt(false));
+    }
+
+    #[tokio::test]
+    async fn test_recall_prompt_stops_once_the_prompt_is_edited() {
+        let (mut app, _database) = test_app().await;
+        set_editor_state_text(&mut app.textarea, String::new());
+        app.remember_prompt("first".to_string());
+        app.remember_prompt("second".to_string());
+
+        assert!(app.recall_prompt(true));
+        set_editor_state_text(&mut app.textarea, "second, edited".to_string());
+        // the edit is kept, Up and Down move the cursor like they do in any draft
+        assert!(!app.recall_prompt(true));
+        assert!(!app.recall_prompt(false));
+        assert_eq!(editor_state_to_string(&app.textarea), "second, edited");
+        assert_eq!(app.prompt_history_index, None);
+    }
+
+    #[tokio::test]
+    async fn test_toggling_a_message_keeps_it_selected() {
+        let (mut app, _database) = test_app().await;
+        let model_id = 1;
+        app.current_chat_profile.model_ids = vec![model_id];
+        app.current_model_idx = 0;
+        let mut messages = vec![
+            ChatMessage::new_user_message(1, "prompt".to_string()),
+            ChatMessage::new_assistant_message(1, model_id, "**reply**".to_string(), 0),
+            ChatMessage::new_user_message(1, "next".to_string()),
+        ];
+        for (id, message) in messages.iter_mut().enumerate() {
+            message.id = id as i64 + 1;
+        }
+        app.current_messages.insert(model_id, messages);
+        // the reply's second chunk was selected, with the view starting at the prompt
+        app.current_message_index.insert(model_id, 0);
+        app.chat_item_selections.insert(model_id, Some(2));
+        app.current_selected_message_index = Some(1);
+
+        app.toggle_selected_message_in(|app| &mut app.raw_message_ids);
+        assert!(app.raw_message_ids.contains(&2));
+        assert_eq!(app.current_message_index[&model_id], 1);
+        assert_eq!(app.chat_item_selections[&model_id], Some(0));
+
+        // render finds the same message under the selection, so a second press toggles it back
+        app.toggle_selected_message_in(|app| &mut app.raw_message_ids);
+        assert!(app.raw_message_ids.is_empty());
+        assert_eq!(app.chat_item_selections[&model_id], Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_copying_preview_messages_says_why_it_cant() {
+        let (mut app, _database) = test_app().await;
+        let press_m = KeyEvent::new(KeyCode::Char('m'), KeyModifiers::NONE);
+        let status = |app: &App| app.status_message.as_ref().map(|(message, _)| message.clone());
+
+        app.request_preview = Some(("gpt-5".to_string(), r#"{"model": "gpt-5"}"#.to_string()));
+        app.handle_request_preview_key(press_m).await.unwrap();
+        assert_eq!(status(&app).as_deref(), Some("This request has no messages to copy"));
+
+        app.request_preview = Some(("gpt-5".to_string(), "not json".to_string()));
+        app.handle_request_preview_key(press_m).await.unwrap();
+        assert!(status(&app).unwrap().starts_with("Couldn't read the request as JSON"));
+    }
+
+    #[tokio::test]
+    async fn test_configured_fallback_models() {
+        let (mut app, database) = test_app().await;
+        let providers = database.get_providers().await.unwrap();
+        let template = database.get_all_models(false).await.unwrap().remove(0);
+        let mut model = |id: i64, provider_name: &str, name: &str| {
+            let provider =
+                providers.iter().find(|provider| provider.name == provider_name).unwrap();
+            let client: Arc<dyn ProviderClient> = Arc::new(OpenAIProvider::new(provider.clone()));
+            app.provider_clients.insert(provider.id, client);
+            app.provider_names.insert(provider.id, provider.name.clone());
+            let model = Model {
+                id,
+                provider_id: provider.id,
+                model: name.to_string(),
+                ..template.clone()
+            };
+            app.available_models.insert(id, model.clone());
+            model
+        };
+        let gpt = model(101, "OpenAI", "gpt-5");
+        let llama = model(102, "Groq", "meta-llama/llama-4-scout");
+        let mini = model(103, "OpenAI", "gpt-5-mini");
+        let other = model(104, "Groq", "qwen3");
+        app.settings.apply(
+            "fallback_models",
+            "OpenAI/o3, OpenAI/gpt-5, Groq/meta-llama/llama-4-scout, OpenAI/gpt-5-mini",
+        )
+        .unwrap();
+        let fallbacks = |app: &App, model: &Model| {
+            app.configured_fallback_models(model)
+                .into_iter()
+                .map(|(fallback, _, _)| fallback.id)
+                .collect::<Vec<_>>()
+        };
+
+        // a model outside the list falls back to all of it, o3 isn't available so it's skipped
+        assert_eq!(fallbacks(&app, &other), [gpt.id, llama.id, mini.id]);
+        // a model in the list only goes on to the ones after it, the chain doesn't loop back
+        assert_eq!(fallbacks(&app, &gpt), [llama.id, mini.id]);
+        assert_eq!(fallbacks(&app, &llama), [mini.id]);
+        assert!(fallbacks(&app, &mini).is_empty());
+
+        // listed twice, the model still isn't retried on itself
+        app.settings.apply("fallback_models", "OpenAI/gpt-5, Groq/qwen3, OpenAI/gpt-5").unwrap();
+        assert_eq!(fallbacks(&app, &gpt), [other.id]);
+    }
+
+    #[test]
+    fn test_older_page_before_dt() {
+        // responses share their prompt's dt, so this is two prompts
+        let page = [
+            ChatMessage { dt: 1, ..ChatMessage::new_user_message(1, "first".to_string()) },
+            ChatMessage::new_assistant_message(1, 1, "reply".to_string(), 1),
+            ChatMessage { dt: 2, ..ChatMessage::new_user_message(1, "second".to_string()) },
+            ChatMessage::new_assistant_message(1, 1, "reply".to_string(), 2),
+        ];
+        // a full page may have more before it, a short one reached the first prompt
+        assert_eq!(older_page_before_dt(&page, 2), Some(1));
+        assert_eq!(older_page_before_dt(&page, 3), None);
+        assert_eq!(older_page_before_dt(&page[2..], 1), Some(2));
+        assert_eq!(older_page_before_dt(&[], 1), None);
+    }
+
+    #[tokio::test]
+    async fn test_recall_prompt_reaches_past_the_loaded_page() {
+        let (mut app, database) = test_app().await;
+        let chat_id = database.create_chat(None).await.unwrap();
+        for (dt, prompt) in [(1000, "first"), (2000, "second"), (3000, "third")] {
+            let message = ChatMessage::new_user_message(chat_id, prompt.to_string());
+            database.add_chat_message(&ChatMessage { dt, ..message }).await.unwrap();
+        }
+        let aside = ChatMessage::new_aside_message(chat_id, "aside".to_string());
+        database.add_chat_message(&ChatMessage { dt: 2500, ..aside }).await.unwrap();
+
+        app.settings.chat_message_page_size = 1;
+        app.reload_chat_history().await.unwrap();
+        app.chat_history_index =
+            app.chat_history.iter().position(|chat| chat.id == chat_id).unwrap();
+        app.load_selected_chat().await.unwrap();
+        assert_eq!(app.older_messages_before_dt, Some(3000));
+
+        set_editor_state_text(&mut app.textarea, String::new());
+        for expected in ["third", "second", "first"] {
+            assert!(app.recall_prompt(true));
+            assert_eq!(editor_state_to_string(&app.textarea), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_global_search_spans_the_databases_next_to_the_current_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let main_path = dir.path().join("main.db");
+        let other_path = dir.path().join("other.db");
+        let notes_path = dir.path().join("notes.db");
+        for (path, text) in [(&main_path, "giraffe in main"), (&other_path, "giraffe in other")] {
+            let database = Database::new(path).await.unwrap();
+            let chat_id = database.create_chat(Some(text.to_string())).await.unwrap();
+            database
+                .add_chat_message(&ChatMessage::new_user_message(chat_id, text.to_string()))
+                .await
+                .unwrap();
+            database.pool.close().await;
+        }
+        // a .db file that isn't shore's is left out, and left alone
+        let options = SqliteConnectOptions::new().filename(&notes_path).create_if_missing(true);
+        let notes = SqlitePool::connect_with(options).await.unwrap();
+        sqlx::query("CREATE TABLE note (text TEXT)").execute(&notes).await.unwrap();
+        notes.close().await;
+
+        let database = Database::new(&main_path).await.unwrap();
+        let (mut app, _) =
+            App::new(database, main_path.clone(), false, Some(LogBuffer::new(10))).await.unwrap();
+        app.open_global_search().await.unwrap();
+        let search = app.global_search.as_mut().unwrap();
+        let paths: Vec<&PathBuf> = search.databases.iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, [&main_path, &other_path]);
+
+        set_editor_state_text(&mut search.textarea, "giraffe".to_string());
+        app.run_global_search().await;
+        let search = app.global_search.as_ref().unwrap();
+        let mut found: Vec<(&PathBuf, Option<&str>)> = search
+            .results
+            .iter()
+            .map(|(path, chat)| (path, chat.title.as_deref()))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [(&main_path, Some("giraffe in main")), (&other_path, Some("giraffe in other"))]
+        );
+
+        // opening them for the search didn't migrate them or switch them to WAL
+        let notes = SqlitePool::connect_with(SqliteConnectOptions::new().filename(&notes_path))
+            .await
+            .unwrap();
+        let tables: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table'")
+                .fetch_all(&notes)
+                .await
+                .unwrap();
+        assert_eq!(tables, ["note"]);
+        let journal_mode: String =
+            sqlx::query_scalar("PRAGMA journal_mode").fetch_one(&notes).await.unwrap();
+        assert_eq!(journal_mode, "delete");
+    }
+
+    #[tokio::test]
+    async fn test_recheck_only_announces_providers_that_answered() {
+        let (mut app, _database) = test_app().await;
+        let provider_id = |app: &App, name: &str| {
+            app.providers.values().find(|provider| provider.name == name).unwrap().id
+        };
+        let openai = provider_id(&app, "OpenAI");
+        let groq = provider_id(&app, "Groq");
+        let perplexity = provider_id(&app, "Perplexity");
+        let anthropic = provider_id(&app, "Anthropic");
+        app.providers_marked_down.extend([openai, groq, perplexity]);
+        // Anthropic wasn't marked down, so the re-check didn't ask it
+        app.provider_failure_streaks
+            .extend([(openai, 3), (groq, 3), (perplexity, 3), (anthropic, 1)]);
+
+        app.handle_inference_event(InferenceEvent::ModelsRefreshed {
+            models: Vec::new(),
+            added: 0,
+            removed: 0,
+            answered_provider_ids: vec![openai],
+            failed_provider_ids: vec![groq],
+            recheck_only: true,
+        })
+        .await
+        .unwrap();
+
+        // Perplexity has no models endpoint, it gets another try but wasn't checked so isn't announced
+        assert_eq!(app.providers_marked_down, HashSet::from([groq]));
+        assert_eq!(app.status_message.as_ref().unwrap().0, "Back online: OpenAI");
+        // only OpenAI answered, so it's the only one to lose its streak
+        assert_eq!(
+            app.provider_failure_streaks,
+            HashMap::from([(groq, 3), (perplexity, 3), (anthropic, 1)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jumping_to_the_oldest_logs_fills_the_page() {
+        let (mut app, _database) = test_app().await;
+        let log_buffer = LogBuffer::new(100);
+        for i in 0..30 {
+            writeln!(log_buffer.make_writer(), "line {}", i).unwrap();
+        }
+        app.log_buffer = Some(log_buffer.clone());
+        app.log_viewer_rows = 10;
+
+        app.handle_log_viewer_key(KeyEvent::from(KeyCode::Char('g'))).await.unwrap();
+        assert_eq!(app.log_viewer_scroll, 20);
+        assert_eq!(log_buffer.tail(app.log_viewer_scroll, 10).first().unwrap(), "line 0");
+        // already showing the oldest page, scrolling up stays put
+        app.handle_log_viewer_key(KeyEvent::from(KeyCode::Char('k'))).await.unwrap();
+        assert_eq!(app.log_viewer_scroll, 20);
+        app.handle_log_viewer_key(KeyEvent::from(KeyCode::Char('G'))).await.unwrap();
+        assert_eq!(app.log_viewer_scroll, 0);
+    }
+
+    #[tokio::test]
+    async fn test_deleting_a_library_prompt_asks_first() {
+        let (mut app, database) = test_app().await;
+        database.save_library_prompt(None, "Coding", "You write code.").await.unwrap();
+        app.library_prompts = database.get_library_prompts().await.unwrap();
+        app.prompt_library_index = 0;
+        app.state = AppState::PromptLibrary;
+        let press = |code| KeyEvent::from(code);
+
+        app.handle_key_event(press(KeyCode::Char('d'))).await.unwrap();
+        assert_eq!(app.state, AppState::PromptLibraryDeleteConfirmation);
+        app.handle_key_event(press(KeyCode::Char('n'))).await.unwrap();
+        assert_eq!(app.state, AppState::PromptLibrary);
+        assert_eq!(database.get_library_prompts().await.unwrap().len(), 1);
+
+        app.handle_key_event(press(KeyCode::Char('x'))).await.unwrap();
+        app.handle_key_event(press(KeyCode::Char('y'))).await.unwrap();
+        assert_eq!(app.state, AppState::PromptLibrary);
+        assert!(app.library_prompts.is_empty());
+        assert!(database.get_library_prompts().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retrying_the_latest_prompt_ignores_asides_after_it() {
+        let (mut app, database) = test_app().await;
+        let provider = Arc::new(ScriptedProvider::default());
+        provider.replies.lock().unwrap().push_back(scripted_reply("retried", &[]));
+        let (model, mut rx) = scripted_chat(&mut app, &database, provider.clone()).await;
+        let chat_id = app.current_chat.id;
+
+        let mut messages = vec![
+            ChatMessage { dt: 1000, ..ChatMessage::new_user_message(chat_id, "prompt".to_string()) },
+            ChatMessage::new_assistant_message_with_error(
+                chat_id,
+                model.id,
+                "error".to_string(),
+                1000,
+            ),
+            ChatMessage { dt: 2000, ..ChatMessage::new_aside_message(chat_id, "aside".to_string()) },
+        ];
+        for message in messages.iter_mut() {
+            message.id = database.add_chat_message(message).await.unwrap();
+        }
+        app.current_messages.insert(model.id, messages);
+        app.current_selected_message_index = Some(1);
+
+        app.retry_selected_message().await.unwrap();
+        // the next prompt has to wait for the retried response to build on it
+        assert!(app.inference_handles_by_chat_and_model.contains_key(&(chat_id, model.id)));
+        answer_scripted_request(&mut app, &mut rx).await;
+
+        // the aside goes out with the next prompt, not with the retry
+        let requests = provider.requests.lock().unwrap();
+        let sent: Vec<_> =
+            requests[0].iter().map(|message| message.content.as_deref().unwrap()).collect();
+        assert_eq!(sent, ["prompt"]);
+        let messages = &app.current_messages[&model.id];
+        let shown: Vec<_> =
+            messages.iter().map(|message| message.content.as_deref().unwrap_or("")).collect();
+        assert_eq!(shown, ["prompt", "retried", "aside"]);
+        assert!(messages[1].error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_next_prompt_carries_the_chosen_candidate() {
+        let (mut app, database) = test_app().await;
+        let provider = Arc::new(ScriptedProvider::default());
+        provider.replies.lock().unwrap().extend([
+            scripted_reply("first", &["second"]),
+            scripted_reply("ok", &[]),
+        ]);
+        let (model, mut rx) = scripted_chat(&mut app, &database, provider.clone()).await;
+
+        send_scripted_prompt(&mut app, &database, &mut rx, model.id, "pick one").await;
+        app.current_message_index.insert(model.id, 0);
+        app.cycle_response_candidate(true).await.unwrap();
+        send_scripted_prompt(&mut app, &database, &mut rx, model.id, "why that one?").await;
+
+        let requests = provider.requests.lock().unwrap();
+        let contents: Vec<_> =
+            requests[1].iter().map(|message| message.content.as_deref().unwrap()).collect();
+        assert_eq!(contents, ["pick one", "second", "why that one?"]);
+    }
+
+    #[tokio::test]
+    async fn test_next_prompt_carries_the_continued_response() {
+        let (mut app, database) = test_app().await;
+        let provider = Arc::new(ScriptedProvider::default());
+        let cut_off = GenerationResult {
+            finish_reason: Some("length".to_string()),
+            ..scripted_reply("The answer", &[])
+        };
+        provider.replies.lock().unwrap().extend([
+            cut_off,
+            scripted_reply(" is 42", &[]),
+            scripted_reply("ok", &[]),
+        ]);
+        let (model, mut rx) = scripted_chat(&mut app, &database, provider.clone()).await;
+
+        send_scripted_prompt(&mut app, &database, &mut rx, model.id, "what is it?").await;
+        app.current_selected_message_index = Some(1);
+        app.continue_selected_message().await.unwrap();
+        let event = rx.recv().await.unwrap();
+        assert!(matches!(event, InferenceEvent::ContinuationComplete { .. }));
+        app.handle_inference_event(event).await.unwrap();
+        send_scripted_prompt(&mut app, &database, &mut rx, model.id, "why?").await;
+
+        let requests = provider.requests.lock().unwrap();
+        let contents: Vec<_> =
+            requests[2].iter().map(|message| message.content.as_deref().unwrap()).collect();
+        assert_eq!(contents, ["what is it?", "The answer is 42", "why?"]);
+    }
+
+    #[tokio::test]
+    async fn test_library_prompts_stay_put_in_read_only_mode() {
+        let (mut app, database) = test_app().await;
+        database.save_library_prompt(None, "Coding", "You write code.").await.unwrap();
+        app.library_prompts = database.get_library_prompts().await.unwrap();
+        app.prompt_library_index = 0;
+        app.read_only = true;
+
+        app.state = AppState::PromptLibraryDeleteConfirmation;
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('y'))).await.unwrap();
+        assert_eq!(app.state, AppState::PromptLibrary);
+        assert_eq!(app.library_prompts.len(), 1);
+
+        app.state = AppState::PromptLibraryContentEdit;
+        app.pending_library_prompt_name = "Writing".to_string();
+        set_editor_state_text(&mut app.library_prompt_textarea, "You write prose.".to_string());
+        app.handle_key_event(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL))
+            .await
+            .unwrap();
+        assert_eq!(database.get_library_prompts().await.unwrap().len(), 1);
+        assert_eq!(
+            app.status_message.as_ref().unwrap().0,
+            "Saving system prompts is disabled in read-only mode"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_only_recheck_leaves_models_alone() {
+        let (mut app, database) = test_app().await;
+        let (model, mut rx) =
+            scripted_chat(&mut app, &database, Arc::new(ScriptedProvider::default())).await;
+        let model_count = database.get_all_models(false).await.unwrap().len();
+        app.read_only = true;
+        app.providers_marked_down.insert(model.provider_id);
+        app.last_provider_recheck = Instant::now() - PROVIDER_RECHECK_INTERVAL;
+
+        app.recheck_marked_down_providers();
+        let event = rx.recv().await.unwrap();
+        app.handle_inference_event(event).await.unwrap();
+
+        // the provider lists no models, a sync would have removed all of its own
+        assert!(app.providers_marked_down.is_empty());
+        assert_eq!(database.get_all_models(false).await.unwrap().len(), model_count);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_answers_dont_clear_the_failing_providers_streak() {
+        let (mut app, database) = test_app().await;
+        let fallback = Arc::new(ScriptedProvider::default());
+        fallback.replies.lock().unwrap().extend([
+            scripted_reply("from the fallback", &[]),
+            scripted_reply("from the fallback again", &[]),
+        ]);
+        // the model's own provider has nothing scripted, so every request to it fails
+        let (model, mut rx) =
+            scripted_chat(&mut app, &database, Arc::new(ScriptedProvider::default())).await;
+        let fallback_model =
+            scripted_fallback(&mut app, &database, &model, fallback.clone()).await;
+        app.settings.provider_failure_threshold = 2;
+
+        send_scripted_prompt(&mut app, &database, &mut rx, model.id, "one").await;
+        assert_eq!(app.provider_failure_streaks.get(&model.provider_id), Some(&1));
+        send_scripted_prompt(&mut app, &database, &mut rx, model.id, "two").await;
+
+        assert_eq!(fallback.requests.lock().unwrap().len(), 2);
+        assert!(app.providers_marked_down.contains(&model.provider_id));
+        assert!(!app.providers_marked_down.contains(&fallback_model.provider_id));
+        assert!(!app.provider_failure_streaks.contains_key(&fallback_model.provider_id));
+    }
+
+    #[tokio::test]
+    async fn test_failing_fallbacks_count_against_their_own_provider() {
+        let (mut app, database) = test_app().await;
+        let primary = Arc::new(ScriptedProvider::default());
+        primary.replies.lock().unwrap().push_back(GenerationResult {
+            content: None,
+            ..scripted_reply("", &[])
+        });
+        let (model, mut rx) = scripted_chat(&mut app, &database, primary).await;
+        // the fallback's provider has nothing scripted, so it fails
+        let fallback_model =
+            scripted_fallback(&mut app, &database, &model, Arc::new(ScriptedProvider::default()))
+                .await;
+
+        send_scripted_prompt(&mut app, &database, &mut rx, model.id, "one").await;
+
+        // an empty reply isn't an error from the provider, the fallback's failure is
+        assert!(!app.provider_failure_streaks.contains_key(&model.provider_id));
+        assert_eq!(app.provider_failure_streaks.get(&fallback_model.provider_id), Some(&1));
+    }
+}
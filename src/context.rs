@@ -0,0 +1,105 @@
+use crate::model::chat::{ChatMessage, ChatRole};
+
+/// Rough token estimate, about four characters per token for typical text
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn estimate_message_tokens(message: &ChatMessage) -> usize {
+    // a few extra tokens for the role and message framing
+    4 + message.content.as_deref().map(estimate_tokens).unwrap_or(0)
+}
+
+/// Drops the oldest messages until the conversation fits in `context_window` tokens.
+/// The system prompt and the most recent user message (plus anything after it) are always kept,
+/// even if they don't fit on their own. Returns how many messages were dropped
+pub fn truncate_to_context_window(
+    conversation: &mut Vec<ChatMessage>,
+    system_prompt: &str,
+    context_window: usize,
+) -> usize {
+    let Some(last_user_idx) = conversation
+        .iter()
+        .rposition(|m| m.chat_role == ChatRole::User)
+    else {
+        return 0;
+    };
+
+    let mut total_tokens = estimate_tokens(system_prompt)
+        + conversation.iter().map(estimate_message_tokens).sum::<usize>();
+    let mut drop_count = 0;
+    while total_tokens > context_window && drop_count < last_user_idx {
+        total_tokens -= estimate_message_tokens(&conversation[drop_count]);
+        drop_count += 1;
+    }
+
+    // don't leave an orphaned assistant reply at the start of the conversation
+    while drop_count > 0
+        && drop_count < last_user_idx
+        && conversation[drop_count].chat_role != ChatRole::User
+    {
+        drop_count += 1;
+    }
+
+    conversation.drain(..drop_count);
+    drop_count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> Vec<ChatMessage> {
+        // 400 characters is 100 tokens, plus 4 for the framing
+        let text = "a".repeat(400);
+        vec![
+            ChatMessage::new_user_message(1, text.clone()),
+            ChatMessage::new_assistant_message(1, 1, text.clone(), 0),
+            ChatMessage::new_user_message(1, text.clone()),
+            ChatMessage::new_assistant_message(1, 1, text.clone(), 0),
+            ChatMessage::new_user_message(1, text),
+        ]
+    }
+
+    #[test]
+    fn test_estimate_tokens_rounds_up() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        // characters, not bytes
+        assert_eq!(estimate_tokens("éééé"), 1);
+    }
+
+    #[test]
+    fn test_conversation_that_fits_is_kept() {
+        let mut messages = conversation();
+        assert_eq!(truncate_to_context_window(&mut messages, "", 520), 0);
+        assert_eq!(messages.len(), 5);
+    }
+
+    #[test]
+    fn test_oldest_turns_are_dropped_without_orphaning_a_reply() {
+        // room for four messages, but they would start with a reply so it goes too
+        let mut messages = conversation();
+        assert_eq!(truncate_to_context_window(&mut messages, "", 416), 2);
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0].chat_role, ChatRole::User);
+
+        // the system prompt counts against the window
+        let mut messages = conversation();
+        assert_eq!(truncate_to_context_window(&mut messages, &"s".repeat(40), 312), 4);
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[test]
+    fn test_last_prompt_is_kept_even_if_it_does_not_fit() {
+        let mut messages = conversation();
+        assert_eq!(truncate_to_context_window(&mut messages, "", 10), 4);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].chat_role, ChatRole::User);
+
+        // without a prompt there's nothing to anchor on
+        let mut replies = vec![ChatMessage::new_assistant_message(1, 1, "a".repeat(400), 0)];
+        assert_eq!(truncate_to_context_window(&mut replies, "", 10), 0);
+    }
+}
@@ -1,9 +1,76 @@
-use crate::{model::{chat::{Chat, ChatMessage, ChatProfile}, model::Model}, provider::provider::Provider};
+use crate::{model::{chat::{Bookmark, Chat, ChatMessage, ChatProfile, ChatRole, ChatSort, LibraryPrompt, NamedProfile, RequestLogEntry, ASIDE_NAME}, model::Model}, provider::provider::{ListedModel, Provider}};
 use anyhow::Result;
-use sqlx::{sqlite::{SqlitePool, SqliteConnectOptions}, Row, Sqlite, Pool, QueryBuilder};
-use std::path::Path;
+use sqlx::{sqlite::{SqlitePool, SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous}, Row, Sqlite, Pool, QueryBuilder};
+use std::{collections::HashSet, path::Path, time::Duration};
 use tracing::{info, instrument};
 
+/// Profile id of the default model selection new chats start with. Named profiles take their ids
+/// from the named_profile table, which start at 1, so 0 can't collide with one of them
+pub const DEFAULT_PROFILE_ID: i64 = 0;
+
+/// How many entries the request log keeps, the oldest are deleted past it
+const REQUEST_LOG_CAPACITY: i64 = 10_000;
+
+/// How long a connection waits on another one's lock before SQLite gives up with SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Writes that still fail with SQLITE_BUSY or SQLITE_LOCKED are retried this many times
+const BUSY_RETRIES: u32 = 5;
+/// Delay before the first retry, doubled for each one after it
+const BUSY_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+fn is_busy_error(error: &sqlx::Error) -> bool {
+    // extended result codes like SQLITE_BUSY_SNAPSHOT keep the primary code in the low byte
+    error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| matches!(code & 0xff, 5 | 6))
+}
+
+/// Run a write, retrying it with backoff while the database is busy or locked. The busy timeout
+/// covers most contention, but SQLite returns SQLITE_BUSY right away when a transaction that read
+/// first can't upgrade to a write lock, so the whole operation has to be run again
+async fn retry_if_busy<T, F, Fut>(mut operation: F) -> sqlx::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = sqlx::Result<T>>,
+{
+    let mut delay = BUSY_RETRY_BACKOFF;
+    for attempt in 1..=BUSY_RETRIES {
+        match operation().await {
+            Err(e) if is_busy_error(&e) => {
+                info!("Database busy, retrying in {:?} (attempt {}/{}): {}", delay, attempt, BUSY_RETRIES, e);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    operation().await
+}
+
+/// Provider columns that can be changed with set_provider_setting
+const PROVIDER_SETTINGS: &[&str] = &["model_include", "model_exclude", "system_role", "request_timeout_secs", "extra_headers", "no_models_endpoint"];
+
+/// Extra headers are a JSON object of header names to values. Requests skip a bad header, so it's
+/// caught when the setting is saved rather than showing up as a warning in the logs later
+fn validate_extra_headers(json: &str) -> Result<()> {
+    let headers: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)
+        .map_err(|e| anyhow::anyhow!("extra_headers must be a JSON object like {{\"X-Title\": \"shore\"}}: {}", e))?;
+    for (name, value) in headers {
+        let Some(value) = value.as_str() else {
+            anyhow::bail!("The value of header {} must be a string", name);
+        };
+        if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err() {
+            anyhow::bail!("{} isn't a valid header name", name);
+        }
+        if reqwest::header::HeaderValue::from_str(value).is_err() {
+            anyhow::bail!("The value of header {} isn't a valid header value", name);
+        }
+    }
+    Ok(())
+}
+
 pub struct Database {
     pub pool: Pool<Sqlite>,
 }
@@ -15,8 +82,13 @@ impl Database {
         let connection_options = SqliteConnectOptions::new()
             .filename(&db_path)
             .create_if_missing(true)
-            .foreign_keys(true); // Enable foreign key constraints
-            
+            .foreign_keys(true) // Enable foreign key constraints
+            // background model refreshes and inference tasks write while the UI reads
+            .journal_mode(SqliteJournalMode::Wal)
+            // with WAL this only risks the last commits on power loss, never corruption
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(BUSY_TIMEOUT);
+
         let pool = SqlitePool::connect_with(connection_options).await?;
 
         let db = Database { pool };
@@ -25,6 +97,29 @@ impl Database {
         Ok(db)
     }
 
+    /// Open another database only to read from it. Unlike `new` nothing is created, migrated or
+    /// switched to WAL, and a file without shore's chat tables is refused
+    #[instrument(level = "info", skip(db_path), fields(db_path = %db_path.as_ref().display()))]
+    pub async fn open_read_only<P: AsRef<Path>>(db_path: P) -> Result<Self> {
+        let connection_options = SqliteConnectOptions::new()
+            .filename(&db_path)
+            .read_only(true)
+            .busy_timeout(BUSY_TIMEOUT);
+        let pool = SqlitePool::connect_with(connection_options).await?;
+
+        let chat_tables: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name IN ('chat', 'chat_message')"
+        )
+        .fetch_one(&pool)
+        .await?;
+        if chat_tables < 2 {
+            pool.close().await;
+            anyhow::bail!("Not a shore database, it has no chat tables");
+        }
+
+        Ok(Database { pool })
+    }
+
 
     #[instrument(level = "info", skip(self))]
     pub async fn create_chat(&self, title: Option<String>) -> Result<i64> {
@@ -41,7 +136,7 @@ impl Database {
     #[instrument(level = "info", skip(self))]
     pub async fn get_recent_chats(&self, limit: i32) -> Result<Vec<Chat>> {
         let chats = sqlx::query_as::<_, Chat>(
-            "SELECT id, dt, title FROM chat ORDER BY dt DESC LIMIT ?"
+            "SELECT id, dt, title, archived, json_mode, stop_sequences, system_prompt FROM chat WHERE NOT archived ORDER BY COALESCE(sort_order, dt * 1000) DESC, id DESC LIMIT ?"
         )
         .bind(limit)
         .fetch_all(&self.pool)
@@ -51,21 +146,117 @@ impl Database {
     }
 
     #[instrument(level = "info", skip(self))]
-    pub async fn get_all_chats(&self) -> Result<Vec<Chat>> {
-        let chats = sqlx::query_as::<_, Chat>(
-            "SELECT id, dt, title FROM chat ORDER BY dt DESC"
-        )
+    pub async fn get_chats(&self, include_archived: bool, sort: ChatSort, limit: i64, offset: i64) -> Result<Vec<Chat>> {
+        // newest first breaks ties, so paging through an order with lots of equal keys is stable
+        let order_by = match sort {
+            // chats that were moved by hand are placed by their sort_order instead of when they were created
+            ChatSort::Date => "COALESCE(sort_order, dt * 1000) DESC, id DESC",
+            ChatSort::Title => "title IS NULL, title COLLATE NOCASE ASC, dt DESC, id DESC",
+            ChatSort::MessageCount => "(SELECT COUNT(*) FROM chat_message WHERE chat_message.chat_id = chat.id) DESC, dt DESC, id DESC",
+        };
+        let chats = sqlx::query_as::<_, Chat>(&format!(
+            "SELECT id, dt, title, archived, json_mode, stop_sequences, system_prompt FROM chat WHERE ? OR NOT archived ORDER BY {} LIMIT ? OFFSET ?",
+            order_by
+        ))
+        .bind(include_archived)
+        .bind(limit)
+        .bind(offset)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(chats)
     }
+
+    #[instrument(level = "info", skip(self))]
+    pub async fn set_chat_json_mode(&self, chat_id: i64, json_mode: bool) -> Result<()> {
+        sqlx::query("UPDATE chat SET json_mode = ? WHERE id = ?")
+            .bind(json_mode)
+            .bind(chat_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "info", skip(self, system_prompt))]
+    pub async fn set_chat_system_prompt(&self, chat_id: i64, system_prompt: Option<String>) -> Result<()> {
+        sqlx::query("UPDATE chat SET system_prompt = ? WHERE id = ?")
+            .bind(system_prompt)
+            .bind(chat_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "info", skip(self))]
+    pub async fn set_chat_stop_sequences(&self, chat_id: i64, stop_sequences: Option<String>) -> Result<()> {
+        sqlx::query("UPDATE chat SET stop_sequences = ? WHERE id = ?")
+            .bind(stop_sequences)
+            .bind(chat_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Swap the places of two chats in the date ordered history list. Chats created in the same second
+    /// share a place until one of them is moved, so those are spread out first, keeping their order
+    #[instrument(level = "info", skip(self))]
+    pub async fn swap_chat_places(&self, chat_id: i64, other_chat_id: i64) -> Result<()> {
+        const PLACE: &str = "SELECT COALESCE(sort_order, dt * 1000) FROM chat WHERE id = ?";
+        let mut tx = self.pool.begin().await?;
+        let mut place: i64 = sqlx::query_scalar(PLACE).bind(chat_id).fetch_one(&mut *tx).await?;
+        let mut other_place: i64 = sqlx::query_scalar(PLACE).bind(other_chat_id).fetch_one(&mut *tx).await?;
+        if place == other_place {
+            let tied: Vec<i64> = sqlx::query_scalar("SELECT id FROM chat WHERE COALESCE(sort_order, dt * 1000) = ? ORDER BY id DESC")
+                .bind(place)
+                .fetch_all(&mut *tx)
+                .await?;
+            let shared_place = place;
+            for (offset, id) in tied.into_iter().enumerate() {
+                let spread_place = shared_place - offset as i64;
+                sqlx::query("UPDATE chat SET sort_order = ? WHERE id = ?")
+                    .bind(spread_place)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                if id == chat_id {
+                    place = spread_place;
+                } else if id == other_chat_id {
+                    other_place = spread_place;
+                }
+            }
+        }
+
+        for (id, new_place) in [(chat_id, other_place), (other_chat_id, place)] {
+            sqlx::query("UPDATE chat SET sort_order = ? WHERE id = ?")
+                .bind(new_place)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "info", skip(self))]
+    pub async fn set_chat_archived(&self, chat_id: i64, archived: bool) -> Result<()> {
+        sqlx::query("UPDATE chat SET archived = ? WHERE id = ?")
+            .bind(archived)
+            .bind(chat_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
     
 
     #[instrument(level = "info", skip(self))]
     pub async fn get_chat_messages(&self, chat_id: i64) -> Result<Vec<ChatMessage>> {
         let messages = sqlx::query_as::<_, ChatMessage>(
-            "SELECT id, chat_id, dt, response_dt, model_id, chat_role, content, reasoning_content, tool_calls, tool_call_id, name, error FROM chat_message WHERE chat_id = ? ORDER BY dt, chat_role"
+            "SELECT id, chat_id, dt, response_dt, model_id, chat_role, content, reasoning_content, tool_calls, tool_call_id, name, error, reasoning_tokens, reasoning_tokens_estimated, prompt_tokens, completion_tokens, incomplete, finish_reason, answered_by_model_id FROM chat_message WHERE chat_id = ? ORDER BY dt, chat_role, model_id, id"
         )
         .bind(chat_id)
         .fetch_all(&self.pool)
@@ -74,32 +265,105 @@ impl Database {
         Ok(messages)
     }
 
+    /// The last `limit` prompts sent before `before_dt` (or the newest ones when None), with their responses, in the
+    /// same order as get_chat_messages. Responses share their prompt's dt, so a page never splits a prompt from them
+    #[instrument(level = "info", skip(self))]
+    pub async fn get_chat_messages_paged(&self, chat_id: i64, before_dt: Option<i64>, limit: i64) -> Result<Vec<ChatMessage>> {
+        let before_dt = before_dt.unwrap_or(i64::MAX);
+        let messages = sqlx::query_as::<_, ChatMessage>(
+            "SELECT id, chat_id, dt, response_dt, model_id, chat_role, content, reasoning_content, tool_calls, tool_call_id, name, error, reasoning_tokens, reasoning_tokens_estimated, prompt_tokens, completion_tokens, incomplete, finish_reason, answered_by_model_id FROM chat_message
+             WHERE chat_id = ? AND dt < ? AND dt >= (SELECT MIN(dt) FROM (SELECT DISTINCT dt FROM chat_message WHERE chat_id = ? AND dt < ? ORDER BY dt DESC LIMIT ?))
+             ORDER BY dt, chat_role, model_id, id"
+        )
+        .bind(chat_id)
+        .bind(before_dt)
+        .bind(chat_id)
+        .bind(before_dt)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+
+    /// The content of a chat's last `limit` prompts, oldest first, leaving out asides. Unlike the
+    /// paged messages this reaches back to the start of the chat, for recalling earlier prompts
+    #[instrument(level = "info", skip(self))]
+    pub async fn get_chat_prompts(&self, chat_id: i64, limit: i64) -> Result<Vec<String>> {
+        let prompts = sqlx::query_scalar(
+            "SELECT content FROM (
+                 SELECT id, dt, content FROM chat_message
+                 WHERE chat_id = ? AND chat_role = ? AND content IS NOT NULL AND name IS NOT ?
+                 ORDER BY dt DESC, id DESC LIMIT ?
+             ) ORDER BY dt, id"
+        )
+        .bind(chat_id)
+        .bind(ChatRole::User)
+        .bind(ASIDE_NAME)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(prompts)
+    }
+
     #[instrument(level = "info", skip(self, message), fields(chat_id = message.chat_id, role = %message.chat_role))]
     pub async fn add_chat_message(&self, message: &ChatMessage) -> Result<i64> {
-        let result = sqlx::query(
-            "INSERT INTO chat_message (chat_id, dt, response_dt, model_id, chat_role, content, reasoning_content, tool_calls, tool_call_id, name, error) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id"
+        let result = retry_if_busy(move || sqlx::query(
+            "INSERT INTO chat_message (chat_id, dt, response_dt, model_id, chat_role, content, reasoning_content, tool_calls, tool_call_id, name, error, reasoning_tokens, reasoning_tokens_estimated, prompt_tokens, completion_tokens, incomplete, finish_reason, answered_by_model_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id"
         )
         .bind(message.chat_id)
         .bind(message.dt)
         .bind(message.response_dt)
         .bind(message.model_id)
-        .bind(&message.chat_role)
+        .bind(message.chat_role)
         .bind(&message.content)
         .bind(&message.reasoning_content)
         .bind(&message.tool_calls)
         .bind(&message.tool_call_id)
         .bind(&message.name)
         .bind(&message.error)
-        .fetch_one(&self.pool)
+        .bind(message.reasoning_tokens)
+        .bind(message.reasoning_tokens_estimated)
+        .bind(message.prompt_tokens)
+        .bind(message.completion_tokens)
+        .bind(message.incomplete)
+        .bind(&message.finish_reason)
+        .bind(message.answered_by_model_id)
+        .fetch_one(&self.pool))
         .await?;
 
         Ok(result.get(0))
     }
 
+    /// Overwrite an incomplete placeholder with the finished response
+    #[instrument(level = "info", skip(self, message))]
+    pub async fn complete_chat_message(&self, message_id: i64, message: &ChatMessage) -> Result<()> {
+        retry_if_busy(move || sqlx::query(
+            "UPDATE chat_message SET response_dt = ?, content = ?, reasoning_content = ?, tool_calls = ?, error = ?, reasoning_tokens = ?, reasoning_tokens_estimated = ?, prompt_tokens = ?, completion_tokens = ?, incomplete = 0, finish_reason = ?, answered_by_model_id = ? WHERE id = ?"
+        )
+        .bind(message.response_dt)
+        .bind(&message.content)
+        .bind(&message.reasoning_content)
+        .bind(&message.tool_calls)
+        .bind(&message.error)
+        .bind(message.reasoning_tokens)
+        .bind(message.reasoning_tokens_estimated)
+        .bind(message.prompt_tokens)
+        .bind(message.completion_tokens)
+        .bind(&message.finish_reason)
+        .bind(message.answered_by_model_id)
+        .bind(message_id)
+        .execute(&self.pool))
+        .await?;
+
+        Ok(())
+    }
+
     #[instrument(level = "info", skip(self))]
     pub async fn get_providers(&self) -> Result<Vec<Provider>> {
         let providers = sqlx::query_as::<_, Provider>(
-            "SELECT id, name, base_url, disabled, deprecated, api_key_env_var, created_dt FROM provider WHERE NOT deprecated ORDER BY id ASC"
+            "SELECT id, name, base_url, disabled, deprecated, api_key_env_var, created_dt, model_include, model_exclude, request_timeout_secs, extra_headers, no_models_endpoint, system_role FROM provider WHERE NOT deprecated ORDER BY id ASC"
         )
         .fetch_all(&self.pool)
         .await?;
@@ -107,10 +371,67 @@ impl Database {
         Ok(providers)
     }
 
+    /// Change one of a provider's settings by name. Values are checked here so a typo is refused
+    /// instead of being written to the db, an empty value clears the setting
+    #[instrument(level = "info", skip(self))]
+    pub async fn set_provider_setting(&self, provider_name: &str, key: &str, value: &str) -> Result<()> {
+        if !PROVIDER_SETTINGS.contains(&key) {
+            anyhow::bail!("Unknown provider setting: {}, expected one of {}", key, PROVIDER_SETTINGS.join(", "));
+        }
+        let value = value.trim();
+        let text = (!value.is_empty()).then_some(value);
+
+        // the column is one of PROVIDER_SETTINGS, never the key as given
+        let sql = format!("UPDATE provider SET {} = ? WHERE name = ? COLLATE NOCASE AND NOT deprecated", key);
+        let query = sqlx::query(&sql);
+        let query = match key {
+            "system_role" => {
+                if text.is_some_and(|role| role != "system" && role != "developer") {
+                    anyhow::bail!("system_role must be system or developer, got {}", value);
+                }
+                query.bind(text)
+            }
+            "request_timeout_secs" => {
+                let secs = text
+                    .map(|secs| {
+                        secs.parse::<i64>()
+                            .ok()
+                            .filter(|secs| *secs > 0)
+                            .ok_or_else(|| anyhow::anyhow!("request_timeout_secs must be a number of seconds, got {}", secs))
+                    })
+                    .transpose()?;
+                query.bind(secs)
+            }
+            "no_models_endpoint" => {
+                // the column can't be null, clearing it goes back to syncing the provider's models
+                let no_models_endpoint = match text {
+                    None => false,
+                    Some(flag) => flag
+                        .parse::<bool>()
+                        .map_err(|_| anyhow::anyhow!("no_models_endpoint must be true or false, got {}", flag))?,
+                };
+                query.bind(no_models_endpoint)
+            }
+            "extra_headers" => {
+                if let Some(json) = text {
+                    validate_extra_headers(json)?;
+                }
+                query.bind(text)
+            }
+            _ => query.bind(text),
+        };
+
+        let result = query.bind(provider_name).execute(&self.pool).await?;
+        if result.rows_affected() == 0 {
+            anyhow::bail!("No provider named {}", provider_name);
+        }
+        Ok(())
+    }
+
     #[instrument(level = "info", skip(self))]
     pub async fn get_models_for_provider(&self, provider_id: i64) -> Result<Vec<Model>> {
         let models = sqlx::query_as::<_, Model>(
-            "SELECT id, provider_id, model, api_type, disabled, deprecated, created_dt FROM model WHERE provider_id = ? AND NOT deprecated ORDER BY id ASC"
+            "SELECT id, provider_id, model, api_type, disabled, deprecated, created_dt, context_window, supports_json_mode, alias, family, parameter_size, quantization, size_bytes FROM model WHERE provider_id = ? AND NOT deprecated ORDER BY id ASC"
         )
         .bind(provider_id)
         .fetch_all(&self.pool)
@@ -119,20 +440,99 @@ impl Database {
         Ok(models)
     }
 
-    pub async fn get_all_models(&self) -> Result<Vec<Model>> {
+    /// Every model, optionally with the ones their provider stopped listing. Those can't be used anymore,
+    /// but old chats still need their names
+    pub async fn get_all_models(&self, include_deprecated: bool) -> Result<Vec<Model>> {
         let models = sqlx::query_as::<_, Model>(
-            "SELECT id, provider_id, model, api_type, disabled, deprecated, created_dt FROM model WHERE NOT deprecated ORDER BY provider_id, model"
+            "SELECT id, provider_id, model, api_type, disabled, deprecated, created_dt, context_window, supports_json_mode, alias, family, parameter_size, quantization, size_bytes FROM model WHERE ? OR NOT deprecated ORDER BY provider_id, model"
         )
+        .bind(include_deprecated)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(models)
     }
 
+    /// Bring a provider's models in line with what its models endpoint lists.
+    /// New models are inserted, previously deprecated ones that came back are restored and
+    /// ones no longer listed are deprecated. Returns (added, removed) counts
+    #[instrument(level = "info", skip(self, listed_models))]
+    pub async fn sync_provider_models(&self, provider_id: i64, listed_models: &[ListedModel]) -> Result<(usize, usize)> {
+        Ok(retry_if_busy(|| self.try_sync_provider_models(provider_id, listed_models)).await?)
+    }
+
+    async fn try_sync_provider_models(&self, provider_id: i64, listed_models: &[ListedModel]) -> sqlx::Result<(usize, usize)> {
+        let existing: Vec<(i64, String, bool)> = sqlx::query_as(
+            "SELECT id, model, deprecated FROM model WHERE provider_id = ?"
+        )
+        .bind(provider_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let listed: HashSet<&str> = listed_models.iter().map(|model| model.id.as_str()).collect();
+        // routers like Hugging Face take a ":backend" suffix that the models endpoint doesn't include
+        let is_listed = |model: &str| {
+            listed.contains(model)
+                || model.split_once(':').is_some_and(|(base, _)| listed.contains(base))
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let mut added = 0;
+        let mut removed = 0;
+        for (id, model, deprecated) in &existing {
+            let should_deprecate = !is_listed(model);
+            if should_deprecate != *deprecated {
+                sqlx::query("UPDATE model SET deprecated = ? WHERE id = ?")
+                    .bind(should_deprecate)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                if should_deprecate {
+                    removed += 1;
+                } else {
+                    added += 1;
+                }
+            }
+        }
+
+        let existing_names: HashSet<&str> = existing.iter().map(|(_, model, _)| model.as_str()).collect();
+        let now = chrono::Utc::now().timestamp();
+        for listed_model in listed_models {
+            let metadata = &listed_model.metadata;
+            if existing_names.contains(listed_model.id.as_str()) {
+                // local models get re-pulled with different weights under the same name
+                sqlx::query("UPDATE model SET family = ?, parameter_size = ?, quantization = ?, size_bytes = ? WHERE provider_id = ? AND model = ?")
+                    .bind(&metadata.family)
+                    .bind(&metadata.parameter_size)
+                    .bind(&metadata.quantization)
+                    .bind(metadata.size_bytes)
+                    .bind(provider_id)
+                    .bind(&listed_model.id)
+                    .execute(&mut *tx)
+                    .await?;
+                continue;
+            }
+            sqlx::query("INSERT INTO model (provider_id, model, api_type, disabled, deprecated, created_dt, family, parameter_size, quantization, size_bytes) VALUES (?, ?, 0, 0, 0, ?, ?, ?, ?, ?)")
+                .bind(provider_id)
+                .bind(&listed_model.id)
+                .bind(now)
+                .bind(&metadata.family)
+                .bind(&metadata.parameter_size)
+                .bind(&metadata.quantization)
+                .bind(metadata.size_bytes)
+                .execute(&mut *tx)
+                .await?;
+            added += 1;
+        }
+        tx.commit().await?;
+
+        Ok((added, removed))
+    }
+
     #[instrument(level = "info", skip(self, model), fields(provider_id = model.provider_id, model_name = %model.model))]
     pub async fn add_model(&self, model: &Model) -> Result<i64> {
         let result = sqlx::query(
-            "INSERT INTO model (provider_id, model, api_type, disabled, deprecated, created_dt) VALUES (?, ?, ?, ?, ?, ?) RETURNING id"
+            "INSERT INTO model (provider_id, model, api_type, disabled, deprecated, created_dt, context_window, supports_json_mode) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING id"
         )
         .bind(model.provider_id)
         .bind(&model.model)
@@ -140,12 +540,106 @@ impl Database {
         .bind(model.disabled)
         .bind(model.deprecated)
         .bind(model.created_dt)
+        .bind(model.context_window)
+        .bind(model.supports_json_mode)
         .fetch_one(&self.pool)
         .await?;
 
         Ok(result.get(0))
     }
 
+    /// Add a model by hand, for providers without a models endpoint. A model the provider already has
+    /// is offered again if it was removed. Returns the model's id
+    #[instrument(level = "info", skip(self))]
+    pub async fn add_manual_model(&self, provider_name: &str, model_name: &str) -> Result<i64> {
+        let provider = self
+            .get_providers()
+            .await?
+            .into_iter()
+            .find(|provider| provider.name.eq_ignore_ascii_case(provider_name))
+            .ok_or_else(|| anyhow::anyhow!("No provider named {}", provider_name))?;
+        if !provider.no_models_endpoint {
+            info!("Provider {} syncs its models, {} is removed again unless it lists it", provider.name, model_name);
+        }
+
+        let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM model WHERE provider_id = ? AND model = ?")
+            .bind(provider.id)
+            .bind(model_name)
+            .fetch_optional(&self.pool)
+            .await?;
+        if let Some(model_id) = existing {
+            sqlx::query("UPDATE model SET deprecated = 0 WHERE id = ?")
+                .bind(model_id)
+                .execute(&self.pool)
+                .await?;
+            return Ok(model_id);
+        }
+
+        self.add_model(&Model {
+            id: 0, // Will be set by database
+            provider_id: provider.id,
+            model: model_name.to_string(),
+            api_type: 0,
+            disabled: false,
+            deprecated: false,
+            created_dt: chrono::Utc::now().timestamp(),
+            context_window: None,
+            supports_json_mode: false,
+            alias: None,
+            metadata: Default::default(),
+        })
+        .await
+    }
+
+    async fn model_id_by_name(&self, provider_name: &str, model_name: &str) -> Result<i64> {
+        sqlx::query_scalar(
+            "SELECT model.id FROM model JOIN provider ON provider.id = model.provider_id WHERE provider.name = ? COLLATE NOCASE AND model.model = ? AND NOT provider.deprecated"
+        )
+        .bind(provider_name)
+        .bind(model_name)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No model {} for provider {}", model_name, provider_name))
+    }
+
+    /// Set how many tokens of conversation a model is sent, None sends all of it
+    #[instrument(level = "info", skip(self))]
+    pub async fn set_model_context_window(&self, provider_name: &str, model_name: &str, context_window: Option<i64>) -> Result<()> {
+        let model_id = self.model_id_by_name(provider_name, model_name).await?;
+        sqlx::query("UPDATE model SET context_window = ? WHERE id = ?")
+            .bind(context_window)
+            .bind(model_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark whether a model accepts the JSON response format, so chats in JSON mode ask it for one
+    #[instrument(level = "info", skip(self))]
+    pub async fn set_model_supports_json_mode(&self, provider_name: &str, model_name: &str, supports_json_mode: bool) -> Result<()> {
+        let model_id = self.model_id_by_name(provider_name, model_name).await?;
+        sqlx::query("UPDATE model SET supports_json_mode = ? WHERE id = ?")
+            .bind(supports_json_mode)
+            .bind(model_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Set the name a model is shown under, None goes back to the model name
+    #[instrument(level = "info", skip(self))]
+    pub async fn set_model_alias(&self, model_id: i64, alias: Option<String>) -> Result<()> {
+        sqlx::query("UPDATE model SET alias = ? WHERE id = ?")
+            .bind(alias)
+            .bind(model_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn get_chat_models_ids(&self, chat_id: i64) -> Result<Vec<i64>> {
         let models = sqlx::query_scalar(
             r#"
@@ -222,11 +716,13 @@ impl Database {
     #[instrument(level = "info", skip(self))]
     pub async fn update_chat_title(&self, chat_id: i64, title: &String) -> Result<()> {
         info!("is this function really being called 10 times?");
-        sqlx::query("UPDATE chat SET title = ? WHERE id = ?")
-            .bind(title)
-            .bind(chat_id)
-            .execute(&self.pool)
-            .await?;
+        retry_if_busy(move || {
+            sqlx::query("UPDATE chat SET title = ? WHERE id = ?")
+                .bind(title)
+                .bind(chat_id)
+                .execute(&self.pool)
+        })
+        .await?;
 
         Ok(())
     }
@@ -255,6 +751,99 @@ impl Database {
         })
     }
 
+    pub async fn get_named_profiles(&self) -> Result<Vec<NamedProfile>> {
+        let profiles: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT id, name FROM named_profile ORDER BY name COLLATE NOCASE"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut named_profiles = Vec::with_capacity(profiles.len());
+        for (id, name) in profiles {
+            let model_ids = self.get_chat_profile(id).await?.model_ids;
+            named_profiles.push(NamedProfile { id, name, model_ids });
+        }
+        Ok(named_profiles)
+    }
+
+    /// Save a named profile, replacing the models of an existing one with the same name
+    #[instrument(level = "info", skip(self, model_ids))]
+    pub async fn save_named_profile(&self, name: &str, model_ids: Vec<i64>) -> Result<i64> {
+        let profile_id: i64 = sqlx::query_scalar(
+            "INSERT INTO named_profile (name, created_dt) VALUES (?, ?) ON CONFLICT(name) DO UPDATE SET name = excluded.name RETURNING id"
+        )
+        .bind(name)
+        .bind(chrono::Utc::now().timestamp())
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.set_chat_profile_models(profile_id, model_ids).await?;
+        Ok(profile_id)
+    }
+
+    pub async fn get_library_prompts(&self) -> Result<Vec<LibraryPrompt>> {
+        let prompts = sqlx::query_as::<_, LibraryPrompt>(
+            "SELECT id, name, content, created_dt FROM system_prompt ORDER BY name COLLATE NOCASE"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(prompts)
+    }
+
+    /// Add a prompt to the library, or update the one with `prompt_id` when given
+    #[instrument(level = "info", skip(self, content))]
+    pub async fn save_library_prompt(&self, prompt_id: Option<i64>, name: &str, content: &str) -> Result<i64> {
+        match prompt_id {
+            Some(prompt_id) => {
+                sqlx::query("UPDATE system_prompt SET name = ?, content = ? WHERE id = ?")
+                    .bind(name)
+                    .bind(content)
+                    .bind(prompt_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(prompt_id)
+            }
+            None => {
+                let prompt_id: i64 = sqlx::query_scalar(
+                    "INSERT INTO system_prompt (name, content, created_dt) VALUES (?, ?, ?) RETURNING id"
+                )
+                .bind(name)
+                .bind(content)
+                .bind(chrono::Utc::now().timestamp())
+                .fetch_one(&self.pool)
+                .await?;
+                Ok(prompt_id)
+            }
+        }
+    }
+
+    pub async fn delete_library_prompt(&self, prompt_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM system_prompt WHERE id = ?")
+            .bind(prompt_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_named_profile(&self, profile_id: i64) -> Result<()> {
+        // never touch the default profile
+        if profile_id == DEFAULT_PROFILE_ID {
+            return Ok(());
+        }
+        sqlx::query("DELETE FROM chat_profile_model WHERE profile_id = ?")
+            .bind(profile_id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM named_profile WHERE id = ?")
+            .bind(profile_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn remove_chat_profile_model(&self, profile_id: i64, model_id: i64) -> Result<()> {
         sqlx::query("DELETE FROM chat_profile_model WHERE profile_id = ? AND model_id = ?")
             .bind(profile_id)
@@ -300,23 +889,70 @@ impl Database {
 
     pub async fn create_default_chat_profile(&self, model_id: i64) -> Result<()> {
         // Create default profile with the specified model and no tools
-        self.set_chat_profile_models(0, vec![model_id]).await?;
+        self.set_chat_profile_models(DEFAULT_PROFILE_ID, vec![model_id]).await?;
         
         // Get model details for logging
-        if let Ok(models) = self.get_all_models().await {
+        if let Ok(models) = self.get_all_models(false).await {
             if let Some(model) = models.into_iter().find(|m| m.id == model_id) {
-                info!("Created default chat profile (ID 0) with model ID: {} ({})", 
-                         model.id, model.model);
+                info!("Created default chat profile (ID {}) with model ID: {} ({})", 
+                         DEFAULT_PROFILE_ID, model.id, model.model);
             } else {
-                info!("Created default chat profile (ID 0) with model ID: {}", model_id);
+                info!("Created default chat profile (ID {}) with model ID: {}", DEFAULT_PROFILE_ID, model_id);
             }
         } else {
-            info!("Created default chat profile (ID 0) with model ID: {}", model_id);
+            info!("Created default chat profile (ID {}) with model ID: {}", DEFAULT_PROFILE_ID, model_id);
         }
 
         Ok(())
     }
 
+    pub async fn get_settings(&self) -> Result<Vec<(String, String)>> {
+        let settings = sqlx::query_as::<_, (String, String)>("SELECT key, value FROM setting")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(settings)
+    }
+
+    #[instrument(level = "info", skip(self))]
+    pub async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query("INSERT INTO setting (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+            .bind(key)
+            .bind(value)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The prompt draft saved by the last auto-save, if there is one
+    pub async fn get_prompt_draft(&self) -> Result<Option<String>> {
+        let draft = sqlx::query_scalar::<_, String>("SELECT content FROM prompt_draft WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(draft)
+    }
+
+    /// Saves the prompt draft, an empty one removes the saved draft instead
+    #[instrument(level = "info", skip(self, content))]
+    pub async fn save_prompt_draft(&self, content: &str) -> Result<()> {
+        let saved_dt = chrono::Utc::now().timestamp();
+        retry_if_busy(move || {
+            let query = if content.is_empty() {
+                sqlx::query("DELETE FROM prompt_draft WHERE id = 1")
+            } else {
+                sqlx::query("INSERT INTO prompt_draft (id, content, saved_dt) VALUES (1, ?, ?) ON CONFLICT(id) DO UPDATE SET content = excluded.content, saved_dt = excluded.saved_dt")
+                    .bind(content)
+                    .bind(saved_dt)
+            };
+            query.execute(&self.pool)
+        })
+        .await?;
+
+        Ok(())
+    }
+
     #[instrument(level = "info", skip(self))]
     pub async fn delete_chat(&self, chat_id: i64) -> Result<()> {
         // Delete the chat - related records will be cascade deleted automatically
@@ -329,6 +965,255 @@ impl Database {
         Ok(())
     }
 
+    /// Delete several chats at once, their messages, models and tools go with them like in delete_chat
+    #[instrument(level = "info", skip(self))]
+    pub async fn delete_chats(&self, chat_ids: &[i64]) -> Result<()> {
+        if chat_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut query_builder = QueryBuilder::<Sqlite>::new("DELETE FROM chat WHERE id IN (");
+        let mut separated = query_builder.separated(", ");
+        for chat_id in chat_ids {
+            separated.push_bind(chat_id);
+        }
+        separated.push_unseparated(")");
+        query_builder.build().execute(&self.pool).await?;
+
+        Ok(())
+    }
+
+    /// Delete every chat, with their messages, models and tools like in delete_chat. Returns how many were deleted
+    #[instrument(level = "info", skip(self))]
+    pub async fn delete_all_chats(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM chat").execute(&self.pool).await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_chat_messages(&self, chat_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM chat_message WHERE chat_id = ?")
+            .bind(chat_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[instrument(level = "info", skip(self))]
+    pub async fn delete_chat_message(&self, message_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM chat_message WHERE id = ?")
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Bookmark a message, or remove its bookmark. Returns whether it's bookmarked now
+    #[instrument(level = "info", skip(self))]
+    pub async fn toggle_bookmark(&self, message_id: i64) -> Result<bool> {
+        let removed = sqlx::query("DELETE FROM bookmark WHERE message_id = ?")
+            .bind(message_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        if removed > 0 {
+            return Ok(false);
+        }
+
+        sqlx::query("INSERT INTO bookmark (message_id, created_dt) VALUES (?, ?)")
+            .bind(message_id)
+            .bind(chrono::Utc::now().timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(true)
+    }
+
+    /// Every bookmark with its message and chat, newest first
+    pub async fn get_bookmarks(&self) -> Result<Vec<Bookmark>> {
+        let bookmarks = sqlx::query_as::<_, Bookmark>(
+            r#"
+            SELECT b.message_id, cm.chat_id, c.title AS chat_title, c.archived AS chat_archived, cm.model_id, cm.content, cm.error, b.created_dt
+            FROM bookmark b
+            JOIN chat_message cm ON cm.id = b.message_id
+            JOIN chat c ON c.id = cm.chat_id
+            ORDER BY b.created_dt DESC, b.id DESC
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(bookmarks)
+    }
+
+    /// Record a request. Only the newest REQUEST_LOG_CAPACITY entries are kept, older ones are
+    /// dropped as new ones come in
+    #[instrument(level = "info", skip(self, entry))]
+    pub async fn add_request_log_entry(&self, entry: &RequestLogEntry) -> Result<i64> {
+        // these are written from inference tasks, which run alongside each other
+        let result = retry_if_busy(move || sqlx::query(
+            "INSERT INTO request_log (dt, chat_id, model_id, model, message_count, latency_ms, prompt_tokens, completion_tokens, error) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id"
+        )
+        .bind(entry.dt)
+        .bind(entry.chat_id)
+        .bind(entry.model_id)
+        .bind(&entry.model)
+        .bind(entry.message_count)
+        .bind(entry.latency_ms)
+        .bind(entry.prompt_tokens)
+        .bind(entry.completion_tokens)
+        .bind(&entry.error)
+        .fetch_one(&self.pool))
+        .await?;
+        self.prune_request_log(REQUEST_LOG_CAPACITY).await?;
+
+        Ok(result.get(0))
+    }
+
+    /// Delete all but the `keep` most recently written request log entries
+    async fn prune_request_log(&self, keep: i64) -> Result<()> {
+        retry_if_busy(|| sqlx::query(
+            "DELETE FROM request_log WHERE id <= (SELECT id FROM request_log ORDER BY id DESC LIMIT 1 OFFSET ?)"
+        )
+        .bind(keep)
+        .execute(&self.pool))
+        .await?;
+
+        Ok(())
+    }
+
+    /// The most recent requests in the request log, newest first
+    #[instrument(level = "info", skip(self))]
+    pub async fn get_request_log(&self, limit: i64) -> Result<Vec<RequestLogEntry>> {
+        let entries = sqlx::query_as::<_, RequestLogEntry>(
+            "SELECT id, dt, chat_id, model_id, model, message_count, latency_ms, prompt_tokens, completion_tokens, error FROM request_log ORDER BY dt DESC, id DESC LIMIT ?"
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Copy a chat with all of its messages, models and tools into a new chat, returning the copy
+    #[instrument(level = "info", skip(self))]
+    pub async fn duplicate_chat(&self, chat_id: i64) -> Result<Chat> {
+        let now = chrono::Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+        let chat = sqlx::query_as::<_, Chat>(
+            "INSERT INTO chat (dt, title, json_mode, stop_sequences, system_prompt) SELECT ?, title || ' (copy)', json_mode, stop_sequences, system_prompt FROM chat WHERE id = ? RETURNING id, dt, title, archived, json_mode, stop_sequences, system_prompt"
+        )
+        .bind(now)
+        .bind(chat_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // placeholders for responses still being generated belong to the original chat only
+        sqlx::query(
+            "INSERT INTO chat_message (chat_id, dt, response_dt, model_id, chat_role, content, reasoning_content, tool_calls, tool_call_id, name, error, reasoning_tokens, reasoning_tokens_estimated, prompt_tokens, completion_tokens, incomplete, finish_reason, answered_by_model_id) \
+             SELECT ?, dt, response_dt, model_id, chat_role, content, reasoning_content, tool_calls, tool_call_id, name, error, reasoning_tokens, reasoning_tokens_estimated, prompt_tokens, completion_tokens, incomplete, finish_reason, answered_by_model_id \
+             FROM chat_message WHERE chat_id = ? AND NOT incomplete ORDER BY id"
+        )
+        .bind(chat.id)
+        .bind(chat_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("INSERT INTO chat_model (chat_id, model_id, display_order) SELECT ?, model_id, display_order FROM chat_model WHERE chat_id = ?")
+            .bind(chat.id)
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO chat_tool (chat_id, tool_id) SELECT ?, tool_id FROM chat_tool WHERE chat_id = ?")
+            .bind(chat.id)
+            .bind(chat_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(chat)
+    }
+
+    /// The model imported chats are assigned to, created on first use. Its provider has no api key and
+    /// is hidden like a removed one, so the model only shows up as the name on those chats
+    #[instrument(level = "info", skip(self))]
+    pub async fn get_or_create_import_model(&self, provider_name: &str, model_name: &str) -> Result<i64> {
+        let now = chrono::Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+        let provider_id: Option<i64> = sqlx::query_scalar("SELECT id FROM provider WHERE name = ? AND deprecated")
+            .bind(provider_name)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let provider_id = match provider_id {
+            Some(provider_id) => provider_id,
+            None => {
+                sqlx::query_scalar(
+                    "INSERT INTO provider (name, base_url, disabled, deprecated, api_key_env_var, created_dt, no_models_endpoint) VALUES (?, '', 1, 1, '', ?, 1) RETURNING id"
+                )
+                .bind(provider_name)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+
+        let model_id: Option<i64> = sqlx::query_scalar("SELECT id FROM model WHERE provider_id = ? AND model = ?")
+            .bind(provider_id)
+            .bind(model_name)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let model_id = match model_id {
+            Some(model_id) => model_id,
+            None => {
+                sqlx::query_scalar(
+                    "INSERT INTO model (provider_id, model, api_type, disabled, deprecated, created_dt, supports_json_mode) VALUES (?, ?, 0, 1, 1, ?, 0) RETURNING id"
+                )
+                .bind(provider_id)
+                .bind(model_name)
+                .bind(now)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+        tx.commit().await?;
+
+        Ok(model_id)
+    }
+
+    /// Add a chat from another app's history with its messages and the model they're assigned to,
+    /// all at once so a failed import doesn't leave half a chat behind. The messages' chat ids are ignored
+    #[instrument(level = "info", skip(self, messages), fields(message_count = messages.len()))]
+    pub async fn import_chat(&self, title: Option<&str>, dt: i64, model_id: i64, messages: &[ChatMessage]) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let chat_id: i64 = sqlx::query_scalar("INSERT INTO chat (dt, title) VALUES (?, ?) RETURNING id")
+            .bind(dt)
+            .bind(title)
+            .fetch_one(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO chat_model (chat_id, model_id, display_order) VALUES (?, ?, 0)")
+            .bind(chat_id)
+            .bind(model_id)
+            .execute(&mut *tx)
+            .await?;
+        for message in messages {
+            sqlx::query(
+                "INSERT INTO chat_message (chat_id, dt, response_dt, model_id, chat_role, content, incomplete) VALUES (?, ?, ?, ?, ?, ?, 0)"
+            )
+            .bind(chat_id)
+            .bind(message.dt)
+            .bind(message.response_dt)
+            .bind(message.model_id)
+            .bind(message.chat_role)
+            .bind(&message.content)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(chat_id)
+    }
+
     /// Search chats by title using FTS
     #[instrument(level = "info", skip(self))]
     pub async fn search_chats(&self, query: &str, limit: i32) -> Result<Vec<Chat>> {
@@ -341,10 +1226,10 @@ impl Database {
         
         let chats = sqlx::query_as::<_, Chat>(
             r#"
-            SELECT c.id, c.dt, c.title
+            SELECT c.id, c.dt, c.title, c.archived, c.json_mode, c.stop_sequences, c.system_prompt
             FROM chat c
             JOIN chat_fts ON chat_fts.rowid = c.id
-            WHERE chat_fts MATCH ?
+            WHERE chat_fts MATCH ? AND NOT c.archived
             ORDER BY c.dt DESC
             LIMIT ?
             "#
@@ -369,11 +1254,11 @@ impl Database {
         
         let chats = sqlx::query_as::<_, Chat>(
             r#"
-            SELECT DISTINCT c.id, c.dt, c.title
+            SELECT DISTINCT c.id, c.dt, c.title, c.archived, c.json_mode, c.stop_sequences, c.system_prompt
             FROM chat c
             JOIN chat_message cm ON cm.chat_id = c.id
             JOIN chat_message_fts ON chat_message_fts.rowid = cm.id
-            WHERE chat_message_fts MATCH ?
+            WHERE chat_message_fts MATCH ? AND NOT c.archived
             ORDER BY c.dt DESC
             LIMIT ?
             "#
@@ -388,7 +1273,7 @@ impl Database {
 
     /// Combined search across both chat titles and messages
     #[instrument(level = "info", skip(self))]
-    pub async fn search_all(&self, query: &str, limit: i32) -> Result<Vec<Chat>> {
+    pub async fn search_all(&self, query: &str, limit: i32, include_archived: bool) -> Result<Vec<Chat>> {
         if query.trim().is_empty() {
             return self.get_recent_chats(limit).await;
         }
@@ -398,26 +1283,469 @@ impl Database {
         
         let chats = sqlx::query_as::<_, Chat>(
             r#"
-            SELECT DISTINCT c.id, c.dt, c.title
+            SELECT DISTINCT c.id, c.dt, c.title, c.archived, c.json_mode, c.stop_sequences, c.system_prompt
             FROM chat c
             JOIN chat_fts ON chat_fts.rowid = c.id
-            WHERE chat_fts MATCH ?
+            WHERE chat_fts MATCH ? AND (? OR NOT c.archived)
             UNION
-            SELECT DISTINCT c.id, c.dt, c.title
+            SELECT DISTINCT c.id, c.dt, c.title, c.archived, c.json_mode, c.stop_sequences, c.system_prompt
             FROM chat c
             JOIN chat_message cm ON cm.chat_id = c.id
             JOIN chat_message_fts ON chat_message_fts.rowid = cm.id
-            WHERE chat_message_fts MATCH ?
+            WHERE chat_message_fts MATCH ? AND (? OR NOT c.archived)
             ORDER BY dt DESC
             LIMIT ?
             "#
         )
         .bind(&search_query)
+        .bind(include_archived)
         .bind(&search_query)
+        .bind(include_archived)
         .bind(limit)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(chats)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::provider::provider::DEFAULT_REQUEST_TIMEOUT;
+
+    use std::{
+        ops::Deref,
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    static NEXT_TEST_DATABASE: AtomicUsize = AtomicUsize::new(0);
+
+    /// A migrated database in its own temp file, removed along with its WAL files when dropped,
+    /// even if the test panics
+    pub(crate) struct TestDatabase {
+        database: Database,
+        path: PathBuf,
+    }
+
+    impl Deref for TestDatabase {
+        type Target = Database;
+
+        fn deref(&self) -> &Database {
+            &self.database
+        }
+    }
+
+    impl Drop for TestDatabase {
+        fn drop(&mut self) {
+            remove_db_files(&self.path);
+        }
+    }
+
+    fn remove_db_files(db_path: &Path) {
+        let _ = std::fs::remove_file(db_path);
+        for suffix in ["-wal", "-shm"] {
+            let mut path = db_path.as_os_str().to_owned();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// A fresh database for one test. Tests run in parallel, so each gets a path of its own
+    pub(crate) async fn test_database() -> TestDatabase {
+        let path = std::env::temp_dir().join(format!(
+            "shore-test-{}-{}.db",
+            std::process::id(),
+            NEXT_TEST_DATABASE.fetch_add(1, Ordering::Relaxed)
+        ));
+        remove_db_files(&path);
+        let database = Database::new(&path).await.unwrap();
+        TestDatabase { database, path }
+    }
+
+    #[tokio::test]
+    async fn test_new_enables_wal_and_keeps_foreign_keys() {
+        let database = test_database().await;
+
+        let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode").fetch_one(&database.pool).await.unwrap();
+        assert_eq!(journal_mode, "wal");
+        // 1 is NORMAL
+        let synchronous: i64 = sqlx::query_scalar("PRAGMA synchronous").fetch_one(&database.pool).await.unwrap();
+        assert_eq!(synchronous, 1);
+
+        // a message for a chat that doesn't exist is still rejected
+        let message = ChatMessage::new_user_message(i64::MAX, "orphan".to_string());
+        assert!(database.add_chat_message(&message).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_chat_messages_interleave_models_in_a_stable_order() {
+        let database = test_database().await;
+
+        let models = database.get_all_models(false).await.unwrap();
+        let (first_model, second_model) = (models[0].id.min(models[1].id), models[0].id.max(models[1].id));
+        let chat_id = database.create_chat(None).await.unwrap();
+        let mut expected = Vec::new();
+        for (turn, dt) in [1_000, 2_000].into_iter().enumerate() {
+            let mut prompt = ChatMessage::new_user_message(chat_id, format!("prompt {}", turn));
+            prompt.dt = dt;
+            expected.push(database.add_chat_message(&prompt).await.unwrap());
+
+            // every response shares its prompt's dt, and they're written in whatever order they finish
+            let second = ChatMessage::new_assistant_message(chat_id, second_model, "second".to_string(), dt);
+            let first = ChatMessage::new_assistant_message(chat_id, first_model, "first".to_string(), dt);
+            let second_id = database.add_chat_message(&second).await.unwrap();
+            let first_id = database.add_chat_message(&first).await.unwrap();
+            // a retry answers the same prompt again and goes after the earlier reply
+            let retry_id = database.add_chat_message(&first).await.unwrap();
+            expected.extend([first_id, retry_id, second_id]);
+        }
+
+        for _ in 0..3 {
+            let ids: Vec<i64> = database.get_chat_messages(chat_id).await.unwrap().iter().map(|message| message.id).collect();
+            assert_eq!(ids, expected);
+        }
+
+        // paging by one prompt at a time brings back the same messages, newest page first
+        let ids = |messages: Vec<ChatMessage>| messages.iter().map(|message| message.id).collect::<Vec<i64>>();
+        let newest = database.get_chat_messages_paged(chat_id, None, 1).await.unwrap();
+        assert_eq!(ids(newest.clone()), expected[4..]);
+        let older = database.get_chat_messages_paged(chat_id, Some(newest[0].dt), 1).await.unwrap();
+        assert_eq!(ids(older.clone()), expected[..4]);
+        assert!(database.get_chat_messages_paged(chat_id, Some(older[0].dt), 1).await.unwrap().is_empty());
+        assert_eq!(ids(database.get_chat_messages_paged(chat_id, None, 5).await.unwrap()), expected);
+    }
+
+    #[tokio::test]
+    async fn test_swapped_chats_keep_their_places() {
+        let database = test_database().await;
+
+        // created within the same second, so only their ids tell them apart
+        let mut chat_ids = Vec::new();
+        for _ in 0..3 {
+            chat_ids.push(database.create_chat(None).await.unwrap());
+        }
+        let (first, second, third) = (chat_ids[0], chat_ids[1], chat_ids[2]);
+        let listed = || async {
+            let chats = database.get_chats(false, ChatSort::Date, 10, 0).await.unwrap();
+            chats.iter().map(|chat| chat.id).collect::<Vec<i64>>()
+        };
+        assert_eq!(listed().await, [third, second, first]);
+
+        database.swap_chat_places(first, second).await.unwrap();
+        assert_eq!(listed().await, [third, first, second]);
+        database.swap_chat_places(first, third).await.unwrap();
+        assert_eq!(listed().await, [first, third, second]);
+    }
+
+    #[tokio::test]
+    async fn test_default_profile_is_created_and_fetched_under_the_same_id() {
+        let database = test_database().await;
+
+        assert!(!database.chat_profile_exists(DEFAULT_PROFILE_ID).await.unwrap());
+        let model_id = database.get_all_models(false).await.unwrap()[0].id;
+        database.create_default_chat_profile(model_id).await.unwrap();
+        assert!(database.chat_profile_exists(DEFAULT_PROFILE_ID).await.unwrap());
+        let profile = database.get_chat_profile(DEFAULT_PROFILE_ID).await.unwrap();
+        assert_eq!(profile.model_ids, vec![model_id]);
+
+        // named profiles never share the default profile's id, and can't delete it
+        let named_id = database.save_named_profile("work", vec![model_id]).await.unwrap();
+        assert_ne!(named_id, DEFAULT_PROFILE_ID);
+        database.delete_named_profile(DEFAULT_PROFILE_ID).await.unwrap();
+        assert!(database.chat_profile_exists(DEFAULT_PROFILE_ID).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_context_window_is_set_by_provider_and_model_name() {
+        let database = test_database().await;
+
+        let model = database.get_all_models(false).await.unwrap().remove(0);
+        let provider = database.get_providers().await.unwrap().into_iter().find(|provider| provider.id == model.provider_id).unwrap();
+        let context_window = || async {
+            database.get_all_models(false).await.unwrap().into_iter().find(|m| m.id == model.id).unwrap().context_window
+        };
+
+        // provider names match regardless of case, like --add-model
+        database.set_model_context_window(&provider.name.to_uppercase(), &model.model, Some(8000)).await.unwrap();
+        assert_eq!(context_window().await, Some(8000));
+        database.set_model_context_window(&provider.name, &model.model, None).await.unwrap();
+        assert_eq!(context_window().await, None);
+
+        assert!(database.set_model_context_window(&provider.name, "no-such-model", Some(1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_provider_setting() {
+        let database = test_database().await;
+        let provider = || async {
+            database.get_providers().await.unwrap().into_iter().find(|provider| provider.name == "OpenAI").unwrap()
+        };
+
+        // provider names match regardless of case, like the model setters
+        database.set_provider_setting("openai", "model_include", " gpt-*, o? ").await.unwrap();
+        database.set_provider_setting("OpenAI", "model_exclude", "*-mini").await.unwrap();
+        assert_eq!(provider().await.model_include.as_deref(), Some("gpt-*, o?"));
+        assert!(provider().await.includes_model("gpt-4o"));
+        assert!(!provider().await.includes_model("gpt-4o-mini"));
+
+        database.set_provider_setting("OpenAI", "model_include", "").await.unwrap();
+        assert_eq!(provider().await.model_include, None);
+
+        database.set_provider_setting("OpenAI", "system_role", "developer").await.unwrap();
+        assert!(provider().await.uses_developer_role());
+        assert!(database.set_provider_setting("OpenAI", "system_role", "assistant").await.is_err());
+        assert!(provider().await.uses_developer_role());
+        database.set_provider_setting("OpenAI", "system_role", "").await.unwrap();
+        assert!(!provider().await.uses_developer_role());
+
+        database.set_provider_setting("OpenAI", "request_timeout_secs", "300").await.unwrap();
+        assert_eq!(provider().await.request_timeout(), Duration::from_secs(300));
+        assert!(database.set_provider_setting("OpenAI", "request_timeout_secs", "0").await.is_err());
+        assert!(database.set_provider_setting("OpenAI", "request_timeout_secs", "soon").await.is_err());
+        database.set_provider_setting("OpenAI", "request_timeout_secs", "").await.unwrap();
+        assert_eq!(provider().await.request_timeout(), DEFAULT_REQUEST_TIMEOUT);
+
+        database.set_provider_setting("OpenAI", "extra_headers", r#"{"X-Title": "shore"}"#).await.unwrap();
+        assert_eq!(provider().await.extra_headers(), [("X-Title".to_string(), "shore".to_string())]);
+        for bad in [r#"["X-Title"]"#, r#"{"X-Title": 1}"#, r#"{"X Title": "shore"}"#, r#"{"X-Title": "a\nb"}"#] {
+            assert!(database.set_provider_setting("OpenAI", "extra_headers", bad).await.is_err(), "{}", bad);
+        }
+        assert_eq!(provider().await.extra_headers().len(), 1);
+        database.set_provider_setting("OpenAI", "extra_headers", "").await.unwrap();
+        assert!(provider().await.extra_headers().is_empty());
+
+        database.set_provider_setting("OpenAI", "no_models_endpoint", "true").await.unwrap();
+        assert!(provider().await.no_models_endpoint);
+        assert!(database.set_provider_setting("OpenAI", "no_models_endpoint", "yes").await.is_err());
+        assert!(provider().await.no_models_endpoint);
+        database.set_provider_setting("OpenAI", "no_models_endpoint", "").await.unwrap();
+        assert!(!provider().await.no_models_endpoint);
+
+        assert!(database.set_provider_setting("OpenAI", "base_url", "http://localhost").await.is_err());
+        assert!(database.set_provider_setting("NoSuchProvider", "model_include", "*").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_built_in_providers_without_a_models_endpoint_are_never_synced() {
+        let database = test_database().await;
+
+        let mut without_endpoint: Vec<String> = database
+            .get_providers()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|provider| provider.no_models_endpoint)
+            .map(|provider| provider.name)
+            .collect();
+        without_endpoint.sort();
+        assert_eq!(without_endpoint, ["MiniMax", "Perplexity", "zAI"]);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_chat_copies_settings_messages_and_models() {
+        let database = test_database().await;
+
+        let model_id = database.get_all_models(false).await.unwrap()[0].id;
+        let chat_id = database.create_chat(Some("Original".to_string())).await.unwrap();
+        database.set_chat_models(chat_id, vec![model_id]).await.unwrap();
+        database.set_chat_system_prompt(chat_id, Some("Be brief.".to_string())).await.unwrap();
+        database.set_chat_json_mode(chat_id, true).await.unwrap();
+        database.set_chat_stop_sequences(chat_id, Some(r#"["END"]"#.to_string())).await.unwrap();
+
+        let prompt = ChatMessage::new_user_message(chat_id, "prompt".to_string());
+        database.add_chat_message(&prompt).await.unwrap();
+        let response = ChatMessage::new_assistant_message(chat_id, model_id, "response".to_string(), prompt.dt);
+        database.add_chat_message(&response).await.unwrap();
+        // a response still being generated stays with the original
+        let mut placeholder = ChatMessage::new_assistant_message(chat_id, model_id, String::new(), prompt.dt);
+        placeholder.incomplete = true;
+        database.add_chat_message(&placeholder).await.unwrap();
+
+        let copy = database.duplicate_chat(chat_id).await.unwrap();
+        assert_ne!(copy.id, chat_id);
+        assert_eq!(copy.title.as_deref(), Some("Original (copy)"));
+        assert_eq!(copy.system_prompt.as_deref(), Some("Be brief."));
+        assert!(copy.json_mode);
+        assert_eq!(copy.stop_sequence_list(), ["END"]);
+        assert_eq!(database.get_chat_models_ids(copy.id).await.unwrap(), [model_id]);
+
+        let contents = |messages: Vec<ChatMessage>| messages.into_iter().map(|message| message.content).collect::<Vec<_>>();
+        assert_eq!(
+            contents(database.get_chat_messages(copy.id).await.unwrap()),
+            [Some("prompt".to_string()), Some("response".to_string())]
+        );
+        assert_eq!(database.get_chat_messages(chat_id).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_delete_all_chats_takes_their_messages_models_and_bookmarks() {
+        let database = test_database().await;
+
+        let model_id = database.get_all_models(false).await.unwrap()[0].id;
+        for title in ["First", "Second"] {
+            let chat_id = database.create_chat(Some(title.to_string())).await.unwrap();
+            database.set_chat_models(chat_id, vec![model_id]).await.unwrap();
+            let message_id = database.add_chat_message(&ChatMessage::new_user_message(chat_id, "prompt".to_string())).await.unwrap();
+            database.toggle_bookmark(message_id).await.unwrap();
+        }
+        database.set_setting("show_timestamps", "true").await.unwrap();
+
+        assert_eq!(database.delete_all_chats().await.unwrap(), 2);
+        assert!(database.get_chats(true, ChatSort::Date, 10, 0).await.unwrap().is_empty());
+        for table in ["chat_message", "chat_model"] {
+            let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {}", table)).fetch_one(&database.pool).await.unwrap();
+            assert_eq!(rows, 0, "{} rows were left behind", table);
+        }
+        assert!(database.get_bookmarks().await.unwrap().is_empty());
+        // only chats go, settings stay
+        assert!(database.get_settings().await.unwrap().contains(&("show_timestamps".to_string(), "true".to_string())));
+        assert_eq!(database.delete_all_chats().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_chat_message_pages_keep_responses_with_their_prompt() {
+        let database = test_database().await;
+
+        let model_id = database.get_all_models(false).await.unwrap()[0].id;
+        let chat_id = database.create_chat(None).await.unwrap();
+        for (dt, prompt) in [(1000, "first"), (2000, "second"), (3000, "third")] {
+            database.add_chat_message(&ChatMessage { dt, ..ChatMessage::new_user_message(chat_id, prompt.to_string()) }).await.unwrap();
+            database.add_chat_message(&ChatMessage::new_assistant_message(chat_id, model_id, format!("{} reply", prompt), dt)).await.unwrap();
+        }
+        let page = |before_dt, limit| {
+            let database = &database;
+            async move {
+                database
+                    .get_chat_messages_paged(chat_id, before_dt, limit)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|message| message.content.unwrap())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        // a page of one prompt still holds its response, which has the same dt
+        assert_eq!(page(None, 1).await, ["third", "third reply"]);
+        // before_dt is exclusive
+        assert_eq!(page(Some(3000), 1).await, ["second", "second reply"]);
+        assert_eq!(page(Some(2001), 1).await, ["second", "second reply"]);
+        assert_eq!(page(Some(3000), 5).await, ["first", "first reply", "second", "second reply"]);
+        assert!(page(Some(1000), 1).await.is_empty());
+        assert_eq!(page(None, 10).await.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_get_chat_prompts_leaves_out_asides_and_responses() {
+        let database = test_database().await;
+
+        let model_id = database.get_all_models(false).await.unwrap()[0].id;
+        let chat_id = database.create_chat(None).await.unwrap();
+        for (dt, prompt) in [(1000, "first"), (2000, "second"), (3000, "third")] {
+            database.add_chat_message(&ChatMessage { dt, ..ChatMessage::new_user_message(chat_id, prompt.to_string()) }).await.unwrap();
+            database.add_chat_message(&ChatMessage::new_assistant_message(chat_id, model_id, "reply".to_string(), dt)).await.unwrap();
+        }
+        database.add_chat_message(&ChatMessage::new_aside_message(chat_id, "aside".to_string())).await.unwrap();
+
+        assert_eq!(database.get_chat_prompts(chat_id, 10).await.unwrap(), ["first", "second", "third"]);
+        // the limit keeps the newest
+        assert_eq!(database.get_chat_prompts(chat_id, 2).await.unwrap(), ["second", "third"]);
+    }
+
+    #[tokio::test]
+    async fn test_request_log_keeps_the_newest_entries() {
+        let database = test_database().await;
+
+        let chat_id = database.create_chat(None).await.unwrap();
+        for (dt, error) in [(1000, None), (2000, Some("timeout".to_string())), (3000, None)] {
+            let entry = RequestLogEntry { id: 0, dt, chat_id: Some(chat_id), model_id: 1, model: "gpt-5".to_string(), message_count: 3, latency_ms: 1200, prompt_tokens: Some(10), completion_tokens: None, error };
+            database.add_request_log_entry(&entry).await.unwrap();
+        }
+        let dts = |entries: Vec<RequestLogEntry>| entries.into_iter().map(|entry| entry.dt).collect::<Vec<_>>();
+        assert_eq!(dts(database.get_request_log(10).await.unwrap()), [3000, 2000, 1000]);
+        assert_eq!(database.get_request_log(10).await.unwrap()[1].error.as_deref(), Some("timeout"));
+
+        // under the cap nothing goes, past it the oldest entries do
+        database.prune_request_log(5).await.unwrap();
+        assert_eq!(dts(database.get_request_log(10).await.unwrap()), [3000, 2000, 1000]);
+        database.prune_request_log(2).await.unwrap();
+        assert_eq!(dts(database.get_request_log(10).await.unwrap()), [3000, 2000]);
+
+        // deleting the chat keeps its entries
+        database.delete_chat(chat_id).await.unwrap();
+        let entries = database.get_request_log(10).await.unwrap();
+        assert!(entries.iter().all(|entry| entry.chat_id.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_add_manual_model() {
+        let database = test_database().await;
+
+        // provider names are matched without case, Perplexity has no models endpoint to sync from
+        let model_id = database.add_manual_model("perplexity", "sonar-pro").await.unwrap();
+        let model = database.get_all_models(false).await.unwrap().into_iter().find(|model| model.id == model_id).unwrap();
+        assert_eq!(model.model, "sonar-pro");
+        let perplexity = database.get_providers().await.unwrap().into_iter().find(|provider| provider.name == "Perplexity").unwrap();
+        assert_eq!(model.provider_id, perplexity.id);
+
+        // adding it again offers the same model again, even after it went away
+        sqlx::query("UPDATE model SET deprecated = 1 WHERE id = ?").bind(model_id).execute(&database.pool).await.unwrap();
+        assert_eq!(database.add_manual_model("Perplexity", "sonar-pro").await.unwrap(), model_id);
+        let deprecated: bool = sqlx::query_scalar("SELECT deprecated FROM model WHERE id = ?").bind(model_id).fetch_one(&database.pool).await.unwrap();
+        assert!(!deprecated);
+
+        assert!(database.add_manual_model("Nobody", "sonar-pro").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_model_alias_is_shown_instead_of_the_name() {
+        let database = test_database().await;
+
+        let model = database.get_all_models(false).await.unwrap().remove(0);
+        assert_eq!(model.display_name(), model.model);
+        let find = |models: Vec<Model>| models.into_iter().find(|found| found.id == model.id).unwrap();
+
+        database.set_model_alias(model.id, Some("Work model".to_string())).await.unwrap();
+        let aliased = find(database.get_all_models(false).await.unwrap());
+        assert_eq!(aliased.display_name(), "Work model");
+        // the alias is only for showing, requests still use the name
+        assert_eq!(aliased.model, model.model);
+
+        database.set_model_alias(model.id, None).await.unwrap();
+        assert_eq!(find(database.get_all_models(false).await.unwrap()).display_name(), model.model);
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_retried_while_another_connection_holds_the_lock() {
+        let database = test_database().await;
+        // a second connection that gives up right away instead of waiting on the lock
+        let impatient = SqlitePool::connect_with(SqliteConnectOptions::new().filename(&database.path).busy_timeout(Duration::ZERO)).await.unwrap();
+        let write = || sqlx::query("INSERT INTO setting (key, value) VALUES ('retry_test', '1')").execute(&impatient);
+
+        let mut holder = database.pool.acquire().await.unwrap();
+        sqlx::query("BEGIN EXCLUSIVE").execute(&mut *holder).await.unwrap();
+        let error = write().await.unwrap_err();
+        assert!(is_busy_error(&error), "{}", error);
+        assert!(!is_busy_error(&sqlx::Error::RowNotFound));
+
+        // the lock is let go while the retries back off
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(BUSY_RETRY_BACKOFF * 2).await;
+            sqlx::query("COMMIT").execute(&mut *holder).await.unwrap();
+        });
+        retry_if_busy(write).await.unwrap();
+        release.await.unwrap();
+
+        // anything else fails on the first try
+        let mut attempts = 0;
+        let result: sqlx::Result<()> = retry_if_busy(|| {
+            attempts += 1;
+            async { Err(sqlx::Error::RowNotFound) }
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,190 @@
+use std::io;
+
+use crossterm::{
+    cursor::{RestorePosition, SavePosition},
+    queue,
+};
+use ratatui::{
+    backend::Backend,
+    buffer::{Buffer, Cell},
+    style::{Color, Modifier},
+    text::Span,
+};
+
+/// A rendered link and the cells showing it, possibly wrapped over several rows
+pub struct Hyperlink {
+    pub url: String,
+    pub cells: Vec<(u16, u16, Cell)>,
+}
+
+/// Links are underlined in the theme's link color (see markdown.rs), headings may share it but are also bold
+fn is_link_cell(cell: &Cell, link_color: Color) -> bool {
+    cell.fg == link_color
+        && cell.modifier.contains(Modifier::UNDERLINED)
+        && !cell.modifier.contains(Modifier::BOLD)
+}
+
+/// Blank space or a vertical border, i.e. nothing that would be part of wrapped text
+fn is_padding(cell: &Cell) -> bool {
+    cell.symbol()
+        .chars()
+        .all(|ch| ch.is_whitespace() || matches!(ch, '│' | '┃' | '║'))
+}
+
+fn is_padding_between(buffer: &Buffer, y: u16, from_x: u16, to_x: u16) -> bool {
+    (from_x..to_x).all(|x| is_padding(&buffer[(x, y)]))
+}
+
+/// The url a link's text points to. Bare urls are shown as is, markdown links as "text (url)"
+fn link_url(text: &str) -> Option<String> {
+    let is_url = |url: &str| {
+        (url.starts_with("http://") || url.starts_with("https://"))
+            && !url.chars().any(|ch| ch.is_control() || ch.is_whitespace())
+    };
+    if is_url(text) {
+        return Some(text.to_string());
+    }
+    let (_, url) = text.strip_suffix(')')?.rsplit_once(" (")?;
+    is_url(url).then(|| url.to_string())
+}
+
+/// Find the links in a rendered frame. A run of link cells that ends a row is joined with one
+/// starting the next row, since that's a link wrapped by the chat view
+pub fn find_hyperlinks(buffer: &Buffer, link_color: Color) -> Vec<Hyperlink> {
+    let area = buffer.area;
+    let mut runs: Vec<Vec<(u16, u16, Cell)>> = Vec::new();
+    for y in area.top()..area.bottom() {
+        let mut run = Vec::new();
+        let mut hidden = 0;
+        for x in area.left()..area.right() {
+            let cell = &buffer[(x, y)];
+            // the cells covered by a wide character aren't drawn
+            if hidden > 0 {
+                hidden -= 1;
+                continue;
+            }
+            hidden = Span::raw(cell.symbol()).width().saturating_sub(1);
+
+            if is_link_cell(cell, link_color) {
+                run.push((x, y, cell.clone()));
+            } else if !run.is_empty() {
+                runs.push(std::mem::take(&mut run));
+            }
+        }
+        if !run.is_empty() {
+            runs.push(run);
+        }
+    }
+
+    let mut joined: Vec<Vec<(u16, u16, Cell)>> = Vec::new();
+    for mut run in runs {
+        while run.last().is_some_and(|(_, _, cell)| is_padding(cell)) {
+            run.pop();
+        }
+        let Some(&(start_x, y, _)) = run.first() else {
+            continue;
+        };
+
+        if let Some(previous) = joined.last_mut()
+            && let Some(&(end_x, previous_y, _)) = previous.last()
+            && previous_y + 1 == y
+            && is_padding_between(buffer, previous_y, end_x + 1, area.right())
+            && is_padding_between(buffer, y, area.left(), start_x)
+        {
+            previous.extend(run);
+        } else {
+            joined.push(run);
+        }
+    }
+
+    joined
+        .into_iter()
+        .filter_map(|cells| {
+            let text: String = cells.iter().map(|(_, _, cell)| cell.symbol()).collect();
+            link_url(&text).map(|url| Hyperlink { url, cells })
+        })
+        .collect()
+}
+
+/// Redraw the links' cells wrapped in OSC 8 escape sequences so supporting terminals make them clickable.
+/// This goes straight to the backend after the frame is drawn since ratatui counts the escape sequences
+/// towards a cell's width
+pub fn draw_hyperlinks<B: Backend + io::Write>(
+    backend: &mut B,
+    hyperlinks: &[Hyperlink],
+) -> io::Result<()> {
+    if hyperlinks.is_empty() {
+        return Ok(());
+    }
+
+    let mut cells = Vec::new();
+    for hyperlink in hyperlinks {
+        for (idx, (x, y, cell)) in hyperlink.cells.iter().enumerate() {
+            // each row of a wrapped link is opened and closed on its own
+            let row_start = idx == 0 || hyperlink.cells[idx - 1].1 != *y;
+            let row_end = hyperlink.cells.get(idx + 1).is_none_or(|next| next.1 != *y);
+            let mut symbol = cell.symbol().to_string();
+            if row_start {
+                symbol = format!("\x1b]8;;{}\x1b\\{}", hyperlink.url, symbol);
+            }
+            if row_end {
+                symbol.push_str("\x1b]8;;\x1b\\");
+            }
+            let mut cell = cell.clone();
+            cell.set_symbol(&symbol);
+            cells.push((*x, *y, cell));
+        }
+    }
+
+    queue!(backend, SavePosition)?;
+    backend.draw(cells.iter().map(|(x, y, cell)| (*x, *y, cell)))?;
+    queue!(backend, RestorePosition)?;
+    Backend::flush(backend)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{layout::Rect, style::Style};
+
+    fn link_style() -> Style {
+        Style::default().fg(Color::Blue).add_modifier(Modifier::UNDERLINED)
+    }
+
+    #[test]
+    fn test_link_url() {
+        assert_eq!(link_url("https://example.com/a?b=c").as_deref(), Some("https://example.com/a?b=c"));
+        assert_eq!(link_url("the docs (https://example.com)").as_deref(), Some("https://example.com"));
+        assert_eq!(link_url("example.com"), None);
+        assert_eq!(link_url("the docs (example.com)"), None);
+        assert_eq!(link_url("https://example.com/a b"), None);
+    }
+
+    #[test]
+    fn test_find_hyperlinks_joins_a_wrapped_link() {
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 12, 3));
+        buffer.set_string(0, 0, "see ", Style::default());
+        buffer.set_string(4, 0, "https://", link_style());
+        buffer.set_string(0, 1, "example.com", link_style());
+        // a heading in the link color isn't a link
+        buffer.set_string(0, 2, "https://x.y", link_style().add_modifier(Modifier::BOLD));
+
+        let hyperlinks = find_hyperlinks(&buffer, Color::Blue);
+        assert_eq!(hyperlinks.len(), 1);
+        assert_eq!(hyperlinks[0].url, "https://example.com");
+        let rows: Vec<u16> = hyperlinks[0].cells.iter().map(|(_, y, _)| *y).collect();
+        assert_eq!(rows, [0; 8].into_iter().chain([1; 11]).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn test_find_hyperlinks_keeps_links_on_separate_rows_apart() {
+        // the first row doesn't end in the link, so the second row's link is its own
+        let mut buffer = Buffer::empty(Rect::new(0, 0, 24, 2));
+        buffer.set_string(0, 0, "https://a.example", link_style());
+        buffer.set_string(18, 0, "text", Style::default());
+        buffer.set_string(0, 1, "https://b.example", link_style());
+
+        let urls: Vec<String> = find_hyperlinks(&buffer, Color::Blue).into_iter().map(|link| link.url).collect();
+        assert_eq!(urls, ["https://a.example", "https://b.example"]);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    database::Database,
+    model::chat::{ChatMessage, ChatRole},
+};
+use anyhow::Result;
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+use tracing::{info, instrument};
+
+/// Imported ChatGPT chats are assigned to this placeholder provider and model
+const CHATGPT_PROVIDER_NAME: &str = "ChatGPT (imported)";
+const CHATGPT_MODEL_NAME: &str = "chatgpt";
+
+/// One conversation from the conversations.json of a ChatGPT data export. Messages form a tree,
+/// since editing a prompt or regenerating a response branches it, keyed by node id
+#[derive(Debug, Deserialize)]
+struct Conversation {
+    title: Option<String>,
+    create_time: Option<f64>, // seconds, with a fraction
+    #[serde(default)]
+    mapping: HashMap<String, ConversationNode>,
+    current_node: Option<String>, // the last message of the branch that was showing
+}
+
+#[derive(Debug, Deserialize)]
+struct ConversationNode {
+    message: Option<ConversationMessage>,
+    parent: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConversationMessage {
+    author: ConversationAuthor,
+    create_time: Option<f64>,
+    content: ConversationContent,
+    #[serde(default)]
+    metadata: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ConversationAuthor {
+    role: String, // user, assistant, system or tool
+}
+
+#[derive(Debug, Deserialize)]
+struct ConversationContent {
+    content_type: String,
+    #[serde(default)]
+    parts: Vec<serde_json::Value>, // strings, or objects for images and other attachments
+}
+
+impl ConversationMessage {
+    /// The message's text, None for anything shore can't show: system and tool messages, hidden
+    /// ones, code and browsing output, or messages that are only attachments
+    fn text(&self) -> Option<(ChatRole, String)> {
+        let role = match self.author.role.as_str() {
+            "user" => ChatRole::User,
+            "assistant" => ChatRole::Assistant,
+            _ => return None,
+        };
+        if self.metadata["is_visually_hidden_from_conversation"].as_bool() == Some(true) {
+            return None;
+        }
+        if !matches!(self.content.content_type.as_str(), "text" | "multimodal_text") {
+            return None;
+        }
+        let text = self
+            .content
+            .parts
+            .iter()
+            .filter_map(|part| part.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let text = text.trim();
+        (!text.is_empty()).then(|| (role, text.to_string()))
+    }
+}
+
+fn to_millis(seconds: f64) -> i64 {
+    (seconds * 1000.0) as i64
+}
+
+/// The messages of the branch that was showing, oldest first, as shore messages for `model_id`.
+/// Responses take their prompt's dt like shore's own, and a prompt answered in several messages
+/// (e.g. around a tool call) gets them joined into one response
+fn conversation_messages(conversation: &Conversation, model_id: i64) -> Vec<ChatMessage> {
+    // without a current node, follow the branch ending in the newest message
+    let current_node = conversation.current_node.clone().or_else(|| {
+        conversation
+            .mapping
+            .iter()
+            .filter_map(|(id, node)| Some((id, node.message.as_ref()?.create_time?)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(id, _)| id.clone())
+    });
+
+    let mut branch = Vec::new();
+    let mut node_id = current_node;
+    // the length cap guards against a malformed export whose parents loop
+    while let Some(node) = node_id.and_then(|id| conversation.mapping.get(&id))
+        && branch.len() < conversation.mapping.len()
+    {
+        branch.push(node);
+        node_id = node.parent.clone();
+    }
+    branch.reverse();
+
+    let mut messages: Vec<ChatMessage> = Vec::new();
+    let mut last_dt = conversation.create_time.map(to_millis).unwrap_or(0);
+    let mut prompt_dt = None;
+    for message in branch.iter().filter_map(|node| node.message.as_ref()) {
+        let Some((role, text)) = message.text() else {
+            continue;
+        };
+        let dt = message.create_time.map(to_millis).unwrap_or(last_dt);
+        match role {
+            ChatRole::User => {
+                // prompts need distinct dts, responses are matched up with them by dt
+                let dt = dt.max(last_dt + 1);
+                last_dt = dt;
+                prompt_dt = Some(dt);
+                messages.push(ChatMessage {
+                    dt,
+                    ..ChatMessage::new_user_message(0, text)
+                });
+            }
+            _ => {
+                // shore has nowhere to show a response before the first prompt
+                let Some(prompt_dt) = prompt_dt else {
+                    continue;
+                };
+                if let Some(response) = messages
+                    .last_mut()
+                    .filter(|last| last.chat_role == ChatRole::Assistant)
+                {
+                    let content = response.content.get_or_insert_default();
+                    content.push_str("\n\n");
+                    content.push_str(&text);
+                    response.response_dt = Some(dt.max(prompt_dt));
+                } else {
+                    messages.push(ChatMessage {
+                        response_dt: Some(dt.max(prompt_dt)),
+                        ..ChatMessage::new_assistant_message(0, model_id, text, prompt_dt)
+                    });
+                }
+            }
+        }
+    }
+    messages
+}
+
+/// Import every conversation from a ChatGPT export's conversations.json as a chat. Conversations
+/// with nothing shore can show are skipped. Returns (chats, messages) imported
+#[instrument(level = "info", skip(database, path), fields(path = %path.display()))]
+pub async fn import_chatgpt_export(database: &Database, path: &Path) -> Result<(usize, usize)> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Could not read {}: {}", path.display(), e))?;
+    let conversations: Vec<Conversation> = serde_json::from_str(&json).map_err(|e| {
+        anyhow::anyhow!("{} is not a ChatGPT conversations.json: {}", path.display(), e)
+    })?;
+
+    let model_id = database
+        .get_or_create_import_model(CHATGPT_PROVIDER_NAME, CHATGPT_MODEL_NAME)
+        .await?;
+    let mut chat_count = 0;
+    let mut message_count = 0;
+    for conversation in &conversations {
+        let messages = conversation_messages(conversation, model_id);
+        if messages.is_empty() {
+            info!("Skipping conversation {:?} with no text messages", conversation.title);
+            continue;
+        }
+        let dt = conversation
+            .create_time
+            .map(|seconds| seconds as i64)
+            .unwrap_or(messages[0].dt / 1000);
+        let title = conversation
+            .title
+            .as_deref()
+            .map(str::trim)
+            .filter(|title| !title.is_empty());
+        database.import_chat(title, dt, model_id, &messages).await?;
+        chat_count += 1;
+        message_count += messages.len();
+    }
+
+    Ok((chat_count, message_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_messages_follow_the_current_branch() {
+        // the first response was regenerated, only the second one is on the current branch
+        let conversation: Conversation = serde_json::from_value(serde_json::json!({
+            "title": "Test",
+            "create_time": 1700000000.0,
+            "current_node": "d",
+            "mapping": {
+                "root": {"message": null, "parent": null},
+                "sys": {"parent": "root", "message": {
+                    "author": {"role": "system"}, "create_time": null,
+                    "content": {"content_type": "text", "parts": [""]}}},
+                "a": {"parent": "sys", "message": {
+                    "author": {"role": "user"}, "create_time": 1700000001.5,
+                    "content": {"content_type": "text", "parts": ["Hi"]}}},
+                "b": {"parent": "a", "message": {
+                    "author": {"role": "assistant"}, "create_time": 1700000002.0,
+                    "content": {"content_type": "text", "parts": ["Old answer"]}}},
+                "c": {"parent": "a", "message": {
+                    "author": {"role": "assistant"}, "create_time": 1700000003.0,
+                    "content": {"content_type": "code", "text": "print(1)"}}},
+                "d": {"parent": "c", "message": {
+                    "author": {"role": "assistant"}, "create_time": 1700000004.0,
+                    "content": {"content_type": "text", "parts": ["New answer"]}}}
+            }
+        }))
+        .unwrap();
+
+        let messages = conversation_messages(&conversation, 7);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].chat_role, ChatRole::User);
+        assert_eq!(messages[0].content.as_deref(), Some("Hi"));
+        assert_eq!(messages[0].dt, 1700000001500);
+        // the response shares its prompt's dt, like shore's own responses
+        assert_eq!(messages[1].chat_role, ChatRole::Assistant);
+        assert_eq!(messages[1].content.as_deref(), Some("New answer"));
+        assert_eq!(messages[1].dt, messages[0].dt);
+        assert_eq!(messages[1].response_dt, Some(1700000004000));
+        assert_eq!(messages[1].model_id, Some(7));
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+/// How many log lines are kept in memory, the oldest are dropped first
+pub const LOG_BUFFER_CAPACITY: usize = 2000;
+/// Longer lines (a logged request body, say) are cut, so the buffer stays around a few MB at most
+const MAX_LINE_CHARS: usize = 1000;
+
+/// Ring buffer of recent log lines so logs can be read inside the TUI instead of on stderr
+#[derive(Clone)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<String>> {
+        // a panic while holding the lock can't leave the buffer in a bad state, so keep using it
+        self.lines.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn push(&self, mut line: String) {
+        if let Some((cut, _)) = line.char_indices().nth(MAX_LINE_CHARS) {
+            line.truncate(cut);
+            line.push('…');
+        }
+        let mut lines = self.lock();
+        if lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Up to `count` lines ending `from_bottom` lines before the newest one, oldest first
+    pub fn tail(&self, from_bottom: usize, count: usize) -> Vec<String> {
+        let lines = self.lock();
+        let end = lines.len().saturating_sub(from_bottom);
+        let start = end.saturating_sub(count);
+        lines.range(start..end).cloned().collect()
+    }
+}
+
+/// Collects one formatted event and adds its lines to the buffer when dropped
+pub struct LogBufferWriter {
+    buffer: LogBuffer,
+    pending: Vec<u8>,
+}
+
+impl io::Write for LogBufferWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogBufferWriter {
+    fn drop(&mut self) {
+        let text = String::from_utf8_lossy(&self.pending);
+        for line in text.lines() {
+            self.buffer.push(line.to_string());
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogBuffer {
+    type Writer = LogBufferWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LogBufferWriter {
+            buffer: self.clone(),
+            pending: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_oldest_lines_are_dropped_at_capacity() {
+        let buffer = LogBuffer::new(3);
+        for i in 0..5 {
+            buffer.push(format!("line {}", i));
+        }
+        assert_eq!(buffer.line_count(), 3);
+        assert_eq!(buffer.tail(0, 10), ["line 2", "line 3", "line 4"]);
+        // scrolled up by one, two lines
+        assert_eq!(buffer.tail(1, 2), ["line 2", "line 3"]);
+        assert!(buffer.tail(5, 2).is_empty());
+    }
+
+    #[test]
+    fn test_writer_splits_an_event_into_lines_when_dropped() {
+        let buffer = LogBuffer::new(10);
+        {
+            let mut writer = buffer.make_writer();
+            write!(writer, "first\nsec").unwrap();
+            writeln!(writer, "ond").unwrap();
+            assert_eq!(buffer.line_count(), 0);
+        }
+        assert_eq!(buffer.tail(0, 10), ["first", "second"]);
+    }
+
+    #[test]
+    fn test_long_lines_are_cut() {
+        let buffer = LogBuffer::new(10);
+        buffer.push("é".repeat(MAX_LINE_CHARS + 5));
+        let line = buffer.tail(0, 1).remove(0);
+        assert_eq!(line.chars().count(), MAX_LINE_CHARS + 1);
+        assert!(line.ends_with('…'));
+    }
+}
@@ -1,8 +1,14 @@
 mod app;
+mod context;
 mod database;
+mod hyperlink;
+mod import;
+mod log_buffer;
 mod ui;
 mod markdown;
 mod model_select_modal;
+mod settings;
+mod theme;
 pub mod model;
 pub mod provider;
 
@@ -10,41 +16,257 @@ use anyhow::Result;
 use app::App;
 use clap::Parser;
 use database::Database;
-use tracing_subscriber::fmt::format::FmtSpan;
+use log_buffer::{LOG_BUFFER_CAPACITY, LogBuffer};
+use settings::Settings;
+use std::path::PathBuf;
+use tracing_subscriber::{
+    Layer, filter::LevelFilter, fmt::format::FmtSpan, layer::SubscriberExt,
+    util::SubscriberInitExt,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
     #[arg(short, long, help = "Database name (without .db extension)")]
     database: Option<String>,
+    #[arg(long, value_name = "PATH", help = "Directory to keep databases in, or a .db file to use directly. Defaults to $SHORE_DATA_DIR, then ~/.shore")]
+    db_path: Option<PathBuf>,
+    #[arg(long = "set", value_name = "KEY=VALUE", help = "Persist a setting, e.g. --set max_concurrent_inferences=2")]
+    set: Vec<String>,
+    #[arg(long, value_name = "PROVIDER/MODEL", help = "Add a model by hand, for providers without a models endpoint, e.g. --add-model MyGateway/llama-3-70b")]
+    add_model: Vec<String>,
+    #[arg(long, value_name = "PROVIDER/MODEL=TOKENS", help = "Drop the oldest messages that don't fit in a model's context window, e.g. --context-window OpenAI/gpt-4o=128000. 0 sends the whole conversation again")]
+    context_window: Vec<String>,
+    #[arg(long, value_name = "PROVIDER/MODEL=true|false", help = "Mark whether a model supports JSON mode, e.g. --json-mode OpenAI/gpt-4o=true. Chats in JSON mode only ask the models that do for a JSON object")]
+    json_mode: Vec<String>,
+    #[arg(long, value_name = "PROVIDER.KEY=VALUE", help = "Change a provider's setting, e.g. --provider-set OpenRouter.model_include=openai/*,anthropic/*. Settings: model_include and model_exclude (comma separated globs), system_role (system or developer), request_timeout_secs, extra_headers (a JSON object, e.g. {\"X-Title\": \"shore\"}), no_models_endpoint (true for providers that can't list their models). An empty value clears it")]
+    provider_set: Vec<String>,
+    #[arg(long, value_name = "PROMPT", help = "Send one prompt to the default profile's first model, print the response and exit. Use - to read it from stdin")]
+    prompt: Option<String>,
+    #[arg(long, requires = "prompt", help = "With --prompt, ask for a JSON object if the model supports JSON mode, like a chat in JSON mode")]
+    json: bool,
+    #[arg(long, value_name = "SEQUENCE", requires = "prompt", help = "With --prompt, stop the response before this sequence. Can be given more than once")]
+    stop: Vec<String>,
+    #[arg(long, value_name = "PATH", help = "Import the chats from a ChatGPT data export's conversations.json and exit")]
+    import_chatgpt: Option<PathBuf>,
+    #[arg(long, help = "Browse and search chats without sending, deleting or renaming anything")]
+    read_only: bool,
+    #[arg(long, help = "Don't keep recent logs in memory for the in-app log viewer (L)")]
+    no_log_viewer: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // only log if the SHORE_LOG env var is set
+    let cli = Cli::parse();
+
+    // recent logs are kept in memory so they can be viewed inside the app, unless turned off. The
+    // buffer is capped at LOG_BUFFER_CAPACITY lines of bounded length, so this costs a few MB at most
+    let log_buffer = (!cli.no_log_viewer).then(|| LogBuffer::new(LOG_BUFFER_CAPACITY));
+    let buffer_layer = log_buffer.clone().map(|log_buffer| {
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(log_buffer)
+            .with_filter(LevelFilter::INFO)
+    });
+    // only log to stderr if the SHORE_LOG env var is set
     // when doing this the user needs to make sure to pipe stderr
     // to a file and tail the file if they want to follow the logs
     // otherwise the TUI interface will be ruined by log output
-    if let Ok(_) = std::env::var("SHORE_LOG") {
-        tracing_subscriber::fmt()
+    let stderr_layer = std::env::var("SHORE_LOG").is_ok().then(|| {
+        tracing_subscriber::fmt::layer()
             .with_span_events(FmtSpan::ENTER | FmtSpan::CLOSE)
             .with_writer(std::io::stderr)
-            .init();
-    }
+            .with_filter(LevelFilter::INFO)
+    });
+    tracing_subscriber::registry()
+        .with(buffer_layer)
+        .with(stderr_layer)
+        .init();
 
-    let cli = Cli::parse();
     let db_name = cli.database.unwrap_or_else(|| "default".to_string());
 
-    let home_dir = dirs::home_dir()
-        .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
-    let shore_dir = home_dir.join(".shore");
-    std::fs::create_dir_all(&shore_dir)?;
+    let db_path = resolve_db_path(cli.db_path, &db_name)?;
+    let data_dir = db_path
+        .parent()
+        .map(|dir| dir.to_path_buf())
+        .unwrap_or_default();
+    std::fs::create_dir_all(&data_dir)?;
+    let database = Database::new(&db_path).await?;
+
+    for setting in &cli.set {
+        if cli.read_only {
+            anyhow::bail!("--set changes a setting, it can't be combined with --read-only");
+        }
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Settings must be given as KEY=VALUE, got {}", setting))?;
+        // validate before persisting so a typo doesn't get written to the db
+        Settings::default().apply(key, value)?;
+        database.set_setting(key, value).await?;
+    }
+
+    for model in &cli.add_model {
+        if cli.read_only {
+            anyhow::bail!("--add-model adds a model, it can't be combined with --read-only");
+        }
+        let (provider_name, model_name) = parse_provider_model(model)?;
+        database.add_manual_model(provider_name, model_name).await?;
+    }
+
+    for context_window in &cli.context_window {
+        if cli.read_only {
+            anyhow::bail!("--context-window changes a model, it can't be combined with --read-only");
+        }
+        let (model, tokens) = context_window
+            .rsplit_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Context windows must be given as PROVIDER/MODEL=TOKENS, got {}", context_window))?;
+        let tokens: i64 = tokens
+            .parse()
+            .ok()
+            .filter(|tokens| *tokens >= 0)
+            .ok_or_else(|| anyhow::anyhow!("Context window must be a number of tokens, got {}", tokens))?;
+        let (provider_name, model_name) = parse_provider_model(model)?;
+        database
+            .set_model_context_window(provider_name, model_name, (tokens > 0).then_some(tokens))
+            .await?;
+    }
 
-    let db_path = shore_dir.join(format!("{}.db", db_name));
-    let database = Database::new(db_path).await?;
+    for json_mode in &cli.json_mode {
+        if cli.read_only {
+            anyhow::bail!("--json-mode changes a model, it can't be combined with --read-only");
+        }
+        let (model, supported) = json_mode
+            .rsplit_once('=')
+            .ok_or_else(|| anyhow::anyhow!("JSON mode support must be given as PROVIDER/MODEL=true|false, got {}", json_mode))?;
+        let supported: bool = supported
+            .parse()
+            .map_err(|_| anyhow::anyhow!("JSON mode support must be true or false, got {}", supported))?;
+        let (provider_name, model_name) = parse_provider_model(model)?;
+        database
+            .set_model_supports_json_mode(provider_name, model_name, supported)
+            .await?;
+    }
 
-    let (mut app, user_event_rx) = App::new(database).await?;
+    for provider_setting in &cli.provider_set {
+        if cli.read_only {
+            anyhow::bail!("--provider-set changes a provider, it can't be combined with --read-only");
+        }
+        let (provider_name, key, value) = parse_provider_setting(provider_setting)?;
+        database.set_provider_setting(provider_name, key, value).await?;
+    }
+
+    if let Some(path) = cli.import_chatgpt {
+        if cli.read_only {
+            anyhow::bail!("--import-chatgpt adds chats, it can't be combined with --read-only");
+        }
+        let (chats, messages) = import::import_chatgpt_export(&database, &path).await?;
+        println!("Imported {} chats with {} messages", chats, messages);
+        return Ok(());
+    }
+
+    if let Some(prompt) = cli.prompt {
+        if cli.read_only {
+            anyhow::bail!("--prompt sends a message, it can't be combined with --read-only");
+        }
+        let prompt = if prompt == "-" {
+            std::io::read_to_string(std::io::stdin())?
+        } else {
+            prompt
+        };
+        let response = app::run_single_prompt(&database, prompt.trim(), cli.json, cli.stop).await?;
+        println!("{}", response);
+        return Ok(());
+    }
+
+    let (mut app, user_event_rx) = App::new(database, db_path, cli.read_only, log_buffer).await?;
     app.run(user_event_rx).await?;
 
     Ok(())
 }
+
+/// Split PROVIDER/MODEL. Model names can contain slashes themselves, so only the first one
+/// separates the provider
+fn parse_provider_model(value: &str) -> Result<(&str, &str)> {
+    value
+        .split_once('/')
+        .filter(|(provider_name, model_name)| !provider_name.is_empty() && !model_name.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Models must be given as PROVIDER/MODEL, got {}", value))
+}
+
+/// Split PROVIDER.KEY=VALUE. The value can hold anything, so the key ends at the first =
+fn parse_provider_setting(value: &str) -> Result<(&str, &str, &str)> {
+    value
+        .split_once('=')
+        .and_then(|(provider_key, setting)| {
+            let (provider_name, key) = provider_key.rsplit_once('.')?;
+            Some((provider_name, key, setting))
+        })
+        .filter(|(provider_name, key, _)| !provider_name.is_empty() && !key.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Provider settings must be given as PROVIDER.KEY=VALUE, got {}", value))
+}
+
+/// Where the database lives: --db-path, then SHORE_DATA_DIR, then ~/.shore. A location ending in
+/// .db (or an existing file) is used as the database itself, otherwise it's a directory holding
+/// <name>.db
+fn resolve_db_path(db_path: Option<PathBuf>, db_name: &str) -> Result<PathBuf> {
+    let location = match db_path {
+        Some(path) => path,
+        None => match std::env::var_os("SHORE_DATA_DIR").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => dirs::home_dir()
+                .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
+                .join(".shore"),
+        },
+    };
+
+    if location.extension().is_some_and(|ext| ext == "db") || location.is_file() {
+        Ok(location)
+    } else {
+        Ok(location.join(format!("{}.db", db_name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_provider_model() {
+        assert_eq!(parse_provider_model("OpenAI/gpt-5").unwrap(), ("OpenAI", "gpt-5"));
+        // only the first slash separates the provider, model names can have their own
+        assert_eq!(
+            parse_provider_model("OpenRouter/deepseek/deepseek-r1").unwrap(),
+            ("OpenRouter", "deepseek/deepseek-r1")
+        );
+
+        assert!(parse_provider_model("gpt-5").is_err());
+        assert!(parse_provider_model("/gpt-5").is_err());
+        assert!(parse_provider_model("OpenAI/").is_err());
+    }
+
+    #[test]
+    fn test_parse_provider_setting() {
+        assert_eq!(
+            parse_provider_setting("OpenRouter.model_include=openai/*,anthropic/*").unwrap(),
+            ("OpenRouter", "model_include", "openai/*,anthropic/*")
+        );
+        // an empty value clears the setting
+        assert_eq!(parse_provider_setting("OpenAI.model_exclude=").unwrap(), ("OpenAI", "model_exclude", ""));
+        assert_eq!(parse_provider_setting("OpenAI.model_exclude=a=b").unwrap(), ("OpenAI", "model_exclude", "a=b"));
+
+        assert!(parse_provider_setting("OpenAI.model_include").is_err());
+        assert!(parse_provider_setting("model_include=gpt-*").is_err());
+        assert!(parse_provider_setting(".model_include=gpt-*").is_err());
+        assert!(parse_provider_setting("OpenAI.=gpt-*").is_err());
+    }
+
+    #[test]
+    fn test_prompt_options_need_a_prompt() {
+        let cli = Cli::try_parse_from(["shore", "--prompt", "hi", "--json", "--stop", "END", "--stop", "###"]).unwrap();
+        assert!(cli.json);
+        assert_eq!(cli.stop, ["END", "###"]);
+
+        assert!(Cli::try_parse_from(["shore", "--json"]).is_err());
+        assert!(Cli::try_parse_from(["shore", "--stop", "END"]).is_err());
+    }
+}
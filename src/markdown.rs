@@ -1,33 +1,60 @@
 use ratatui::{
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span, Text},
 };
 
+use crate::theme::Theme;
+use std::collections::HashMap;
+
 /// Parses markdown text and converts it to styled ratatui Text
-pub fn parse_markdown(input: &str) -> Text<'static> {
+pub fn parse_markdown(input: &str, theme: &Theme) -> Text<'static> {
     let mut lines: Vec<Line<'static>> = Vec::new();
     let mut in_code_block = false;
+    let mut in_indented_code_block = false;
+    // an indented code block can't interrupt a paragraph, so it has to follow a blank line
+    let mut previous_blank = true;
+    // indented lines after a list item continue the item rather than starting code
+    let mut in_list = false;
     
     for raw_line in input.lines() {
         // Check if this line is a code block delimiter
         let trimmed = raw_line.trim_start();
-        if trimmed.starts_with("```") {
+        let is_blank = trimmed.is_empty();
+        let is_indented = raw_line.starts_with("    ") || raw_line.starts_with('\t');
+        if !in_code_block && in_indented_code_block && !is_indented && !is_blank {
+            in_indented_code_block = false;
+        } else if !in_code_block && !in_indented_code_block && is_indented && !is_blank {
+            in_indented_code_block = previous_blank && !in_list;
+        }
+
+        if in_indented_code_block {
+            lines.push(Line::from(Span::styled(
+                raw_line.to_string(),
+                Style::default().fg(theme.code),
+            )));
+        } else if trimmed.starts_with("```") {
             in_code_block = !in_code_block;
             // Style the delimiter line
             lines.push(Line::from(Span::styled(
                 raw_line.to_string(),
-                Style::default().fg(Color::Yellow),
+                Style::default().fg(theme.code),
             )));
         } else if in_code_block {
             // Inside a code block - don't parse markdown, just display as-is
             lines.push(Line::from(Span::styled(
                 raw_line.to_string(),
-                Style::default().fg(Color::Yellow),
+                Style::default().fg(theme.code),
             )));
         } else {
             // Outside code block - parse markdown normally
-            lines.push(parse_line(raw_line));
+            lines.push(parse_line(raw_line, theme));
+            if is_list_item(trimmed) {
+                in_list = true;
+            } else if !is_blank && !is_indented {
+                in_list = false;
+            }
         }
+        previous_blank = is_blank;
     }
     
     // If empty, add at least one empty line
@@ -38,19 +65,29 @@ pub fn parse_markdown(input: &str) -> Text<'static> {
     Text::from(lines)
 }
 
+/// Whether a (left trimmed) line starts a bulleted or numbered list item
+fn is_list_item(line: &str) -> bool {
+    if line.starts_with("- ") || line.starts_with("* ") || line.starts_with("+ ") {
+        return true;
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    digits > 0 && (line[digits..].starts_with(". ") || line[digits..].starts_with(") "))
+}
+
 /// Parses a single line of markdown
-fn parse_line(line: &str) -> Line<'static> {
+fn parse_line(line: &str, theme: &Theme) -> Line<'static> {
     let trimmed = line.trim_start();
     let indent_len = line.len() - trimmed.len();
     let indent = " ".repeat(indent_len);
     
     // Check for headings
-    if let Some(heading_line) = parse_heading(trimmed) {
+    if let Some(heading_line) = parse_heading(trimmed, theme) {
         return heading_line;
     }
     
-    // Parse inline styles (bold, italic, code, links)
-    let spans = parse_inline_styles(trimmed);
+    // Parse task list items, falling back to inline styles (bold, italic, code, links)
+    let spans = parse_task_item(trimmed, theme)
+        .unwrap_or_else(|| parse_inline_styles(trimmed, theme));
     
     // Add back indentation if needed
     if !indent.is_empty() {
@@ -63,7 +100,7 @@ fn parse_line(line: &str) -> Line<'static> {
 }
 
 /// Parses heading lines (# through ######)
-fn parse_heading(line: &str) -> Option<Line<'static>> {
+fn parse_heading(line: &str, theme: &Theme) -> Option<Line<'static>> {
     let mut level = 0;
     let chars: Vec<char> = line.chars().collect();
     
@@ -83,16 +120,16 @@ fn parse_heading(line: &str) -> Option<Line<'static>> {
         // Style based on heading level
         let style = match level {
             1 => Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.heading)
                 .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
             2 => Style::default()
-                .fg(Color::Cyan)
+                .fg(theme.heading)
                 .add_modifier(Modifier::BOLD),
             3 => Style::default()
-                .fg(Color::Blue)
+                .fg(theme.subheading)
                 .add_modifier(Modifier::BOLD),
             _ => Style::default()
-                .fg(Color::Blue),
+                .fg(theme.subheading),
         };
         
         Some(Line::from(Span::styled(content.to_string(), style)))
@@ -101,124 +138,233 @@ fn parse_heading(line: &str) -> Option<Line<'static>> {
     }
 }
 
-/// Parses inline markdown styles: **bold**, *italic*, `code`, [text](url)
-fn parse_inline_styles(text: &str) -> Vec<Span<'static>> {
+/// Parses task list items (- [ ] todo, - [x] done) into a checkbox glyph followed by the item text.
+/// Checked items are dimmed and struck through
+fn parse_task_item(line: &str, theme: &Theme) -> Option<Vec<Span<'static>>> {
+    let rest = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))?;
+    let (checked, item_text) = if let Some(item_text) = rest.strip_prefix("[ ]") {
+        (false, item_text)
+    } else if let Some(item_text) = rest
+        .strip_prefix("[x]")
+        .or_else(|| rest.strip_prefix("[X]"))
+    {
+        (true, item_text)
+    } else {
+        return None;
+    };
+
+    // the checkbox has to stand on its own, `- [x]y` is just text
+    if !item_text.is_empty() && !item_text.starts_with(' ') {
+        return None;
+    }
+
+    let mut spans = if checked {
+        vec![Span::styled("☑", Style::default().fg(theme.success))]
+    } else {
+        vec![Span::raw("☐")]
+    };
+    spans.push(Span::raw(" "));
+    for span in parse_inline_styles(item_text.trim_start(), theme) {
+        if checked {
+            spans.push(span.patch_style(
+                Style::default()
+                    .fg(theme.muted)
+                    .add_modifier(Modifier::CROSSED_OUT),
+            ));
+        } else {
+            spans.push(span);
+        }
+    }
+    Some(spans)
+}
+
+/// Parses inline markdown styles: **bold**, __bold__, *italic*, _italic_, ~~strikethrough~~, `code`, [text](url)
+/// Emphasis can be nested, e.g. *italic with **bold** inside*, the inner text gets both modifiers
+fn parse_inline_styles(text: &str, theme: &Theme) -> Vec<Span<'static>> {
+    let chars: Vec<char> = text.chars().collect();
     let mut spans = Vec::new();
+    push_inline_spans(&chars, Style::default(), theme, &mut spans);
+
+    // If no spans were created, return at least one empty span
+    if spans.is_empty() {
+        spans.push(Span::raw(""));
+    }
+
+    spans
+}
+
+/// Pushes the spans for `chars` with `style` as the base, emphasized text is parsed again with the
+/// emphasis' modifier added on top so nested styles compose
+fn push_inline_spans(chars: &[char], style: Style, theme: &Theme, spans: &mut Vec<Span<'static>>) {
     let mut current = String::new();
-    let chars: Vec<char> = text.chars().collect();
     let mut i = 0;
-    
+    let mut closers = HashMap::new();
+
+    let flush = |current: &mut String, spans: &mut Vec<Span<'static>>| {
+        if !current.is_empty() {
+            spans.push(Span::styled(std::mem::take(current), style));
+        }
+    };
+
     while i < chars.len() {
         // Check for bold (**text**)
-        if i + 1 < chars.len() && chars[i] == '*' && chars[i + 1] == '*' {
-            if !current.is_empty() {
-                spans.push(Span::raw(current.clone()));
-                current.clear();
-            }
-            
-            // Find closing **
-            if let Some(end_pos) = find_closing_delimiter(&chars, i + 2, "**") {
-                let bold_text: String = chars[i + 2..end_pos].iter().collect();
-                spans.push(Span::styled(
-                    bold_text,
-                    Style::default().add_modifier(Modifier::BOLD),
-                ));
-                i = end_pos + 2;
-                continue;
-            }
+        if chars[i..].starts_with(&['*', '*'])
+            && let Some(end_pos) = find_closing_emphasis(chars, i + 2, "**", &mut closers)
+        {
+            flush(&mut current, spans);
+            let bold_style = style.add_modifier(Modifier::BOLD);
+            push_inline_spans(&chars[i + 2..end_pos], bold_style, theme, spans);
+            i = end_pos + 2;
+            continue;
         }
-        
+
         // Check for italic (*text*)
-        if chars[i] == '*' {
-            if !current.is_empty() {
-                spans.push(Span::raw(current.clone()));
-                current.clear();
-            }
-            
-            // Find closing *
-            if let Some(end_pos) = find_closing_single(&chars, i + 1, '*') {
-                let italic_text: String = chars[i + 1..end_pos].iter().collect();
-                spans.push(Span::styled(
-                    italic_text,
-                    Style::default().add_modifier(Modifier::ITALIC),
-                ));
-                i = end_pos + 1;
-                continue;
-            }
+        if chars[i] == '*'
+            && let Some(end_pos) = find_closing_emphasis(chars, i + 1, "*", &mut closers)
+        {
+            flush(&mut current, spans);
+            let italic_style = style.add_modifier(Modifier::ITALIC);
+            push_inline_spans(&chars[i + 1..end_pos], italic_style, theme, spans);
+            i = end_pos + 1;
+            continue;
         }
-        
-        // Check for inline code (`code`)
-        if chars[i] == '`' {
-            if !current.is_empty() {
-                spans.push(Span::raw(current.clone()));
-                current.clear();
-            }
-            
-            // Find closing `
-            if let Some(end_pos) = find_closing_single(&chars, i + 1, '`') {
-                let code_text: String = chars[i + 1..end_pos].iter().collect();
-                spans.push(Span::styled(
-                    code_text,
-                    Style::default().fg(Color::Yellow),
-                ));
-                i = end_pos + 1;
+
+        // Check for strikethrough (~~text~~), a lone ~ like in ~/.config is left as is
+        if chars[i..].starts_with(&['~', '~'])
+            && let Some(end_pos) = find_closing_emphasis(chars, i + 2, "~~", &mut closers)
+            && end_pos > i + 2
+        {
+            flush(&mut current, spans);
+            // the struck text can carry its own styles, e.g. ~~**old**~~
+            let struck_style = style.add_modifier(Modifier::CROSSED_OUT);
+            push_inline_spans(&chars[i + 2..end_pos], struck_style, theme, spans);
+            i = end_pos + 2;
+            continue;
+        }
+
+        // Check for underscore bold (__text__) and italic (_text_)
+        // these only count at word boundaries so snake_case identifiers are left alone
+        if chars[i] == '_' && (i == 0 || !chars[i - 1].is_alphanumeric()) {
+            let delim_len = if i + 1 < chars.len() && chars[i + 1] == '_' { 2 } else { 1 };
+            if let Some(end_pos) = find_closing_underscore(chars, i + delim_len, delim_len) {
+                flush(&mut current, spans);
+                let modifier = if delim_len == 2 { Modifier::BOLD } else { Modifier::ITALIC };
+                let emphasized = &chars[i + delim_len..end_pos];
+                push_inline_spans(emphasized, style.add_modifier(modifier), theme, spans);
+                i = end_pos + delim_len;
                 continue;
             }
         }
-        
+
+        // Check for inline code (`code`), its content is shown as written
+        if chars[i] == '`'
+            && let Some(end_pos) = find_closing_single(chars, i + 1, '`')
+        {
+            flush(&mut current, spans);
+            let code_text: String = chars[i + 1..end_pos].iter().collect();
+            spans.push(Span::styled(code_text, style.fg(theme.code)));
+            i = end_pos + 1;
+            continue;
+        }
+
         // Check for links ([text](url))
-        if chars[i] == '[' {
-            if let Some((link_text, url, end_pos)) = parse_link(&chars, i) {
-                if !current.is_empty() {
-                    spans.push(Span::raw(current.clone()));
-                    current.clear();
-                }
-                
-                // Display as "text (url)" in cyan
-                let display = format!("{} ({})", link_text, url);
-                spans.push(Span::styled(
-                    display,
-                    Style::default().fg(Color::Cyan).add_modifier(Modifier::UNDERLINED),
-                ));
-                i = end_pos;
-                continue;
-            }
+        if chars[i] == '['
+            && let Some((link_text, url, end_pos)) = parse_link(chars, i)
+        {
+            flush(&mut current, spans);
+
+            // Display as "text (url)" in the link color, the text can be emphasized itself
+            let link_style = style.fg(theme.link).add_modifier(Modifier::UNDERLINED);
+            let link_chars: Vec<char> = link_text.chars().collect();
+            push_inline_spans(&link_chars, link_style, theme, spans);
+            spans.push(Span::styled(format!(" ({})", url), link_style));
+            i = end_pos;
+            continue;
         }
-        
+
+        // Check for bare urls (http://... or https://...) that aren't in link syntax
+        if chars[i] == 'h'
+            && (i == 0 || !chars[i - 1].is_alphanumeric())
+            && let Some(end_pos) = find_bare_url_end(chars, i)
+        {
+            flush(&mut current, spans);
+            let url: String = chars[i..end_pos].iter().collect();
+            spans.push(Span::styled(
+                url,
+                style.fg(theme.link).add_modifier(Modifier::UNDERLINED),
+            ));
+            i = end_pos;
+            continue;
+        }
+
         // Regular character
         current.push(chars[i]);
         i += 1;
     }
-    
+
     // Add any remaining text
-    if !current.is_empty() {
-        spans.push(Span::raw(current));
-    }
-    
-    // If no spans were created, return at least one empty span
-    if spans.is_empty() {
-        spans.push(Span::raw(""));
+    flush(&mut current, spans);
+}
+
+/// Closing positions already found in one slice, by where the search started and its delimiter
+type ClosingEmphasisCache = HashMap<(usize, &'static str), Option<usize>>;
+
+/// Finds the closing delimiter for *, ** or ~~ emphasis. Code spans and nested star emphasis in
+/// between are skipped, so `*a **b** c*` closes at the last star rather than the first one of **.
+/// Unclosed * and ** make the nested searches repeat each other exponentially often, `closers`
+/// remembers their results so each one only runs once
+fn find_closing_emphasis(
+    chars: &[char],
+    start: usize,
+    delimiter: &'static str,
+    closers: &mut ClosingEmphasisCache,
+) -> Option<usize> {
+    if let Some(&closing) = closers.get(&(start, delimiter)) {
+        return closing;
     }
-    
-    spans
+    let closing = search_closing_emphasis(chars, start, delimiter, closers);
+    closers.insert((start, delimiter), closing);
+    closing
 }
 
-/// Finds the closing delimiter for bold (**) or other multi-character delimiters
-fn find_closing_delimiter(chars: &[char], start: usize, delimiter: &str) -> Option<usize> {
+fn search_closing_emphasis(
+    chars: &[char],
+    start: usize,
+    delimiter: &'static str,
+    closers: &mut ClosingEmphasisCache,
+) -> Option<usize> {
     let delim_chars: Vec<char> = delimiter.chars().collect();
-    let delim_len = delim_chars.len();
-    
+
     let mut i = start;
-    while i + delim_len <= chars.len() {
-        let mut matches = true;
-        for (j, &delim_char) in delim_chars.iter().enumerate() {
-            if chars[i + j] != delim_char {
-                matches = false;
-                break;
-            }
+    while i < chars.len() {
+        if chars[i] == '`'
+            && let Some(end_pos) = find_closing_single(chars, i + 1, '`')
+        {
+            i = end_pos + 1;
+            continue;
         }
-        if matches {
-            return Some(i);
+
+        match delimiter {
+            "*" if chars[i] == '*' => {
+                if chars.get(i + 1) == Some(&'*')
+                    && let Some(end_pos) = find_closing_emphasis(chars, i + 2, "**", closers)
+                {
+                    i = end_pos + 2;
+                    continue;
+                }
+                return Some(i);
+            }
+            "**" if chars[i] == '*' && chars.get(i + 1) != Some(&'*') => {
+                if let Some(end_pos) = find_closing_emphasis(chars, i + 1, "*", closers) {
+                    i = end_pos + 1;
+                    continue;
+                }
+            }
+            _ if chars[i..].starts_with(&delim_chars) => return Some(i),
+            _ => {}
         }
         i += 1;
     }
@@ -227,14 +373,68 @@ fn find_closing_delimiter(chars: &[char], start: usize, delimiter: &str) -> Opti
 
 /// Finds the closing single character delimiter
 fn find_closing_single(chars: &[char], start: usize, delimiter: char) -> Option<usize> {
-    for i in start..chars.len() {
-        if chars[i] == delimiter {
+    chars
+        .iter()
+        .skip(start)
+        .position(|&ch| ch == delimiter)
+        .map(|offset| start + offset)
+}
+
+/// Finds the closing _ or __ for underscore emphasis. The emphasized text can't start or end
+/// with whitespace, and the closing delimiter can't touch another underscore or be followed by a
+/// word character, so a nested __bold__ doesn't close an _italic_ around it
+fn find_closing_underscore(chars: &[char], start: usize, delim_len: usize) -> Option<usize> {
+    if chars.get(start).is_none_or(|ch| ch.is_whitespace()) {
+        return None;
+    }
+
+    let mut i = start + 1;
+    while i + delim_len <= chars.len() {
+        let is_delimiter = chars[i..i + delim_len].iter().all(|&ch| ch == '_');
+        let closes_word = chars
+            .get(i + delim_len)
+            .is_none_or(|ch| !ch.is_alphanumeric() && *ch != '_');
+        let after_text = !chars[i - 1].is_whitespace() && chars[i - 1] != '_';
+        if is_delimiter && closes_word && after_text {
             return Some(i);
         }
+        i += 1;
     }
     None
 }
 
+/// Finds the end of a bare url starting at `start`. The url runs until whitespace, minus any trailing
+/// punctuation that most likely belongs to the sentence, e.g. the period in "see https://x.com."
+fn find_bare_url_end(chars: &[char], start: usize) -> Option<usize> {
+    let rest: String = chars[start..chars.len().min(start + 8)].iter().collect();
+    let scheme_len = if rest.starts_with("https://") {
+        8
+    } else if rest.starts_with("http://") {
+        7
+    } else {
+        return None;
+    };
+
+    let mut end = chars[start..]
+        .iter()
+        .position(|ch| ch.is_whitespace())
+        .map_or(chars.len(), |offset| start + offset);
+    while end > start + scheme_len {
+        let last = chars[end - 1];
+        // a closing paren is kept when it balances one inside the url, like in wikipedia links
+        let unbalanced_paren = last == ')'
+            && chars[start..end].iter().filter(|&&ch| ch == ')').count()
+                > chars[start..end].iter().filter(|&&ch| ch == '(').count();
+        if matches!(last, '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"') || unbalanced_paren {
+            end -= 1;
+        } else {
+            break;
+        }
+    }
+
+    (end > start + scheme_len).then_some(end)
+}
+
 /// Parses a markdown link: [text](url)
 fn parse_link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
     // Find closing ]
@@ -257,22 +457,23 @@ fn parse_link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ratatui::style::Color;
 
     #[test]
     fn test_parse_heading() {
-        let text = parse_markdown("# Heading 1\n## Heading 2\n### Heading 3");
+        let text = parse_markdown("# Heading 1\n## Heading 2\n### Heading 3", &Theme::default());
         assert_eq!(text.lines.len(), 3);
     }
 
     #[test]
     fn test_parse_bold() {
-        let text = parse_markdown("This is **bold** text");
+        let text = parse_markdown("This is **bold** text", &Theme::default());
         assert_eq!(text.lines.len(), 1);
     }
 
     #[test]
     fn test_parse_bold_with_period() {
-        let text = parse_markdown("This is **bold**. Testing 123");
+        let text = parse_markdown("This is **bold**. Testing 123", &Theme::default());
         assert_eq!(text.lines.len(), 1);
         
         // Verify no extra spaces are added between bold text and period
@@ -283,7 +484,7 @@ mod tests {
 
     #[test]
     fn test_parse_code_with_comma() {
-        let text = parse_markdown("This is `code`, with a comma");
+        let text = parse_markdown("This is `code`, with a comma", &Theme::default());
         assert_eq!(text.lines.len(), 1);
         
         // Verify no extra spaces are added between code and comma
@@ -294,7 +495,7 @@ mod tests {
 
     #[test]
     fn test_parse_bold_with_colon() {
-        let text = parse_markdown("This is **bold**: and more");
+        let text = parse_markdown("This is **bold**: and more", &Theme::default());
         assert_eq!(text.lines.len(), 1);
         
         // Verify no extra spaces are added between bold and colon
@@ -306,7 +507,10 @@ mod tests {
     #[test]
     fn test_parse_multiple_formats_no_spaces() {
         // Test formatting with no spaces between formatted text and punctuation
-        let text = parse_markdown("Use `function()`, then **bold**, and *italic*:");
+        let text = parse_markdown(
+            "Use `function()`, then **bold**, and *italic*:",
+            &Theme::default(),
+        );
         assert_eq!(text.lines.len(), 1);
         
         let line = &text.lines[0];
@@ -316,33 +520,233 @@ mod tests {
 
     #[test]
     fn test_parse_italic() {
-        let text = parse_markdown("This is *italic* text");
+        let text = parse_markdown("This is *italic* text", &Theme::default());
         assert_eq!(text.lines.len(), 1);
     }
 
+    #[test]
+    fn test_parse_snake_case_untouched() {
+        let text = parse_markdown(
+            "Call snake_case_words and MAX_RETRY_COUNT here",
+            &Theme::default(),
+        );
+        let line = &text.lines[0];
+        let rendered: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "Call snake_case_words and MAX_RETRY_COUNT here");
+        assert_eq!(line.spans.len(), 1);
+        assert!(line.spans[0].content.contains("snake_case_words"));
+        assert_eq!(line.spans[0].style, Style::default());
+    }
+
+    #[test]
+    fn test_parse_underscore_italic() {
+        let text = parse_markdown("This is _italic_ text", &Theme::default());
+        let line = &text.lines[0];
+        let italic = line.spans.iter().find(|s| s.content == "italic").unwrap();
+        assert!(italic.style.add_modifier.contains(Modifier::ITALIC));
+        let rendered: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "This is italic text");
+    }
+
+    #[test]
+    fn test_parse_underscore_bold() {
+        let text = parse_markdown("This is __bold__, really", &Theme::default());
+        let line = &text.lines[0];
+        let bold = line.spans.iter().find(|s| s.content == "bold").unwrap();
+        assert!(bold.style.add_modifier.contains(Modifier::BOLD));
+        let rendered: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "This is bold, really");
+    }
+
+    #[test]
+    fn test_parse_task_list_items() {
+        let text = parse_markdown("- [ ] todo\n- [x] done", &Theme::default());
+        assert_eq!(text.lines.len(), 2);
+
+        let unchecked: String = text.lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(unchecked, "☐ todo");
+
+        let checked = &text.lines[1];
+        let rendered: String = checked.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "☑ done");
+        let done = checked.spans.iter().find(|s| s.content == "done").unwrap();
+        assert!(done.style.add_modifier.contains(Modifier::CROSSED_OUT));
+    }
+
+    #[test]
+    fn test_parse_invalid_task_is_list_item() {
+        let text = parse_markdown("- [y] not a task", &Theme::default());
+        let rendered: String = text.lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "- [y] not a task");
+    }
+
+    #[test]
+    fn test_parse_strikethrough() {
+        let text = parse_markdown("~~old~~ **new** in ~/projects", &Theme::default());
+        let line = &text.lines[0];
+        let rendered: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "old new in ~/projects");
+
+        let old = line.spans.iter().find(|s| s.content == "old").unwrap();
+        assert!(old.style.add_modifier.contains(Modifier::CROSSED_OUT));
+        let new = line.spans.iter().find(|s| s.content == "new").unwrap();
+        assert!(new.style.add_modifier.contains(Modifier::BOLD));
+        assert!(!new.style.add_modifier.contains(Modifier::CROSSED_OUT));
+    }
+
     #[test]
     fn test_parse_code() {
-        let text = parse_markdown("This is `code` text");
+        let text = parse_markdown("This is `code` text", &Theme::default());
         assert_eq!(text.lines.len(), 1);
     }
 
     #[test]
     fn test_parse_link() {
-        let text = parse_markdown("Check [this link](https://example.com) out");
+        let text = parse_markdown("Check [this link](https://example.com) out", &Theme::default());
         assert_eq!(text.lines.len(), 1);
     }
 
+    #[test]
+    fn test_parse_bare_url() {
+        let text = parse_markdown("see https://x.com.", &Theme::default());
+        let line = &text.lines[0];
+        let rendered: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "see https://x.com.");
+
+        let url = line.spans.iter().find(|s| s.content == "https://x.com").unwrap();
+        assert_eq!(url.style.fg, Some(Color::Cyan));
+        assert!(url.style.add_modifier.contains(Modifier::UNDERLINED));
+        let period = line.spans.last().unwrap();
+        assert_eq!(period.content, ".");
+        assert_eq!(period.style.fg, None);
+
+        // balanced parens stay part of the url, an enclosing one doesn't
+        let text = parse_markdown(
+            "(https://en.wikipedia.org/wiki/Rust_(programming_language))",
+            &Theme::default(),
+        );
+        let spans = &text.lines[0].spans;
+        assert_eq!(spans[1].content, "https://en.wikipedia.org/wiki/Rust_(programming_language)");
+        assert_eq!(spans[2].content, ")");
+    }
+
+    #[test]
+    fn test_parse_bold_inside_italic() {
+        let text = parse_markdown("*italic with **bold** inside*", &Theme::default());
+        let line = &text.lines[0];
+        let rendered: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "italic with bold inside");
+
+        let bold = line.spans.iter().find(|s| s.content == "bold").unwrap();
+        assert!(bold.style.add_modifier.contains(Modifier::BOLD | Modifier::ITALIC));
+        let outer = line.spans.iter().find(|s| s.content == "italic with ").unwrap();
+        assert!(outer.style.add_modifier.contains(Modifier::ITALIC));
+        assert!(!outer.style.add_modifier.contains(Modifier::BOLD));
+
+        // the same goes for underscores and for both at once
+        let text = parse_markdown(
+            "_italic with __bold__ inside_ and ***both***",
+            &Theme::default(),
+        );
+        let line = &text.lines[0];
+        let rendered: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "italic with bold inside and both");
+        for content in ["bold", "both"] {
+            let span = line.spans.iter().find(|s| s.content == content).unwrap();
+            assert!(span.style.add_modifier.contains(Modifier::BOLD | Modifier::ITALIC));
+        }
+    }
+
+    #[test]
+    fn test_parse_code_inside_bold_is_not_parsed() {
+        let theme = Theme::default();
+        let text = parse_markdown("**run `a *b* c` now**", &theme);
+        let line = &text.lines[0];
+        let rendered: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "run a *b* c now");
+
+        let code = line.spans.iter().find(|s| s.content == "a *b* c").unwrap();
+        assert_eq!(code.style.fg, Some(theme.code));
+        assert!(code.style.add_modifier.contains(Modifier::BOLD));
+        assert!(!code.style.add_modifier.contains(Modifier::ITALIC));
+
+        // a delimiter inside code doesn't close the emphasis around it
+        let text = parse_markdown("**see `x**y` here**", &theme);
+        let rendered: String = text.lines[0].spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "see x**y here");
+    }
+
+    #[test]
+    fn test_parse_link_text_with_emphasis() {
+        let theme = Theme::default();
+        let text = parse_markdown("Read [the *new* docs](https://example.com) first", &theme);
+        let line = &text.lines[0];
+        let rendered: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, "Read the new docs (https://example.com) first");
+
+        let new = line.spans.iter().find(|s| s.content == "new").unwrap();
+        assert_eq!(new.style.fg, Some(theme.link));
+        assert!(new.style.add_modifier.contains(Modifier::UNDERLINED | Modifier::ITALIC));
+        let url = line.spans.iter().find(|s| s.content == " (https://example.com)").unwrap();
+        assert_eq!(url.style.fg, Some(theme.link));
+        assert!(!url.style.add_modifier.contains(Modifier::ITALIC));
+    }
+
     // TODO this one needs fixing
     #[test]
     #[ignore]
     fn test_code_block_no_parsing() {
         // Markdown inside a code block should not be parsed
         let input = "Normal text\n```markdown\n# This is not a heading\n**not bold**\n```\nBack to normal";
-        let text = parse_markdown(input);
+        let text = parse_markdown(input, &Theme::default());
         assert_eq!(text.lines.len(), 5);
         
         // All lines inside the code block should be styled in yellow (code style)
         // and not have heading or bold styling applied
     }
-}
 
+    #[test]
+    fn test_parse_indented_code_block() {
+        let theme = Theme::default();
+        let text = parse_markdown(
+            "Example:\n\n    let x = **not bold**;\n\n    x + 1\nAfter",
+            &theme,
+        );
+        assert_eq!(text.lines.len(), 6);
+        for line in [&text.lines[2], &text.lines[4]] {
+            assert_eq!(line.spans.len(), 1);
+            assert_eq!(line.spans[0].style.fg, Some(theme.code));
+        }
+        assert_eq!(text.lines[2].spans[0].content, "    let x = **not bold**;");
+        // the block ends at the first line that isn't indented
+        assert_ne!(text.lines[5].spans[0].style.fg, Some(theme.code));
+    }
+
+    #[test]
+    fn test_parse_indented_list_items_are_not_code() {
+        let theme = Theme::default();
+        let text = parse_markdown(
+            "- outer\n  - inner\n\n    - nested deeper\n\n    continued item\n\
+             paragraph\n    not code",
+            &theme,
+        );
+        for line in &text.lines {
+            assert!(line.spans.iter().all(|span| span.style.fg != Some(theme.code)));
+        }
+    }
+
+    #[test]
+    fn test_pathological_emphasis_parses_quickly() {
+        // unclosed * and ** used to send the closing search into exponential backtracking
+        let input = format!("a{}", "* **".repeat(1000));
+        let started = std::time::Instant::now();
+        let text = parse_markdown(&input, &Theme::default());
+        let elapsed = started.elapsed();
+        assert!(elapsed < std::time::Duration::from_secs(2), "took {:?}", elapsed);
+        assert_eq!(text.lines.len(), 1);
+        let rendered: String =
+            text.lines[0].spans.iter().map(|span| span.content.as_ref()).collect();
+        // delimiters may pair up, but none of the text is lost
+        assert_eq!(rendered.chars().filter(|&ch| ch == ' ').count(), 1000);
+    }
+}
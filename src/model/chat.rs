@@ -1,6 +1,7 @@
 use chrono;
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, encode::IsNull, error::BoxDynError, Database, Decode, Encode, Sqlite, Type};
+use std::collections::HashMap;
 use std::fmt;
 
 use crate::model::model::Model;
@@ -62,8 +63,73 @@ pub struct Chat {
     pub id: i64,
     pub dt: i64, // this is creation dt
     pub title: Option<String>,
+    pub archived: bool,
+    pub json_mode: bool, // ask models that support it to respond with a JSON object
+    pub stop_sequences: Option<String>, // JSON array of strings, stored as a string like tool calls
+    pub system_prompt: Option<String>, // None uses the default system prompt
 }
 
+impl Chat {
+    /// The chat's stop sequences, empty when there are none
+    pub fn stop_sequence_list(&self) -> Vec<String> {
+        self.stop_sequences
+            .as_deref()
+            .and_then(|stop_sequences| serde_json::from_str(stop_sequences).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// How the chat history list is ordered, cycled with O
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChatSort {
+    #[default]
+    Date, // newest first
+    Title, // alphabetical, untitled chats last
+    MessageCount, // most messages first
+}
+
+impl ChatSort {
+    pub fn next(self) -> Self {
+        match self {
+            ChatSort::Date => ChatSort::Title,
+            ChatSort::Title => ChatSort::MessageCount,
+            ChatSort::MessageCount => ChatSort::Date,
+        }
+    }
+
+    /// How the sort is stored in the chat_sort setting
+    pub fn as_setting(self) -> &'static str {
+        match self {
+            ChatSort::Date => "date",
+            ChatSort::Title => "title",
+            ChatSort::MessageCount => "messages",
+        }
+    }
+
+    pub fn from_setting(value: &str) -> Result<Self, String> {
+        match value {
+            "date" => Ok(ChatSort::Date),
+            "title" => Ok(ChatSort::Title),
+            "messages" => Ok(ChatSort::MessageCount),
+            _ => Err(format!("Invalid chat sort: {}, expected date, title or messages", value)),
+        }
+    }
+}
+
+impl fmt::Display for ChatSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChatSort::Date => write!(f, "date"),
+            ChatSort::Title => write!(f, "title"),
+            ChatSort::MessageCount => write!(f, "message count"),
+        }
+    }
+}
+
+/// The name that marks a user message as an aside: context the models see with the next prompt
+/// without it being a prompt of its own
+pub const ASIDE_NAME: &str = "aside";
+
 #[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
 pub struct ChatMessage {
     pub id: i64,
@@ -73,11 +139,19 @@ pub struct ChatMessage {
     pub model_id: Option<i64>, // only populated for assistant messages
     pub chat_role: ChatRole,
     pub content: Option<String>,
-    pub name: Option<String>, // this is the name of the tool that was used to generate the content
+    pub name: Option<String>, // this is the name of the tool that was used to generate the content, or ASIDE_NAME
     pub reasoning_content: Option<String>,
     pub tool_calls: Option<String>,
     pub tool_call_id: Option<String>,
     pub error: Option<String>,
+    pub reasoning_tokens: Option<i64>, // from the provider's usage, or estimated from the reasoning text when it doesn't report them
+    #[serde(default)] // exports from before this was tracked only had estimates, which the import can't tell apart
+    pub reasoning_tokens_estimated: bool,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    pub incomplete: bool, // the response was never finished, e.g. the app exited mid-request
+    pub finish_reason: Option<String>, // as reported by the provider, "length" means it hit the token limit
+    pub answered_by_model_id: Option<i64>, // a fallback model that answered in place of model_id
 }
 
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
@@ -87,6 +161,61 @@ pub struct ChatProfile {
     pub tool_ids: Vec<i64>,
 }
 
+/// A chat written out as JSON by the export, with everything needed to read it back in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatExport {
+    pub chat: Chat,
+    pub models: Vec<Model>, // the chat's models in display order
+    pub provider_names: HashMap<i64, String>, // provider_id -> name, for the models above
+    pub messages: Vec<ChatMessage>, // every model's messages, prompts appear once
+    pub exported_dt: i64,
+}
+
+/// A saved system prompt from the prompt library
+#[derive(Debug, Clone, FromRow)]
+pub struct LibraryPrompt {
+    pub id: i64,
+    pub name: String,
+    pub content: String,
+    pub created_dt: i64,
+}
+
+/// A bookmarked message along with the chat it belongs to, for listing bookmarks across chats
+#[derive(Debug, Clone, FromRow)]
+pub struct Bookmark {
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub chat_title: Option<String>,
+    pub chat_archived: bool,
+    pub model_id: Option<i64>, // None for the user's own messages
+    pub content: Option<String>,
+    pub error: Option<String>,
+    pub created_dt: i64,
+}
+
+/// One inference request as recorded in the request log
+#[derive(Debug, Clone, FromRow)]
+pub struct RequestLogEntry {
+    pub id: i64,
+    pub dt: i64, // when the request went out, in milliseconds
+    pub chat_id: Option<i64>, // None once the chat is deleted
+    pub model_id: i64,
+    pub model: String, // the model's name when the request was made
+    pub message_count: i64, // messages sent, after any were dropped to fit the context window
+    pub latency_ms: i64,
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// A saved model selection that can be applied to a chat
+#[derive(Debug, Clone)]
+pub struct NamedProfile {
+    pub id: i64,
+    pub name: String,
+    pub model_ids: Vec<i64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ChatWithModels {
     pub chat: Chat,
@@ -108,6 +237,20 @@ impl ChatMessage {
             tool_calls: None,
             tool_call_id: None,
             error: None,
+            reasoning_tokens: None,
+            reasoning_tokens_estimated: false,
+            prompt_tokens: None,
+            completion_tokens: None,
+            incomplete: false,
+            finish_reason: None,
+            answered_by_model_id: None,
+        }
+    }
+
+    pub fn new_aside_message(chat_id: i64, content: String) -> Self {
+        Self {
+            name: Some(ASIDE_NAME.to_string()),
+            ..Self::new_user_message(chat_id, content)
         }
     }
 
@@ -125,6 +268,13 @@ impl ChatMessage {
             tool_calls: None,
             tool_call_id: None,
             error: None,
+            reasoning_tokens: None,
+            reasoning_tokens_estimated: false,
+            prompt_tokens: None,
+            completion_tokens: None,
+            incomplete: false,
+            finish_reason: None,
+            answered_by_model_id: None,
         }
     }
 
@@ -142,6 +292,41 @@ impl ChatMessage {
             tool_calls: None,
             tool_call_id: None,
             error: Some(error),
+            reasoning_tokens: None,
+            reasoning_tokens_estimated: false,
+            prompt_tokens: None,
+            completion_tokens: None,
+            incomplete: false,
+            finish_reason: None,
+            answered_by_model_id: None,
+        }
+    }
+
+    pub fn is_aside(&self) -> bool {
+        self.chat_role == ChatRole::User && self.name.as_deref() == Some(ASIDE_NAME)
+    }
+
+    /// The response stopped because it ran into the token limit, so it can be continued
+    pub fn was_cut_off(&self) -> bool {
+        self.error.is_none() && self.finish_reason.as_deref() == Some("length")
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_was_cut_off() {
+        let mut message = ChatMessage::new_assistant_message(1, 1, "partial".to_string(), 0);
+        assert!(!message.was_cut_off());
+        for (finish_reason, cut_off) in [("length", true), ("stop", false), ("content_filter", false), ("tool_calls", false)] {
+            message.finish_reason = Some(finish_reason.to_string());
+            assert_eq!(message.was_cut_off(), cut_off, "{}", finish_reason);
         }
+
+        // a failed response can be retried, not continued
+        let mut failed = ChatMessage::new_assistant_message_with_error(1, 1, "timeout".to_string(), 0);
+        failed.finish_reason = Some("length".to_string());
+        assert!(!failed.was_cut_off());
     }
-}
\ No newline at end of file
+}
@@ -1,3 +1,4 @@
 pub mod chat;
 pub mod tool;
+#[allow(clippy::module_inception)]
 pub mod model;
\ No newline at end of file
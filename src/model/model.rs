@@ -1,3 +1,4 @@
+use crate::{context::truncate_to_context_window, model::chat::ChatMessage};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
@@ -10,4 +11,91 @@ pub struct Model {
     pub disabled: bool,
     pub deprecated: bool,
     pub created_dt: i64,
-}
\ No newline at end of file
+    pub context_window: Option<i64>, // in tokens, None means no truncation
+    pub supports_json_mode: bool,
+    #[serde(default)] // exports from before aliases existed don't have one
+    pub alias: Option<String>, // shown instead of the model name, which is still what's sent
+    #[sqlx(flatten)]
+    pub metadata: ModelMetadata,
+}
+
+impl Model {
+    /// The alias if one is set, otherwise the model name
+    pub fn display_name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.model)
+    }
+
+    /// Drop the oldest messages that don't fit in this model's context window, if it has one.
+    /// Returns how many were dropped
+    pub fn truncate_to_context_window(
+        &self,
+        conversation: &mut Vec<ChatMessage>,
+        system_prompt: &str,
+    ) -> usize {
+        match self.context_window {
+            Some(context_window) => truncate_to_context_window(
+                conversation,
+                system_prompt,
+                context_window.max(0) as usize,
+            ),
+            None => 0,
+        }
+    }
+}
+
+/// What local model servers like Ollama report about a model, for telling variants apart
+#[derive(Debug, Clone, Default, PartialEq, FromRow, Serialize, Deserialize)]
+pub struct ModelMetadata {
+    pub family: Option<String>,
+    pub parameter_size: Option<String>, // e.g. "8.0B"
+    pub quantization: Option<String>,   // e.g. "Q4_K_M"
+    pub size_bytes: Option<i64>,        // size of the weights on disk
+}
+
+impl ModelMetadata {
+    /// A short description like "llama 8.0B Q4_0 4.7GB", None when nothing is known
+    pub fn summary(&self) -> Option<String> {
+        let size = self
+            .size_bytes
+            .filter(|size_bytes| *size_bytes > 0)
+            .map(|size_bytes| format!("{:.1}GB", size_bytes as f64 / 1e9));
+        let parts: Vec<String> = [
+            self.family.clone(),
+            self.parameter_size.clone(),
+            self.quantization.clone(),
+            size,
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|part| !part.is_empty())
+        .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metadata_summary() {
+        assert_eq!(ModelMetadata::default().summary(), None);
+
+        let metadata = ModelMetadata {
+            family: Some("llama".to_string()),
+            parameter_size: Some("8.0B".to_string()),
+            quantization: Some("Q4_0".to_string()),
+            size_bytes: Some(4_661_224_676),
+        };
+        assert_eq!(metadata.summary().as_deref(), Some("llama 8.0B Q4_0 4.7GB"));
+
+        // empty and zero values are left out rather than shown blank
+        let sparse = ModelMetadata {
+            family: Some(String::new()),
+            quantization: Some("Q8_0".to_string()),
+            size_bytes: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(sparse.summary().as_deref(), Some("Q8_0"));
+    }
+}
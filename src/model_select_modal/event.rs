@@ -38,14 +38,13 @@ impl ModelSelectModal {
 
     async fn handle_normal_mode(&mut self, key: KeyEvent) -> Result<ModalResult> {
         // Handle numeric prefix accumulation (only for keys without modifiers)
-        if key.modifiers == KeyModifiers::NONE {
-            if let KeyCode::Char(c) = key.code {
-                if c.is_ascii_digit() {
-                    let digit = c.to_digit(10).unwrap() as usize;
-                    self.numeric_prefix = Some(self.numeric_prefix.unwrap_or(0) * 10 + digit);
-                    return Ok(ModalResult::Continue);
-                }
-            }
+        if key.modifiers == KeyModifiers::NONE
+            && let KeyCode::Char(c) = key.code
+            && c.is_ascii_digit()
+        {
+            let digit = c.to_digit(10).unwrap() as usize;
+            self.numeric_prefix = Some(self.numeric_prefix.unwrap_or(0) * 10 + digit);
+            return Ok(ModalResult::Continue);
         }
 
         let count = self.numeric_prefix.unwrap_or(1);
@@ -149,6 +148,24 @@ impl ModelSelectModal {
                 self.numeric_prefix = None;
                 self.last_key = None;
             }
+            KeyCode::Char('S') => {
+                // Save the current selection as a reusable named profile
+                let selected_models = self.get_selected_model_ids();
+                self.numeric_prefix = None;
+                self.last_key = None;
+                if !selected_models.is_empty() {
+                    return Ok(ModalResult::SaveAsProfile(selected_models));
+                }
+            }
+            KeyCode::Char('a') => {
+                // Give the model under the cursor a shorter name to show it under
+                let model_id = filtered_models.get(self.selection_index).map(|(model_id, _)| **model_id);
+                self.numeric_prefix = None;
+                self.last_key = None;
+                if let Some(model_id) = model_id {
+                    return Ok(ModalResult::EditAlias(model_id));
+                }
+            }
             KeyCode::Char('v') => {
                 // Enter visual mode
                 self.dialog_mode = ModelDialogMode::Visual;
@@ -8,6 +8,7 @@ pub mod render;
 pub enum ModelSelectionMode {
     DefaultModels,
     CurrentChatModels,
+    AddToCurrentChat, // a chat with messages can't swap its models, only take on more of them
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -21,6 +22,8 @@ pub enum ModelDialogMode {
 pub enum ModalResult {
     Continue,           // Modal stays open
     Apply(Vec<i64>),    // Apply these model IDs
+    SaveAsProfile(Vec<i64>), // Save these model IDs as a named profile, modal stays open
+    EditAlias(i64),          // Ask for a new alias for this model, modal stays open
 }
 
 pub struct ModelSelectModal {
@@ -109,7 +112,7 @@ impl ModelSelectModal {
         disabled_models.sort_by(|(_, a), (_, b)| {
             a.provider_id
                 .cmp(&b.provider_id)
-                .then_with(|| a.model.cmp(&b.model))
+                .then_with(|| a.display_name().cmp(b.display_name()))
         });
         
         // Combine enabled and disabled models
@@ -122,6 +125,7 @@ impl ModelSelectModal {
             models.retain(|(_, model)| {
                 let provider_name = self.get_provider_name(model.provider_id);
                 model.model.to_lowercase().contains(&query)
+                    || model.display_name().to_lowercase().contains(&query)
                     || provider_name.to_lowercase().contains(&query)
             });
         }
@@ -147,19 +151,19 @@ impl ModelSelectModal {
     
     /// Move an enabled model up in the order (decreases its index)
     pub fn move_model_up(&mut self, model_id: i64) {
-        if let Some(pos) = self.enabled_model_order.iter().position(|&id| id == model_id) {
-            if pos > 0 {
-                self.enabled_model_order.swap(pos, pos - 1);
-            }
+        if let Some(pos) = self.enabled_model_order.iter().position(|&id| id == model_id)
+            && pos > 0
+        {
+            self.enabled_model_order.swap(pos, pos - 1);
         }
     }
     
     /// Move an enabled model down in the order (increases its index)
     pub fn move_model_down(&mut self, model_id: i64) {
-        if let Some(pos) = self.enabled_model_order.iter().position(|&id| id == model_id) {
-            if pos < self.enabled_model_order.len() - 1 {
-                self.enabled_model_order.swap(pos, pos + 1);
-            }
+        if let Some(pos) = self.enabled_model_order.iter().position(|&id| id == model_id)
+            && pos < self.enabled_model_order.len() - 1
+        {
+            self.enabled_model_order.swap(pos, pos + 1);
         }
     }
     
@@ -1,20 +1,22 @@
 use super::{ModelSelectModal, ModelSelectionMode, ModelDialogMode};
+use crate::theme::Theme;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
-    text::Span,
+    style::{Modifier, Style},
+    text::{Line, Span},
     widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table},
 };
 
 impl ModelSelectModal {
-    pub fn render(&self, f: &mut Frame, area: Rect) {
+    pub fn render(&self, f: &mut Frame, area: Rect, theme: &Theme) {
         let popup_area = centered_rect(80, 70, area);
         f.render_widget(Clear, popup_area);
 
         let base_title = match self.mode {
             ModelSelectionMode::DefaultModels => "Select Default Models",
             ModelSelectionMode::CurrentChatModels => "Select Models for Current Chat",
+            ModelSelectionMode::AddToCurrentChat => "Add Models to Current Chat",
         };
 
         // Add mode indicator to title
@@ -44,7 +46,7 @@ impl ModelSelectModal {
         };
         
         let search_style = if self.dialog_mode == ModelDialogMode::Search {
-            Style::default().fg(Color::Yellow)
+            Style::default().fg(theme.warning)
         } else {
             Style::default()
         };
@@ -84,7 +86,7 @@ impl ModelSelectModal {
             .map(|(i, (model_id, model))| {
                 let is_selected = self.selection_states.get(model_id).unwrap_or(&false);
                 let is_cursor_here = i == self.selection_index;
-                let is_in_visual_range = visual_range.map_or(false, |(start, end)| i >= start && i <= end);
+                let is_in_visual_range = visual_range.is_some_and(|(start, end)| i >= start && i <= end);
 
                 // Get order index for enabled models
                 let order_indicator = if *is_selected {
@@ -101,7 +103,7 @@ impl ModelSelectModal {
                 let provider_name = self.get_provider_name(model.provider_id);
 
                 let checkbox_style = if *is_selected {
-                    Style::default().fg(Color::Green)
+                    Style::default().fg(theme.success)
                 } else {
                     Style::default()
                 };
@@ -109,22 +111,40 @@ impl ModelSelectModal {
                 let row_style = if is_cursor_here {
                     // Cursor position always gets yellow + bold
                     Style::default()
-                        .fg(Color::Yellow)
+                        .fg(theme.warning)
                         .add_modifier(Modifier::BOLD)
                 } else if is_in_visual_range {
                     // Visual range gets cyan background or different style
                     Style::default()
-                        .fg(Color::Cyan)
+                        .fg(theme.accent)
                         .add_modifier(Modifier::BOLD)
                 } else {
                     Style::default()
                 };
 
+                // local variants of a model are told apart by what the provider reports about them
+                let mut provider_cell = Line::from(Span::styled(provider_name, row_style));
+                if let Some(summary) = model.metadata.summary() {
+                    provider_cell.push_span(Span::styled(
+                        format!(" {}", summary),
+                        Style::default().fg(theme.muted),
+                    ));
+                }
+
+                // the real name still shows next to an alias, it's what the provider knows it by
+                let mut model_cell = Line::from(Span::styled(model.display_name().to_string(), row_style));
+                if model.alias.is_some() {
+                    model_cell.push_span(Span::styled(
+                        format!(" {}", model.model),
+                        Style::default().fg(theme.muted),
+                    ));
+                }
+
                 Row::new(vec![
                     Cell::from(Span::styled(checkbox, checkbox_style)),
                     Cell::from(Span::styled(order_indicator, checkbox_style)),
-                    Cell::from(Span::styled(model.model.clone(), row_style)),
-                    Cell::from(Span::styled(provider_name, row_style)),
+                    Cell::from(model_cell),
+                    Cell::from(provider_cell),
                 ])
             })
             .collect();
@@ -162,7 +182,7 @@ impl ModelSelectModal {
             Block::default()
                 .title(title)
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Yellow)),
+                .border_style(Style::default().fg(theme.warning)),
         )
         .column_spacing(1);
 
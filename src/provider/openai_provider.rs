@@ -1,30 +1,52 @@
+use std::{collections::HashMap, sync::Mutex};
+
 use async_trait::async_trait;
 use eyre::Result;
-use openai_api_rs_prime::v1::{api::OpenAIClient, chat_completion::{self, chat_completion::ChatCompletionRequest, ChatCompletionMessage, MessageRole, Tool, ToolCall, ToolCallFunction, ToolChoiceType}, types::{Function, FunctionParameters}};
+use serde::{Deserialize, Serialize};
+use openai_api_rs_prime::v1::{chat_completion::{self, chat_completion::ChatCompletionRequest, ChatCompletionChoice, FinishReason, Tool, ToolCall, ToolCallFunction, ToolChoiceType}, types::{Function, FunctionParameters}};
 use tracing::info;
 
-use crate::{model::{chat::{ChatMessage, ChatRole}}, provider::provider::{GenerationResult, Provider, ProviderClient, ToolCallRequest}};
+use crate::{context::estimate_tokens, model::{chat::{ChatMessage, ChatRole}, model::ModelMetadata}, provider::provider::{GenerationOptions, GenerationResult, ListedModel, Provider, ProviderClient, RateLimitStatus, ResponseFormat, ToolCallRequest}};
 
-fn chat_role_to_message_role(chat_role: &ChatRole) -> MessageRole {
+fn chat_role_to_message_role(chat_role: &ChatRole) -> &'static str {
     match chat_role {
-        ChatRole::User => MessageRole::user,
-        ChatRole::Assistant => MessageRole::assistant,
-        ChatRole::ToolResult => MessageRole::tool,    
+        ChatRole::User => "user",
+        ChatRole::Assistant => "assistant",
+        ChatRole::ToolResult => "tool",
     }
 }
 
+/// A message of the request. It's openai-api-rs-prime's ChatCompletionMessage with the role as a string, so the
+/// system prompt can go out as a "developer" message, which its MessageRole has no variant for
+#[derive(Debug, Serialize)]
+struct RequestMessage {
+    role: &'static str,
+    content: chat_completion::Content,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+/// The chat completion body to send. It's built as json rather than a ChatCompletionRequest so the
+/// system prompt can go out as a "developer" message, which openai-api-rs-prime's MessageRole has no
+/// variant for
 fn create_chat_request(
     model: &str,
     system_prompt: &str,
+    developer_role: bool,
     conversation: &[ChatMessage],
     available_tools: &[&dyn crate::model::tool::Tool],
-) -> Result<ChatCompletionRequest> {
+    options: &GenerationOptions,
+) -> Result<serde_json::Value> {
     let mut messages = Vec::new();
 
     // Add system message if instructions are provided
     if !system_prompt.is_empty() {
-        messages.push(ChatCompletionMessage {
-            role: MessageRole::system,
+        messages.push(RequestMessage {
+            role: if developer_role { "developer" } else { "system" },
             content: chat_completion::Content::Text(system_prompt.to_string()),
             name: None,
             tool_calls: None,
@@ -50,16 +72,36 @@ fn create_chat_request(
             None
         };
 
-        messages.push(ChatCompletionMessage {
+        let mut content = chat_msg.content.clone().unwrap_or_default(); // why doesn't openai offer an optional for content? might be blank for tool calls right?
+        let mut name = chat_msg.name.clone(); // forwarded for every role, not just tool results
+        if chat_msg.is_aside() {
+            // an aside goes out as a user message that says what it is, the name alone isn't something models act on
+            content = format!("(Aside, keep this in mind but don't answer it on its own)\n\n{}", content);
+            name = None;
+        }
+        messages.push(RequestMessage {
             role: chat_role_to_message_role(&chat_msg.chat_role),
-            content: chat_completion::Content::Text(chat_msg.content.clone().unwrap_or_default()), // why doesn't openai offer an optional for content? might be blank for tool calls right?
-            name: chat_msg.name.clone(),
+            content: chat_completion::Content::Text(content),
+            name,
             tool_calls,
             tool_call_id: chat_msg.tool_call_id.clone(),
         });
     }
 
-    let mut res = ChatCompletionRequest::new(model.to_string(), messages);
+    // the options are set on openai-api-rs-prime's request type, the messages are our own
+    let mut res = ChatCompletionRequest::new(model.to_string(), Vec::new());
+    match &options.response_format {
+        ResponseFormat::Text => {}
+        ResponseFormat::JsonObject => {
+            res = res.response_format(serde_json::json!({ "type": "json_object" }));
+        }
+    }
+    if !options.stop.is_empty() {
+        res = res.stop(options.stop.clone());
+    }
+    if let Some(n) = options.n {
+        res = res.n(n);
+    }
     if !available_tools.is_empty() {
         res = res.tools(
             available_tools.iter().map(|t| {
@@ -81,16 +123,241 @@ fn create_chat_request(
         ).parallel_tool_calls(false).tool_choice(ToolChoiceType::Auto);
     }
 
-    Ok(res)
+    let mut request = serde_json::to_value(res)?;
+    request["messages"] = serde_json::to_value(messages)?;
+    Ok(request)
+}
+
+/// Strip the think tokens if asked to, then cut the response at the first stop sequence. Not every
+/// provider honors stop sequences, so we do it ourselves. Thinking that's kept isn't searched for them,
+/// only the answer after it
+fn clean_content(content: String, stop: &[String], remove_think_tokens: bool) -> String {
+    let content = match content.split_once("</think>") {
+        Some((_, after_think)) if remove_think_tokens => {
+            info!("Trimmed think tokens from LLM response!");
+            after_think.trim().to_string() // should we do this trim irrespective of whether we removed think tokens?
+        }
+        _ => content,
+    };
+
+    let answer_start = content.find("</think>").map_or(0, |idx| idx + "</think>".len());
+    match stop.iter().filter_map(|stop| content[answer_start..].find(stop.as_str())).min() {
+        Some(stop_idx) => content[..answer_start + stop_idx].to_string(),
+        None => content,
+    }
+}
+
+/// The reasoning tokens a response spent and whether that's an estimate. Models that keep their reasoning
+/// to themselves only show up in the usage, so the reasoning text is only estimated when the provider
+/// doesn't count them
+fn count_reasoning_tokens(usage: Option<&Usage>, reasoning_content: Option<&str>) -> (Option<i64>, bool) {
+    let reported = usage
+        .and_then(|usage| usage.completion_tokens_details.as_ref())
+        .and_then(|details| details.reasoning_tokens);
+    match (reported, reasoning_content) {
+        (Some(reported), _) => (Some(reported), false),
+        (None, Some(reasoning)) => (Some(estimate_tokens(reasoning) as i64), true),
+        (None, None) => (None, false),
+    }
+}
+
+/// Trim what commonly gets pasted along with an OpenAI compatible base url, and check that what's left looks like one.
+/// The api path (e.g. /v1) isn't added since not every provider uses one
+pub fn normalize_base_url(base_url: &str) -> Result<String> {
+    let base_url = base_url.trim();
+    let Some((scheme, rest)) = base_url.split_once("://") else {
+        eyre::bail!("base url '{}' is missing http:// or https://", base_url);
+    };
+    if scheme != "http" && scheme != "https" {
+        eyre::bail!("base url '{}' must use http or https", base_url);
+    }
+    let host = rest.split('/').next().unwrap_or_default();
+    if host.is_empty() {
+        eyre::bail!("base url '{}' has no host", base_url);
+    }
+    if base_url.contains(char::is_whitespace) || base_url.contains(['?', '#']) {
+        eyre::bail!("base url '{}' is malformed", base_url);
+    }
+
+    // the client appends the endpoint path itself, so a pasted endpoint url or trailing slash would double it up
+    let base_url = base_url.trim_end_matches('/');
+    let base_url = base_url.strip_suffix("/chat/completions").unwrap_or(base_url);
+    Ok(base_url.trim_end_matches('/').to_string())
+}
+
+/// Response of Ollama's native /api/tags endpoint, only the parts we show
+#[derive(Debug, Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModel {
+    name: String,
+    size: Option<i64>,
+    details: Option<OllamaModelDetails>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaModelDetails {
+    family: Option<String>,
+    parameter_size: Option<String>,
+    quantization_level: Option<String>,
+}
+
+/// A chat completion response, only the parts we read. Unlike openai-api-rs-prime's own response type
+/// usage is optional, since some providers leave it out
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+    choices: Vec<ChatCompletionChoice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    prompt_tokens: i64,
+    completion_tokens: i64,
+    #[serde(default)]
+    completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+/// How the completion tokens break down. Reasoning models count the thinking they don't return here
+#[derive(Debug, Deserialize)]
+struct CompletionTokensDetails {
+    #[serde(default)]
+    reasoning_tokens: Option<i64>,
+}
+
+/// What the models endpoint lists, only the ids
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelsResponseModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponseModel {
+    id: Option<String>,
 }
 
 pub struct OpenAIProvider {
     provider: Provider,
+    base_url_error: Option<String>, // reported on every request so a bad url shows up where it's used
+    client: std::result::Result<reqwest::Client, String>, // shared by every request, with the extra headers and timeout
+    rate_limit: Mutex<Option<RateLimitStatus>>, // from the latest response that reported it
 }
 
 impl OpenAIProvider {
-    pub fn new(provider: Provider) -> Self {
-        Self { provider }
+    pub fn new(mut provider: Provider) -> Self {
+        let base_url_error = match normalize_base_url(&provider.base_url) {
+            Ok(base_url) => {
+                provider.base_url = base_url;
+                None
+            }
+            Err(e) => Some(e.to_string()),
+        };
+        // extra_headers only returns valid ones
+        let headers = provider
+            .extra_headers()
+            .into_iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    reqwest::header::HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    reqwest::header::HeaderValue::from_str(&value).ok()?,
+                ))
+            })
+            .collect();
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(provider.request_timeout())
+            .build()
+            .map_err(|e| e.to_string());
+        Self { provider, base_url_error, client, rate_limit: Mutex::new(None) }
+    }
+
+    fn client(&self) -> Result<&reqwest::Client> {
+        self.client
+            .as_ref()
+            .map_err(|e| eyre::eyre!("Could not create client for provider {}: {}", self.provider.name, e))
+    }
+
+    /// A request to one of the provider's endpoints with its api key. A bad base url or an api key
+    /// that went missing fails here rather than with the request
+    fn request(&self, method: reqwest::Method, endpoint: &str) -> Result<reqwest::RequestBuilder> {
+        if let Some(base_url_error) = &self.base_url_error {
+            eyre::bail!("Provider {} is misconfigured: {}", self.provider.name, base_url_error);
+        }
+        let token = std::env::var(&self.provider.api_key_env_var)
+            .map_err(|_| eyre::eyre!("{} is not set for provider {}", self.provider.api_key_env_var, self.provider.name))?;
+        Ok(self
+            .client()?
+            .request(method, format!("{}/{}", self.provider.base_url, endpoint))
+            .bearer_auth(token))
+    }
+
+    /// The client's timeout covers the whole request, reading the response included. Its error doesn't say
+    /// how long that was
+    fn request_error(&self, e: reqwest::Error) -> eyre::Report {
+        if e.is_timeout() {
+            eyre::eyre!("Request timed out after {}s", self.provider.request_timeout().as_secs())
+        } else {
+            e.into()
+        }
+    }
+
+    /// Keep the rate limits a response reported, a response without them leaves the last ones in place
+    fn record_rate_limit(&self, headers: &reqwest::header::HeaderMap) {
+        let rate_limit = RateLimitStatus::from_headers(
+            headers.iter().filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+        );
+        if let Some(rate_limit) = rate_limit {
+            info!("Rate limit status for provider {}: {:?}", self.provider.name, rate_limit);
+            *self.rate_limit.lock().unwrap() = Some(rate_limit);
+        }
+    }
+
+    /// Send a chat completion body built by create_chat_request
+    async fn send_chat_request(&self, request: &serde_json::Value) -> Result<ChatCompletionResponse> {
+        let response = self
+            .request(reqwest::Method::POST, "chat/completions")?
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| self.request_error(e))?;
+        // a 429 is the response most worth reading the limits from, so this comes before the status check
+        self.record_rate_limit(response.headers());
+        let status = response.status();
+        let text = response.text().await.map_err(|e| self.request_error(e))?;
+        if !status.is_success() {
+            eyre::bail!("{}: {}", status, text);
+        }
+
+        serde_json::from_str(&text)
+            .map_err(|e| eyre::eyre!("Failed to parse JSON: {} / response {}", e, text))
+    }
+
+    /// Model details from Ollama's native api, which lives at the root rather than under /v1
+    async fn fetch_ollama_metadata(&self) -> Result<HashMap<String, ModelMetadata>> {
+        let root = self.provider.base_url.strip_suffix("/v1").unwrap_or(&self.provider.base_url);
+        let tags: OllamaTags = self
+            .client()?
+            .get(format!("{}/api/tags", root))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(tags.models.into_iter().map(|model| {
+            let details = model.details;
+            let metadata = ModelMetadata {
+                family: details.as_ref().and_then(|details| details.family.clone()),
+                parameter_size: details.as_ref().and_then(|details| details.parameter_size.clone()),
+                quantization: details.as_ref().and_then(|details| details.quantization_level.clone()),
+                size_bytes: model.size,
+            };
+            (model.name, metadata)
+        }).collect())
     }
 }
 
@@ -100,30 +367,32 @@ impl ProviderClient for OpenAIProvider {
         &self, 
         model: &str,
         system_prompt: &str,
-        conversation: &Vec<ChatMessage>,
+        conversation: &[ChatMessage],
         available_tools: Vec<&dyn crate::model::tool::Tool>,
         remove_think_tokens: bool,
+        options: &GenerationOptions,
     ) -> Result<GenerationResult>
     {
-        let token = std::env::var(&self.provider.api_key_env_var).expect("API key env var not set! This should not happen");
         info!("Running inference with endpoint {} and api key {}", &self.provider.base_url, &self.provider.api_key_env_var);
-        let mut client = OpenAIClient::builder()
-            .with_endpoint(&self.provider.base_url)
-            .with_api_key(token)
-            .build()
-            .expect("could not create OpenAI client");
-
         let request = create_chat_request(
             model,
             system_prompt,
-            &conversation,
+            self.provider.uses_developer_role(),
+            conversation,
             &available_tools,
+            options,
         )?;
 
-        info!("Sending completion request with messages: {:?}", &request.messages);
-        let response = client.chat_completion(request).await?;
+        info!("Sending completion request with messages: {}", &request["messages"]);
+        let response = self.send_chat_request(&request).await?;
+
+        // usage is unknown when it's left out, and when some providers report it as all zeroes
+        let usage = response.usage.filter(|usage| usage.prompt_tokens != 0 || usage.completion_tokens != 0);
+        let prompt_tokens = usage.as_ref().map(|usage| usage.prompt_tokens);
+        let completion_tokens = usage.as_ref().map(|usage| usage.completion_tokens);
 
-        let choice = response.choices.into_iter().next()
+        let mut choices = response.choices.into_iter();
+        let choice = choices.next()
             .ok_or_else(|| eyre::eyre!("No content in response"))?;
         
         let tool_calls = choice.message.tool_calls.map(|tool_calls| {
@@ -137,23 +406,238 @@ impl ProviderClient for OpenAIProvider {
         }).unwrap_or(vec![]);
 
         let content = choice.message.content.clone();
+        let finish_reason = choice.finish_reason.as_ref().and_then(|finish_reason| match finish_reason {
+            FinishReason::stop => Some("stop"),
+            FinishReason::length => Some("length"),
+            FinishReason::content_filter => Some("content_filter"),
+            FinishReason::tool_calls => Some("tool_calls"),
+            FinishReason::null => None,
+        }).map(str::to_string);
 
-        let content = content.map(|content| {
-            if remove_think_tokens {
-                if let Some((_, after_think)) = content.split_once("</think>") {
-                    info!("Trimmed think tokens from LLM response!");
-                    after_think.trim().to_string() // should we do this trim irrespective of whether we removed think tokens?
-                } else {
-                    content
-                }
-            } else {
-                content
-            }
-        }); 
+        // reasoning either comes back in its own field or inline as a <think> block
+        let reasoning_content = choice.message.reasoning_content.clone().or_else(|| {
+            content
+                .as_deref()
+                .and_then(|content| content.split_once("</think>"))
+                .map(|(think, _)| think.trim().trim_start_matches("<think>").trim().to_string())
+        });
+        let (reasoning_tokens, reasoning_tokens_estimated) =
+            count_reasoning_tokens(usage.as_ref(), reasoning_content.as_deref());
+
+        let content = content.map(|content| clean_content(content, &options.stop, remove_think_tokens));
+
+        // the other candidates only matter for their text, tool calls and reasoning come from the first
+        let other_choices = choices
+            .filter_map(|choice| choice.message.content)
+            .map(|content| clean_content(content, &options.stop, remove_think_tokens))
+            .filter(|content| !content.trim().is_empty())
+            .collect();
 
         Ok(GenerationResult {
             content,
-            tool_calls
+            tool_calls,
+            reasoning_content,
+            reasoning_tokens,
+            reasoning_tokens_estimated,
+            prompt_tokens,
+            completion_tokens,
+            finish_reason,
+            other_choices,
         })
     }
-}
\ No newline at end of file
+
+    async fn list_models(&self) -> Result<Vec<ListedModel>> {
+        let response = self
+            .request(reqwest::Method::GET, "models")?
+            .send()
+            .await
+            .map_err(|e| self.request_error(e))?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| self.request_error(e))?;
+        if !status.is_success() {
+            eyre::bail!("{}: {}", status, text);
+        }
+        let response: ModelsResponse = serde_json::from_str(&text)
+            .map_err(|e| eyre::eyre!("Failed to parse JSON: {} / response {}", e, text))?;
+        let mut listed_models: Vec<ListedModel> = response.data.into_iter()
+            .filter_map(|model| model.id)
+            .map(|id| ListedModel { id, metadata: ModelMetadata::default() })
+            .collect();
+
+        // the details are nice to have, so models are still listed without them
+        if self.provider.is_ollama() {
+            match self.fetch_ollama_metadata().await {
+                Ok(mut metadata) => {
+                    for listed_model in &mut listed_models {
+                        if let Some(metadata) = metadata.remove(&listed_model.id) {
+                            listed_model.metadata = metadata;
+                        }
+                    }
+                }
+                Err(e) => info!("Couldn't read model details from provider {}: {}", self.provider.name, e),
+            }
+        }
+        Ok(listed_models)
+    }
+    fn preview_request(
+        &self,
+        model: &str,
+        system_prompt: &str,
+        conversation: &[ChatMessage],
+        options: &GenerationOptions,
+    ) -> Result<String> {
+        let request = create_chat_request(model, system_prompt, self.provider.uses_developer_role(), conversation, &[], options)?;
+        Ok(serde_json::to_string_pretty(&request)?)
+    }
+    fn rate_limit(&self) -> Option<RateLimitStatus> {
+        self.rate_limit.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::provider::tests::test_provider;
+    use std::time::Duration;
+
+    #[test]
+    fn test_normalize_base_url() {
+        assert_eq!(normalize_base_url("https://api.openai.com/v1").unwrap(), "https://api.openai.com/v1");
+        // pasted along with the url
+        assert_eq!(normalize_base_url("  https://api.openai.com/v1/ \n").unwrap(), "https://api.openai.com/v1");
+        assert_eq!(normalize_base_url("https://api.openai.com/v1/chat/completions/").unwrap(), "https://api.openai.com/v1");
+        // no api path is added
+        assert_eq!(normalize_base_url("http://localhost:11434").unwrap(), "http://localhost:11434");
+
+        assert!(normalize_base_url("api.openai.com/v1").is_err());
+        assert!(normalize_base_url("ftp://api.openai.com/v1").is_err());
+        assert!(normalize_base_url("https:///v1").is_err());
+        assert!(normalize_base_url("https://api.openai.com/v1?key=1").is_err());
+        assert!(normalize_base_url("https://api.openai.com/my v1").is_err());
+    }
+
+    #[test]
+    fn test_clean_content_strips_think_tokens_before_stop_sequences() {
+        let stop = vec!["END".to_string()];
+        let response = "<think>the END is near</think>\nAnswer END ignored".to_string();
+        assert_eq!(clean_content(response.clone(), &stop, true), "Answer ");
+        // kept thinking isn't cut at a stop sequence, only the answer is
+        assert_eq!(clean_content(response, &stop, false), "<think>the END is near</think>\nAnswer ");
+
+        assert_eq!(clean_content("a;b.c".to_string(), &[".".to_string(), ";".to_string()], true), "a");
+        assert_eq!(clean_content("no stops".to_string(), &[], true), "no stops");
+    }
+
+    #[test]
+    fn test_create_chat_request_marks_asides() {
+        let conversation = vec![
+            ChatMessage::new_user_message(1, "What's the capital of France?".to_string()),
+            ChatMessage::new_assistant_message(1, 2, "Paris.".to_string(), 0),
+            ChatMessage::new_aside_message(1, "I'm planning a trip there".to_string()),
+        ];
+        let request = create_chat_request("gpt-5", "Be brief.", false, &conversation, &[], &GenerationOptions::default()).unwrap();
+        let messages = request["messages"].as_array().unwrap();
+
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[1]["content"], "What's the capital of France?");
+        assert_eq!(messages[2]["role"], "assistant");
+        // the aside is a user message that says it's one, without the internal name
+        assert_eq!(messages[3]["role"], "user");
+        assert_eq!(messages[3]["content"], "(Aside, keep this in mind but don't answer it on its own)\n\nI'm planning a trip there");
+        assert!(messages[3].get("name").is_none_or(|name| name.is_null()));
+    }
+
+    #[test]
+    fn test_create_chat_request_sends_the_system_prompt_as_developer() {
+        let conversation = vec![ChatMessage::new_user_message(1, "Hi".to_string())];
+        let options = GenerationOptions::default();
+
+        let request = create_chat_request("gpt-5", "Be brief.", true, &conversation, &[], &options).unwrap();
+        assert_eq!(request["messages"][0]["role"], "developer");
+        assert_eq!(request["messages"][0]["content"], "Be brief.");
+        assert_eq!(request["messages"][1]["role"], "user");
+
+        let request = create_chat_request("gpt-5", "Be brief.", false, &conversation, &[], &options).unwrap();
+        assert_eq!(request["messages"][0]["role"], "system");
+        // without a system prompt there's nothing to send as developer
+        let request = create_chat_request("gpt-5", "", true, &conversation, &[], &options).unwrap();
+        assert_eq!(request["messages"][0]["role"], "user");
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_an_api_key_fail_instead_of_panicking() {
+        let provider = OpenAIProvider::new(Provider {
+            api_key_env_var: "SHORE_TEST_UNSET_API_KEY".to_string(),
+            ..test_provider()
+        });
+        let error = provider.list_models().await.unwrap_err();
+        assert_eq!(error.to_string(), "SHORE_TEST_UNSET_API_KEY is not set for provider OpenRouter");
+
+        let provider = OpenAIProvider::new(Provider { base_url: "openrouter.ai".to_string(), ..test_provider() });
+        let error = provider
+            .run("gpt-5", "", &[], vec![], false, &GenerationOptions::default())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Provider OpenRouter is misconfigured"));
+    }
+
+    #[test]
+    fn test_rate_limits_are_kept_until_a_response_reports_new_ones() {
+        let provider = OpenAIProvider::new(test_provider());
+        let headers = |pairs: &[(&'static str, &'static str)]| {
+            pairs
+                .iter()
+                .map(|(name, value)| {
+                    (reqwest::header::HeaderName::from_static(name), reqwest::header::HeaderValue::from_static(value))
+                })
+                .collect::<reqwest::header::HeaderMap>()
+        };
+
+        // what a 429 comes with
+        provider.record_rate_limit(&headers(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "20s"),
+        ]));
+        let wait = provider.rate_limit().and_then(|rate_limit| rate_limit.wait_before_next_request());
+        assert!(wait.is_some_and(|wait| wait > Duration::from_secs(19)));
+
+        provider.record_rate_limit(&headers(&[("content-type", "application/json")]));
+        assert_eq!(provider.rate_limit().unwrap().remaining_requests, Some(0));
+    }
+
+    #[test]
+    fn test_response_without_usage_is_accepted() {
+        let response: ChatCompletionResponse = serde_json::from_str(r#"{
+            "id": "1",
+            "created": 0,
+            "model": "llama",
+            "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}]
+        }"#).unwrap();
+        assert!(response.usage.is_none());
+        assert_eq!(response.choices[0].message.content.as_deref(), Some("Hi"));
+
+        let response: ChatCompletionResponse = serde_json::from_str(r#"{
+            "choices": [],
+            "usage": {"prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15}
+        }"#).unwrap();
+        assert_eq!(response.usage.unwrap().prompt_tokens, 12);
+    }
+
+    #[test]
+    fn test_reasoning_tokens_come_from_the_usage() {
+        let usage = |details: &str| -> Usage {
+            serde_json::from_str(&format!(r#"{{"prompt_tokens": 12, "completion_tokens": 900{}}}"#, details)).unwrap()
+        };
+        let reported = usage(r#", "completion_tokens_details": {"reasoning_tokens": 850}"#);
+
+        // o-series models hide their reasoning, the usage is all there is
+        assert_eq!(count_reasoning_tokens(Some(&reported), None), (Some(850), false));
+        assert_eq!(count_reasoning_tokens(Some(&reported), Some("thinking")), (Some(850), false));
+        // without a count the reasoning text is estimated, and says so
+        let estimated = count_reasoning_tokens(Some(&usage("")), Some("thinking it over"));
+        assert!(estimated.0.is_some_and(|tokens| tokens > 0));
+        assert!(estimated.1);
+        assert_eq!(count_reasoning_tokens(None, None), (None, false));
+    }
+}
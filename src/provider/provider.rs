@@ -2,8 +2,10 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use eyre::Result;
+use std::time::{Duration, Instant};
+use tracing::warn;
 
-use crate::{model::chat::ChatMessage, model::tool::Tool};
+use crate::{model::chat::ChatMessage, model::model::ModelMetadata, model::tool::Tool};
 
 pub enum GenerationRequest {
     Prompt(String), // a "normal" prompt
@@ -14,6 +16,13 @@ pub enum GenerationRequest {
 pub struct GenerationResult {
     pub content: Option<String>,
     pub tool_calls: Vec<ToolCallRequest>,
+    pub reasoning_content: Option<String>,
+    pub reasoning_tokens: Option<i64>, // from the usage's completion_tokens_details when the provider reports them
+    pub reasoning_tokens_estimated: bool, // the provider didn't, so reasoning_tokens was estimated from reasoning_content
+    pub prompt_tokens: Option<i64>,
+    pub completion_tokens: Option<i64>,
+    pub finish_reason: Option<String>, // "stop", "length", "tool_calls" etc. as the provider reported it
+    pub other_choices: Vec<String>, // content of the candidates after the first, when more than one was asked for
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -29,6 +38,132 @@ pub struct StructuredGenerationResult<T> {
     pub tool_calls: Vec<ToolCallRequest>,
 }
 
+/// How the model is asked to format its response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ResponseFormat {
+    #[default]
+    Text,
+    JsonObject,
+}
+
+/// Per-request settings that aren't part of the conversation itself
+#[derive(Debug, Clone, Default)]
+pub struct GenerationOptions {
+    pub response_format: ResponseFormat,
+    pub stop: Vec<String>, // generation halts before any of these, empty means none
+    pub n: Option<i64>, // how many candidate responses to generate, None leaves it to the provider (one)
+}
+
+/// A model as listed by a provider's models endpoint
+#[derive(Debug, Clone)]
+pub struct ListedModel {
+    pub id: String,
+    pub metadata: ModelMetadata, // empty unless the provider reports details, like Ollama does
+}
+
+/// What a provider's rate-limit headers said on its most recent response
+#[derive(Debug, Clone)]
+pub struct RateLimitStatus {
+    pub limit_requests: Option<i64>,
+    pub remaining_requests: Option<i64>,
+    pub reset_requests: Option<Duration>,
+    pub limit_tokens: Option<i64>,
+    pub remaining_tokens: Option<i64>,
+    pub reset_tokens: Option<Duration>,
+    pub observed_at: Instant,
+}
+
+impl RateLimitStatus {
+    /// Read the x-ratelimit-* headers OpenAI and most compatible providers send, None without any
+    pub fn from_headers<'a>(headers: impl Iterator<Item = (&'a str, &'a str)>) -> Option<Self> {
+        let mut status = RateLimitStatus {
+            limit_requests: None,
+            remaining_requests: None,
+            reset_requests: None,
+            limit_tokens: None,
+            remaining_tokens: None,
+            reset_tokens: None,
+            observed_at: Instant::now(),
+        };
+        let mut found = false;
+        for (name, value) in headers {
+            let value = value.trim();
+            match name.to_ascii_lowercase().as_str() {
+                "x-ratelimit-limit-requests" => status.limit_requests = value.parse().ok(),
+                "x-ratelimit-remaining-requests" => status.remaining_requests = value.parse().ok(),
+                "x-ratelimit-reset-requests" => status.reset_requests = parse_reset_duration(value),
+                "x-ratelimit-limit-tokens" => status.limit_tokens = value.parse().ok(),
+                "x-ratelimit-remaining-tokens" => status.remaining_tokens = value.parse().ok(),
+                "x-ratelimit-reset-tokens" => status.reset_tokens = parse_reset_duration(value),
+                _ => continue,
+            }
+            found = true;
+        }
+        found.then_some(status)
+    }
+
+    /// Whether fewer than `fraction` of the requests or tokens in the current window are left
+    pub fn is_near_limit(&self, fraction: f64) -> bool {
+        let near = |remaining: Option<i64>, limit: Option<i64>| match (remaining, limit) {
+            (Some(remaining), Some(limit)) if limit > 0 => {
+                (remaining as f64) < limit as f64 * fraction
+            }
+            (Some(remaining), None) => remaining <= 0,
+            _ => false,
+        };
+        near(self.remaining_requests, self.limit_requests)
+            || near(self.remaining_tokens, self.limit_tokens)
+    }
+
+    /// How long until the exhausted limit resets, None when there's still room for another request
+    pub fn wait_before_next_request(&self) -> Option<Duration> {
+        let elapsed = self.observed_at.elapsed();
+        [
+            (self.remaining_requests, self.reset_requests),
+            (self.remaining_tokens, self.reset_tokens),
+        ]
+        .into_iter()
+        .filter(|(remaining, _)| remaining.is_some_and(|remaining| remaining <= 0))
+        .filter_map(|(_, reset)| reset?.checked_sub(elapsed))
+        .max()
+    }
+}
+
+/// Reset times come as durations like "1s", "6m0s" or "250ms", some providers just send seconds
+fn parse_reset_duration(value: &str) -> Option<Duration> {
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(secs) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).ok();
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..number_len].parse().ok()?;
+        if !number.is_finite() {
+            return None;
+        }
+        rest = &rest[number_len..];
+        let unit_len = rest.find(|ch: char| ch.is_ascii_digit()).unwrap_or(rest.len());
+        let secs = match &rest[..unit_len] {
+            "ms" => number / 1000.0,
+            "s" => number,
+            "m" => number * 60.0,
+            "h" => number * 60.0 * 60.0,
+            _ => return None,
+        };
+        // a value too large for a Duration isn't one we can wait out anyway
+        total = total.checked_add(Duration::try_from_secs_f64(secs).ok()?)?;
+        rest = &rest[unit_len..];
+    }
+    Some(total)
+}
+
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct Provider {
     pub id: i64,
@@ -38,6 +173,107 @@ pub struct Provider {
     pub deprecated: bool,
     pub api_key_env_var: String,
     pub created_dt: i64,
+    pub model_include: Option<String>, // comma separated glob patterns, only matching models are offered
+    pub model_exclude: Option<String>, // comma separated glob patterns, matching models are hidden
+    pub request_timeout_secs: Option<i64>,
+    pub extra_headers: Option<String>, // JSON object of header name to value, sent with every request
+    pub no_models_endpoint: bool, // models are added by hand instead of synced from the models endpoint
+    pub system_role: Option<String>, // "developer" sends the system prompt in the role newer OpenAI models prefer, NULL sends "system"
+}
+
+/// How long a chat completion may take when the provider doesn't set its own timeout
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+impl Provider {
+    pub fn request_timeout(&self) -> Duration {
+        self.request_timeout_secs
+            .filter(|secs| *secs > 0)
+            .map(|secs| Duration::from_secs(secs as u64))
+            .unwrap_or(DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// Whether the system prompt goes out as a "developer" message instead of a "system" one
+    pub fn uses_developer_role(&self) -> bool {
+        self.system_role.as_deref() == Some("developer")
+    }
+
+    /// Ollama serves an OpenAI compatible api next to its own, which describes models in more
+    /// detail. It's recognized by name or by its default port
+    pub fn is_ollama(&self) -> bool {
+        self.name.to_lowercase().contains("ollama") || self.base_url.contains(":11434")
+    }
+
+    /// Whether a model passes this provider's include/exclude patterns
+    pub fn includes_model(&self, model: &str) -> bool {
+        let matches_any = |patterns: &str| {
+            patterns
+                .split(',')
+                .map(str::trim)
+                .filter(|pattern| !pattern.is_empty())
+                .any(|pattern| glob_match(pattern, model))
+        };
+
+        let included = self.model_include.as_deref().is_none_or(matches_any);
+        let excluded = self.model_exclude.as_deref().is_some_and(matches_any);
+        included && !excluded
+    }
+
+    /// The extra headers to send with each request. Bad JSON or a header that isn't valid HTTP is skipped
+    /// with a warning rather than failing the request
+    pub fn extra_headers(&self) -> Vec<(String, String)> {
+        let Some(extra_headers) = self.extra_headers.as_deref().filter(|json| !json.trim().is_empty()) else {
+            return Vec::new();
+        };
+        let headers: serde_json::Map<String, serde_json::Value> = match serde_json::from_str(extra_headers) {
+            Ok(headers) => headers,
+            Err(e) => {
+                warn!("Ignoring extra headers of provider {}, they aren't a JSON object: {}", self.name, e);
+                return Vec::new();
+            }
+        };
+
+        headers.into_iter().filter_map(|(name, value)| {
+            let Some(value) = value.as_str() else {
+                warn!("Ignoring header {} of provider {}, its value isn't a string", name, self.name);
+                return None;
+            };
+            if reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_err()
+                || reqwest::header::HeaderValue::from_str(value).is_err()
+            {
+                warn!("Ignoring header {} of provider {}, it isn't a valid HTTP header", name, self.name);
+                return None;
+            }
+            Some((name, value.to_string()))
+        }).collect()
+    }
+}
+
+/// Minimal glob matching supporting `*` (any run of characters) and `?` (any single character)
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` seen and the text position it was tried against, for backtracking
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // let the last `*` swallow one more character and try again
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&ch| ch == '*')
 }
 
 #[async_trait]
@@ -46,8 +282,142 @@ pub trait ProviderClient: Send + Sync {
         &self,
         model: &str,
         system_prompt: &str,
-        conversation: &Vec<ChatMessage>,
+        conversation: &[ChatMessage],
         available_tools: Vec<&dyn Tool>, // this is a list of tools that the model can use to help with the prompt
         remove_think_tokens: bool,
+        options: &GenerationOptions,
     ) -> Result<GenerationResult>;
-}
\ No newline at end of file
+
+    /// The models the provider currently serves
+    async fn list_models(&self) -> Result<Vec<ListedModel>>;
+
+    /// The request body `run` would send for this conversation, pretty printed instead of sent
+    fn preview_request(
+        &self,
+        model: &str,
+        system_prompt: &str,
+        conversation: &[ChatMessage],
+        options: &GenerationOptions,
+    ) -> Result<String>;
+
+    /// Rate limits reported with the provider's most recent response, if it reports them
+    fn rate_limit(&self) -> Option<RateLimitStatus>;
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit_headers() {
+        let headers = [
+            ("x-ratelimit-limit-requests", "500"),
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "6m0.5s"),
+            ("X-RateLimit-Remaining-Tokens", "149984"),
+            ("x-ratelimit-reset-tokens", "250ms"),
+            ("content-type", "application/json"),
+        ];
+        let status = RateLimitStatus::from_headers(headers.into_iter()).unwrap();
+        assert_eq!(status.limit_requests, Some(500));
+        assert_eq!(status.remaining_requests, Some(0));
+        assert_eq!(status.reset_requests, Some(Duration::from_millis(360_500)));
+        assert_eq!(status.remaining_tokens, Some(149984));
+        assert_eq!(status.reset_tokens, Some(Duration::from_millis(250)));
+        assert!(status.is_near_limit(0.1));
+        assert!(status.wait_before_next_request().unwrap() > Duration::from_secs(359));
+
+        let unrelated = [("content-type", "text/plain")];
+        assert!(RateLimitStatus::from_headers(unrelated.into_iter()).is_none());
+        assert_eq!(parse_reset_duration("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_reset_duration("1h2m"), Some(Duration::from_secs(3720)));
+        assert_eq!(parse_reset_duration("soon"), None);
+        // too large for a Duration
+        assert_eq!(parse_reset_duration("1e30"), None);
+        assert_eq!(parse_reset_duration("99999999999999999999h"), None);
+        assert_eq!(parse_reset_duration("-1"), None);
+    }
+
+    pub(crate) fn test_provider() -> Provider {
+        Provider {
+            id: 1,
+            name: "OpenRouter".to_string(),
+            base_url: "https://openrouter.ai/api/v1".to_string(),
+            disabled: false,
+            deprecated: false,
+            api_key_env_var: "OPENROUTER_API_KEY".to_string(),
+            created_dt: 0,
+            model_include: None,
+            model_exclude: None,
+            request_timeout_secs: None,
+            no_models_endpoint: false,
+            extra_headers: None,
+            system_role: None,
+        }
+    }
+
+    #[test]
+    fn test_extra_headers_skip_what_cant_be_sent() {
+        let mut provider = Provider {
+            extra_headers: Some(r#"{"HTTP-Referer": "https://example.com", "X-Title": "shore", "bad header": "x", "X-Count": 3}"#.to_string()),
+            ..test_provider()
+        };
+        let mut headers = provider.extra_headers();
+        headers.sort();
+        assert_eq!(headers, vec![
+            ("HTTP-Referer".to_string(), "https://example.com".to_string()),
+            ("X-Title".to_string(), "shore".to_string()),
+        ]);
+
+        provider.extra_headers = Some("not json".to_string());
+        assert!(provider.extra_headers().is_empty());
+        provider.extra_headers = None;
+        assert!(provider.extra_headers().is_empty());
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("gpt-4o", "gpt-4o"));
+        assert!(!glob_match("gpt-4o", "gpt-4o-mini"));
+        assert!(glob_match("gpt-*", "gpt-4o-mini"));
+        assert!(glob_match("*mini", "gpt-4o-mini"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("o?-mini", "o3-mini"));
+        assert!(!glob_match("o?-mini", "o-mini"));
+        // a `*` has to give characters back for the rest of the pattern to match
+        assert!(glob_match("*-*-mini", "gpt-4o-mini"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(!glob_match("gpt", ""));
+    }
+
+    #[test]
+    fn test_includes_model() {
+        let mut provider = test_provider();
+        assert!(provider.includes_model("anything"));
+
+        // comma separated, with spaces and empty entries ignored
+        provider.model_include = Some("gpt-*, claude-* ,".to_string());
+        assert!(provider.includes_model("gpt-4o"));
+        assert!(provider.includes_model("claude-3-haiku"));
+        assert!(!provider.includes_model("llama-3"));
+
+        // exclusions win over inclusions
+        provider.model_exclude = Some("*-mini".to_string());
+        assert!(provider.includes_model("gpt-4o"));
+        assert!(!provider.includes_model("gpt-4o-mini"));
+
+        provider.model_include = None;
+        assert!(provider.includes_model("llama-3"));
+        assert!(!provider.includes_model("o3-mini"));
+    }
+
+    #[test]
+    fn test_is_ollama() {
+        assert!(!test_provider().is_ollama());
+        let by_name = Provider { name: "My Ollama".to_string(), base_url: "http://gpu-box:8080/v1".to_string(), ..test_provider() };
+        assert!(by_name.is_ollama());
+        let by_port = Provider { name: "Local".to_string(), base_url: "http://localhost:11434/v1".to_string(), ..test_provider() };
+        assert!(by_port.is_ollama());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::database::Database;
+use crate::model::chat::ChatSort;
+use crate::theme::Theme;
+use anyhow::Result;
+use ratatui::layout::Alignment;
+use std::collections::HashMap;
+use tracing::info;
+
+/// User preferences, persisted as key/value rows in the `setting` table
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// How many inference requests may be in flight at once across all models
+    pub max_concurrent_inferences: usize,
+    /// Show when each message was sent above its content
+    pub show_timestamps: bool,
+    /// Sync each provider's models from its models endpoint when the app starts
+    pub refresh_models_on_startup: bool,
+    /// Responses that spent more (estimated) reasoning tokens than this get a warning, 0 turns it off
+    pub reasoning_token_warning_threshold: i64,
+    /// Make links clickable with OSC 8 escape sequences, only for terminals that support them
+    pub hyperlinks: bool,
+    /// Model that generates chat titles as "provider/model", None uses the chat's first model
+    pub title_model: Option<(String, String)>,
+    /// How many chats the history list loads at a time, more are loaded when scrolling past the end
+    pub chat_history_page_size: usize,
+    /// How many prompts (with their responses) a chat loads at a time, older ones are loaded when scrolling up to them
+    pub chat_message_page_size: usize,
+    /// Models a prompt falls back to, in order, when the model it was sent to fails or replies with
+    /// nothing, as "provider/model"
+    pub fallback_models: Vec<(String, String)>,
+    /// How the chat history list is ordered
+    pub chat_sort: ChatSort,
+    /// Leave the prompt in the input after sending it instead of clearing it
+    pub keep_prompt_after_submit: bool,
+    /// When a provider reports it's out of requests or tokens, wait for the reset before sending more
+    pub wait_for_rate_limit_reset: bool,
+    /// After this many failed requests in a row a provider is marked down until it responds again, 0 never does
+    pub provider_failure_threshold: usize,
+    /// Cut the <think> block from the start of responses, the reasoning is still kept on its own
+    pub hide_think_tokens: bool,
+    /// Per model overrides of hide_think_tokens, keyed by (provider, model)
+    pub hide_think_tokens_by_model: HashMap<(String, String), bool>,
+    /// How many candidate responses each model generates per prompt, the others can be cycled through
+    pub response_candidates: usize,
+    /// How often the prompt draft is saved while it changes, in seconds, 0 turns auto-save off
+    pub draft_autosave_interval_secs: u64,
+    /// Record each request's model, message count, latency, token usage and error in the request_log table,
+    /// which keeps the newest 10000
+    pub request_log: bool,
+    /// Where the user's messages sit in the chat view
+    pub user_message_alignment: Alignment,
+    /// Where responses, and their errors, sit in the chat view
+    pub assistant_message_alignment: Alignment,
+    /// Colors used throughout the UI
+    pub theme: Theme,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            max_concurrent_inferences: 4,
+            show_timestamps: false,
+            refresh_models_on_startup: true,
+            reasoning_token_warning_threshold: 8000,
+            hyperlinks: false,
+            title_model: None,
+            chat_history_page_size: 200,
+            chat_message_page_size: 200,
+            fallback_models: Vec::new(),
+            chat_sort: ChatSort::Date,
+            keep_prompt_after_submit: false,
+            wait_for_rate_limit_reset: false,
+            provider_failure_threshold: 3,
+            hide_think_tokens: false,
+            hide_think_tokens_by_model: HashMap::new(),
+            response_candidates: 1,
+            draft_autosave_interval_secs: 10,
+            request_log: false,
+            user_message_alignment: Alignment::Right,
+            assistant_message_alignment: Alignment::Left,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Load settings from the database, falling back to defaults for anything unset
+    pub async fn load(database: &Database) -> Result<Self> {
+        let mut settings = Settings::default();
+        let mut rows = database.get_settings().await?;
+        // the base theme goes first so individual color overrides are applied on top of it
+        rows.sort_by_key(|(key, _)| key != "theme");
+        for (key, value) in rows {
+            // a bad value in the db shouldn't prevent startup, just ignore it
+            if let Err(e) = settings.apply(&key, &value) {
+                info!("Ignoring setting {}: {}", key, e);
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Parse and apply a single setting by key
+    pub fn apply(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "max_concurrent_inferences" => {
+                let max: usize = value.parse()?;
+                if max == 0 {
+                    anyhow::bail!("max_concurrent_inferences must be at least 1");
+                }
+                self.max_concurrent_inferences = max;
+            }
+            "show_timestamps" => {
+                self.show_timestamps = value.parse()?;
+            }
+            "refresh_models_on_startup" => {
+                self.refresh_models_on_startup = value.parse()?;
+            }
+            "reasoning_token_warning_threshold" => {
+                let threshold: i64 = value.parse()?;
+                if threshold < 0 {
+                    anyhow::bail!("reasoning_token_warning_threshold can't be negative");
+                }
+                self.reasoning_token_warning_threshold = threshold;
+            }
+            "hyperlinks" => {
+                self.hyperlinks = value.parse()?;
+            }
+            "title_model" => {
+                // model names can contain slashes themselves, so only the first one separates the provider
+                self.title_model = if value.trim().is_empty() {
+                    None
+                } else {
+                    let (provider, model) = value
+                        .trim()
+                        .split_once('/')
+                        .ok_or_else(|| anyhow::anyhow!("title_model must be given as provider/model"))?;
+                    Some((provider.to_string(), model.to_string()))
+                };
+            }
+            "fallback_models" => {
+                // comma separated, model names can contain slashes themselves so only the first one separates the provider
+                self.fallback_models = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|model| !model.is_empty())
+                    .map(|model| {
+                        model
+                            .split_once('/')
+                            .map(|(provider, model)| (provider.to_string(), model.to_string()))
+                            .ok_or_else(|| anyhow::anyhow!("fallback_models must be given as provider/model, provider/model, ..."))
+                    })
+                    .collect::<Result<_>>()?;
+            }
+            "chat_history_page_size" => {
+                let page_size: usize = value.parse()?;
+                if page_size == 0 {
+                    anyhow::bail!("chat_history_page_size must be at least 1");
+                }
+                self.chat_history_page_size = page_size;
+            }
+            "chat_message_page_size" => {
+                let page_size: usize = value.parse()?;
+                if page_size == 0 {
+                    anyhow::bail!("chat_message_page_size must be at least 1");
+                }
+                self.chat_message_page_size = page_size;
+            }
+            "chat_sort" => {
+                self.chat_sort = ChatSort::from_setting(value.trim()).map_err(anyhow::Error::msg)?;
+            }
+            "keep_prompt_after_submit" => {
+                self.keep_prompt_after_submit = value.parse()?;
+            }
+            "wait_for_rate_limit_reset" => {
+                self.wait_for_rate_limit_reset = value.parse()?;
+            }
+            "provider_failure_threshold" => {
+                self.provider_failure_threshold = value.parse()?;
+            }
+            "hide_think_tokens" => {
+                self.hide_think_tokens = value.parse()?;
+            }
+            _ if key.starts_with("hide_think_tokens.") => {
+                let (provider, model) = key["hide_think_tokens.".len()..]
+                    .split_once('/')
+                    .ok_or_else(|| anyhow::anyhow!("Per model settings must be given as hide_think_tokens.provider/model"))?;
+                self.hide_think_tokens_by_model
+                    .insert((provider.to_string(), model.to_string()), value.parse()?);
+            }
+            "response_candidates" => {
+                let candidates: usize = value.parse()?;
+                if candidates == 0 {
+                    anyhow::bail!("response_candidates must be at least 1");
+                }
+                self.response_candidates = candidates;
+            }
+            "draft_autosave_interval_secs" => {
+                self.draft_autosave_interval_secs = value.parse()?;
+            }
+            "request_log" => {
+                self.request_log = value.parse()?;
+            }
+            "user_message_alignment" => {
+                self.user_message_alignment = parse_alignment(key, value)?;
+            }
+            "assistant_message_alignment" => {
+                self.assistant_message_alignment = parse_alignment(key, value)?;
+            }
+            "theme" => {
+                self.theme = Theme::named(value.trim())?;
+            }
+            _ if key.starts_with("theme.") => {
+                self.theme.set_color(&key["theme.".len()..], value)?;
+            }
+            _ => anyhow::bail!("Unknown setting: {}", key),
+        }
+        Ok(())
+    }
+
+    /// Whether <think> blocks are cut from a model's responses, its own setting wins over the global one
+    pub fn hides_think_tokens(&self, provider: &str, model: &str) -> bool {
+        self.hide_think_tokens_by_model
+            .get(&(provider.to_string(), model.to_string()))
+            .copied()
+            .unwrap_or(self.hide_think_tokens)
+    }
+}
+
+fn parse_alignment(key: &str, value: &str) -> Result<Alignment> {
+    match value.trim() {
+        "left" => Ok(Alignment::Left),
+        "center" => Ok(Alignment::Center),
+        "right" => Ok(Alignment::Right),
+        _ => anyhow::bail!("Invalid {}: {}, expected left, center or right", key, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hides_think_tokens() {
+        let mut settings = Settings::default();
+        settings.apply("hide_think_tokens", "true").unwrap();
+        // model names can contain slashes, only the first one separates the provider
+        settings
+            .apply("hide_think_tokens.OpenRouter/deepseek/deepseek-r1", "false")
+            .unwrap();
+
+        assert!(!settings.hides_think_tokens("OpenRouter", "deepseek/deepseek-r1"));
+        assert!(settings.hides_think_tokens("OpenRouter", "qwen/qwq-32b"));
+        assert!(settings.hides_think_tokens("Groq", "deepseek/deepseek-r1"));
+
+        assert!(settings.apply("hide_think_tokens.no-model", "true").is_err());
+        assert!(settings.apply("hide_think_tokens.Groq/qwq", "yes").is_err());
+    }
+
+    #[test]
+    fn test_parse_alignment() {
+        assert_eq!(parse_alignment("user_message_alignment", "left").unwrap(), Alignment::Left);
+        assert_eq!(parse_alignment("user_message_alignment", " center ").unwrap(), Alignment::Center);
+        assert_eq!(parse_alignment("user_message_alignment", "right").unwrap(), Alignment::Right);
+
+        let error = parse_alignment("user_message_alignment", "middle").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid user_message_alignment: middle, expected left, center or right");
+        assert!(parse_alignment("user_message_alignment", "Left").is_err());
+        assert!(parse_alignment("user_message_alignment", "").is_err());
+
+        let mut settings = Settings::default();
+        settings.apply("assistant_message_alignment", "center").unwrap();
+        assert_eq!(settings.assistant_message_alignment, Alignment::Center);
+        // a bad value leaves the setting as it was
+        assert!(settings.apply("assistant_message_alignment", "justify").is_err());
+        assert_eq!(settings.assistant_message_alignment, Alignment::Center);
+    }
+
+    #[test]
+    fn test_fallback_models() {
+        let mut settings = Settings::default();
+        // only the first slash separates the provider, and stray commas are ignored
+        settings
+            .apply("fallback_models", " OpenRouter/deepseek/deepseek-r1, Groq/qwen3 ,, ")
+            .unwrap();
+        assert_eq!(
+            settings.fallback_models,
+            [
+                ("OpenRouter".to_string(), "deepseek/deepseek-r1".to_string()),
+                ("Groq".to_string(), "qwen3".to_string()),
+            ]
+        );
+
+        assert!(settings.apply("fallback_models", "Groq/qwen3, gpt-5").is_err());
+        assert_eq!(settings.fallback_models.len(), 2);
+        settings.apply("fallback_models", "").unwrap();
+        assert!(settings.fallback_models.is_empty());
+    }
+}
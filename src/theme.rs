@@ -0,0 +1,118 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use ratatui::style::Color;
+
+/// Colors for each semantic role in the UI. Set with the `theme` setting (dark or light),
+/// individual colors can then be overridden with `theme.<role>`, e.g. --set theme.code=#d19a66
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// The user's own messages
+    pub user_message: Color,
+    /// Model responses
+    pub assistant_message: Color,
+    /// Level 1 and 2 markdown headings
+    pub heading: Color,
+    /// Level 3 and deeper markdown headings
+    pub subheading: Color,
+    /// Inline code and code blocks
+    pub code: Color,
+    /// Links and bare urls
+    pub link: Color,
+    /// Failed responses and destructive dialogs
+    pub error: Color,
+    /// Interrupted responses, warnings and pending requests
+    pub warning: Color,
+    /// Checked tasks and confirmations
+    pub success: Color,
+    /// The current model and informational dialogs
+    pub accent: Color,
+    /// Timestamps, status text and other secondary information, including queued requests
+    pub muted: Color,
+    /// The spinner of a request that's being answered, set apart from queued ones
+    pub running: Color,
+    /// Background of search matches
+    pub search_highlight: Color,
+    /// Text of search matches
+    pub search_highlight_text: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            user_message: Color::Green,
+            assistant_message: Color::Reset,
+            heading: Color::Cyan,
+            subheading: Color::Blue,
+            code: Color::Yellow,
+            link: Color::Cyan,
+            error: Color::Red,
+            warning: Color::Yellow,
+            success: Color::Green,
+            accent: Color::Cyan,
+            muted: Color::DarkGray,
+            running: Color::Gray,
+            search_highlight: Color::Yellow,
+            search_highlight_text: Color::Black,
+        }
+    }
+
+    /// For terminals with a light background, where yellow and cyan text is hard to read
+    pub fn light() -> Self {
+        Self {
+            user_message: Color::Green,
+            assistant_message: Color::Reset,
+            heading: Color::Blue,
+            subheading: Color::Magenta,
+            code: Color::Indexed(130),
+            link: Color::Blue,
+            error: Color::Red,
+            warning: Color::Indexed(130),
+            success: Color::Green,
+            accent: Color::Blue,
+            muted: Color::Gray,
+            running: Color::DarkGray,
+            search_highlight: Color::Yellow,
+            search_highlight_text: Color::Black,
+        }
+    }
+
+    pub fn named(name: &str) -> Result<Self> {
+        match name {
+            "dark" => Ok(Self::dark()),
+            "light" => Ok(Self::light()),
+            _ => anyhow::bail!("Unknown theme {}, expected dark or light", name),
+        }
+    }
+
+    /// Override one role's color. Colors can be names (red, lightblue), hex (#rrggbb) or 256 color indices
+    pub fn set_color(&mut self, role: &str, value: &str) -> Result<()> {
+        let color = Color::from_str(value.trim())
+            .map_err(|_| anyhow::anyhow!("Invalid color: {}", value))?;
+        let slot = match role {
+            "user_message" => &mut self.user_message,
+            "assistant_message" => &mut self.assistant_message,
+            "heading" => &mut self.heading,
+            "subheading" => &mut self.subheading,
+            "code" => &mut self.code,
+            "link" => &mut self.link,
+            "error" => &mut self.error,
+            "warning" => &mut self.warning,
+            "success" => &mut self.success,
+            "accent" => &mut self.accent,
+            "muted" => &mut self.muted,
+            "running" => &mut self.running,
+            "search_highlight" => &mut self.search_highlight,
+            "search_highlight_text" => &mut self.search_highlight_text,
+            _ => anyhow::bail!("Unknown theme color: {}", role),
+        };
+        *slot = color;
+        Ok(())
+    }
+}
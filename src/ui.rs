@@ -1,17 +1,28 @@
 use crate::{
-    app::{App, AppState},
+    app::{App, AppState, SystemPromptHeader, editor_state_to_string},
     markdown::parse_markdown,
-    model::chat::ChatRole,
+    model::chat::{ChatMessage, ChatRole, ChatSort},
+    theme::Theme,
 };
-use edtui::{EditorState, EditorTheme, EditorView};
+use edtui::{EditorMode, EditorState, EditorTheme, EditorView};
+use unicode_width::UnicodeWidthChar;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table},
+    widgets::{Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, Wrap},
 };
 
+/// Below this width the title bar leaves out the chat's relative time so the carousel isn't crowded
+const TITLE_BAR_TIME_MIN_WIDTH: u16 = 100;
+
+/// Below this width the title bar drops the title and carousel for a single "model N/total" line
+const TITLE_BAR_COMPACT_MAX_WIDTH: u16 = 60;
+
+/// How many lines of a collapsed message are still shown
+const COLLAPSED_PREVIEW_LINES: usize = 3;
+
 /// Calculate the height needed for a textarea accounting for line wrapping
 fn calculate_textarea_height(textarea: &EditorState, available_width: u16) -> u16 {
     if available_width <= 2 {
@@ -39,7 +50,7 @@ fn calculate_textarea_height(textarea: &EditorState, available_width: u16) -> u1
         } else {
             // Calculate how many rows this line occupies when wrapped
             // Using ceiling division: (line_len + inner_width - 1) / inner_width
-            total_visual_lines += (line_len + inner_width - 1) / inner_width;
+            total_visual_lines += line_len.div_ceil(inner_width);
         }
     }
     
@@ -198,6 +209,56 @@ fn wrap_text(text: Text, max_width: usize) -> Text<'static> {
     Text::from(wrapped_lines)
 }
 
+/// Cuts each line to `max_width` columns starting `offset` columns in, for viewing without wrapping.
+/// Columns are display width, so wide characters take two. One cut in half by the left edge shows as a
+/// space, one that doesn't fit on the right is left off
+fn clip_text(text: Text, offset: usize, max_width: usize) -> Text<'static> {
+    let clipped_lines: Vec<Line<'static>> = text
+        .lines
+        .into_iter()
+        .map(|line| {
+            let mut column = 0;
+            let mut used = 0;
+            let mut full = false;
+            let mut spans: Vec<Span<'static>> = Vec::new();
+            for span in line.spans {
+                let mut content = String::new();
+                for ch in span.content.chars() {
+                    let start = column;
+                    column += ch.width().unwrap_or(0);
+                    if start < offset && column <= offset {
+                        continue;
+                    }
+                    let width = column - start;
+                    let visible = column - start.max(offset);
+                    if used + visible > max_width {
+                        full = true;
+                        break;
+                    }
+                    if visible < width {
+                        content.push_str(&" ".repeat(visible));
+                    } else {
+                        content.push(ch);
+                    }
+                    used += visible;
+                }
+                if !content.is_empty() {
+                    spans.push(Span::styled(content, span.style));
+                }
+                if full {
+                    break;
+                }
+            }
+            if spans.is_empty() {
+                spans.push(Span::raw(""));
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    Text::from(clipped_lines)
+}
+
 pub fn ui(f: &mut Frame, app: &mut App) {
     let size = f.area();
 
@@ -246,31 +307,117 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         main_layout[1]
     };
 
+    // a long preview could push the messages off screen, so it gets at most half the height
+    let prompt_preview = app
+        .prompt_preview
+        .then(|| prompt_preview_text(app, content_area.width));
+    let prompt_height = match &prompt_preview {
+        Some(preview) => (preview.lines.len() as u16 + 2).min(content_area.height / 2).max(3),
+        None => calculate_textarea_height(&app.textarea, content_area.width),
+    };
+
     let content_layout = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),
             Constraint::Min(0),
-            Constraint::Length(calculate_textarea_height(&app.textarea, content_area.width)),
+            Constraint::Length(prompt_height),
         ])
         .split(content_area);
 
     render_chat_title(f, app, content_layout[0]);
-    render_chat_content(f, app, content_layout[1]);
-    render_prompt_input(f, app, content_layout[2]);
+    let messages_area = render_system_prompt_header(f, app, content_layout[1]);
+    if app.unified_view {
+        render_unified_chat_content(f, app, messages_area);
+    } else {
+        render_chat_content(f, app, messages_area);
+    }
+    render_prompt_input(f, app, content_layout[2], prompt_preview);
 
     if app.state == AppState::ProviderDialog {
         render_provider_dialog(f, app, size);
     }
 
-    if app.state == AppState::ModelSelection {
+    if matches!(
+        app.state,
+        AppState::ModelSelection | AppState::ProfileNameEdit | AppState::ModelAliasEdit
+    ) {
         render_model_selection_dialog(f, app, size);
     }
 
+    if app.state == AppState::ProfileNameEdit {
+        render_text_input_dialog(f, app, size, "Save Models as Profile", "save");
+    }
+
+    if app.state == AppState::ModelAliasEdit {
+        render_text_input_dialog(f, app, size, "Model Alias (empty to clear)", "save");
+    }
+
+    if app.state == AppState::StopSequencesEdit {
+        render_text_input_dialog(
+            f,
+            app,
+            size,
+            "Stop Sequences (comma separated, \\n for newline)",
+            "save",
+        );
+    }
+
+    if app.state == AppState::ProfilePicker {
+        render_profile_picker_dialog(f, app, size);
+    }
+
+    if matches!(
+        app.state,
+        AppState::PromptLibrary
+            | AppState::PromptLibraryNameEdit
+            | AppState::PromptLibraryContentEdit
+            | AppState::PromptLibraryDeleteConfirmation
+    ) {
+        render_prompt_library_dialog(f, app, size);
+    }
+
+    if app.state == AppState::GlobalSearch {
+        render_global_search_dialog(f, app, size);
+    }
+
+    if app.state == AppState::BookmarkList {
+        render_bookmark_list_dialog(f, app, size);
+    }
+
+    if app.state == AppState::RequestLog {
+        render_request_log_dialog(f, app, size);
+    }
+
+    if app.state == AppState::PromptLibraryNameEdit {
+        render_text_input_dialog(f, app, size, "System Prompt Name", "save");
+    }
+
+    if app.state == AppState::PromptLibraryContentEdit {
+        render_library_prompt_editor(f, app, size);
+    }
+
+    if app.state == AppState::PromptLibraryDeleteConfirmation {
+        render_library_prompt_delete_confirmation_dialog(f, app, size);
+    }
+
     if app.state == AppState::DeleteConfirmation {
         render_delete_confirmation_dialog(f, app, size);
     }
 
+    if app.state == AppState::ClearConfirmation {
+        render_clear_confirmation_dialog(f, app, size);
+    }
+
+    if app.state == AppState::ClearAllChatsConfirmation {
+        let title = format!("Delete every chat in \"{}\"? Type its name", app.database_name());
+        render_text_input_dialog(f, app, size, &title, "delete");
+    }
+
+    if app.state == AppState::QuitConfirmation {
+        render_quit_confirmation_dialog(f, app, size);
+    }
+
     if app.state == AppState::TitleEdit {
         render_title_edit_dialog(f, app, size);
     }
@@ -278,9 +425,23 @@ pub fn ui(f: &mut Frame, app: &mut App) {
     if app.state == AppState::UnavailableModelsError {
         render_unavailable_models_error_dialog(f, app, size);
     }
+
+    if app.state == AppState::ErrorDetail {
+        render_error_detail_dialog(f, app, size);
+    }
+
+    if app.state == AppState::RequestPreview {
+        render_request_preview_dialog(f, app, size);
+    }
+
+    if app.state == AppState::LogViewer {
+        render_log_viewer_dialog(f, app, size);
+    }
 }
 
 fn render_chat_history(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
+    let marked = app.chat_history_visual_range();
     let items: Vec<ListItem> = app
         .chat_history
         .iter()
@@ -294,13 +455,18 @@ fn render_chat_history(f: &mut Frame, app: &App, area: Rect) {
             } else {
                 let title = chat.title.clone().unwrap_or_else(|| "New Chat".to_string());
                 // Highlight search terms if we're searching
-                let base_style = if i == app.chat_history_index {
+                let mut base_style = if i == app.chat_history_index {
                     Style::default().add_modifier(Modifier::BOLD)
+                } else if marked.is_some_and(|(first, last)| (first..=last).contains(&i)) {
+                    Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
                 } else {
                     Style::default()
                 };
+                if chat.archived {
+                    base_style = base_style.fg(theme.muted);
+                }
                 if !app.search_query.is_empty() {
-                    highlight_text(&title, &app.search_query, base_style)
+                    highlight_text(&title, &app.search_query, base_style, &theme)
                 } else {
                     Line::from(Span::styled(title, base_style))
                 }
@@ -310,8 +476,21 @@ fn render_chat_history(f: &mut Frame, app: &App, area: Rect) {
         })
         .collect();
 
+    let mut block = if app.show_archived {
+        Block::default().borders(Borders::ALL).title("Including Archived")
+    } else {
+        Block::default().borders(Borders::ALL)
+    };
+    if app.settings.chat_sort != ChatSort::Date {
+        block = block.title(
+            Line::from(format!("by {}", app.settings.chat_sort)).right_aligned(),
+        );
+    }
+    if let Some((first, last)) = marked {
+        block = block.title_bottom(format!("{} marked, d deletes", last - first + 1));
+    }
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL))
+        .block(block)
         .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
 
     let mut state = ListState::default();
@@ -320,10 +499,29 @@ fn render_chat_history(f: &mut Frame, app: &App, area: Rect) {
     f.render_stateful_widget(list, area, &mut state);
 }
 
+/// "Search (12 results) 3/12" while a query is active, the position follows q/z through the results
+fn search_title(app: &App) -> String {
+    if app.search_query.is_empty() {
+        return "Search".to_string();
+    }
+    match app.chat_history.len() {
+        0 => "Search (no results)".to_string(),
+        1 => "Search (1 result) 1/1".to_string(),
+        count => format!(
+            "Search ({} results) {}/{}",
+            count,
+            (app.chat_history_index + 1).min(count),
+            count
+        ),
+    }
+}
+
 fn render_search_input(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.settings.theme;
+    let title = search_title(app);
     if app.state == AppState::SearchMode {
         // In search mode, show the editable search input
-        let block = Block::default().borders(Borders::ALL).title("Search");
+        let block = Block::default().borders(Borders::ALL).title(title);
         let inner_area = block.inner(area);
         f.render_widget(block, area);
 
@@ -339,36 +537,62 @@ fn render_search_input(f: &mut Frame, app: &mut App, area: Rect) {
     } else {
         // Not in search mode, but showing search results - display the query as text
         let paragraph = Paragraph::new(app.search_query.clone())
-            .block(Block::default().borders(Borders::ALL).title("Search"))
-            .style(Style::default().fg(Color::Yellow));
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .style(Style::default().fg(theme.warning));
         f.render_widget(paragraph, area);
     }
 }
 
+/// Byte ranges in `text` that match `query` case-insensitively, always on char boundaries.
+/// Lowercasing can change a char's byte length (e.g. 'İ'), so matches are found in the lowercased
+/// text and mapped back to the original chars they cover
+fn find_matches(text: &str, query: &str) -> Vec<(usize, usize)> {
+    let query_lower = query.to_lowercase();
+    if query_lower.is_empty() {
+        return Vec::new();
+    }
+
+    // for each byte of the lowercased text, the byte range of the original char it came from
+    let mut text_lower = String::with_capacity(text.len());
+    let mut origins: Vec<(usize, usize)> = Vec::with_capacity(text.len());
+    for (idx, ch) in text.char_indices() {
+        text_lower.extend(ch.to_lowercase());
+        origins.resize(text_lower.len(), (idx, idx + ch.len_utf8()));
+    }
+
+    let mut matches: Vec<(usize, usize)> = Vec::new();
+    for (idx, matched) in text_lower.match_indices(&query_lower) {
+        let start = origins[idx].0;
+        let end = origins[idx + matched.len() - 1].1;
+        // a match can only share an original char with the previous one when lowercasing expanded it
+        if matches.last().is_some_and(|&(_, last_end)| start < last_end) {
+            continue;
+        }
+        matches.push((start, end));
+    }
+    matches
+}
+
 /// Highlight occurrences of search query in text with yellow background
-fn highlight_text(text: &str, query: &str, base_style: Style) -> Line<'static> {
+fn highlight_text(text: &str, query: &str, base_style: Style, theme: &Theme) -> Line<'static> {
     if query.is_empty() {
         return Line::from(Span::styled(text.to_string(), base_style));
     }
 
-    let query_lower = query.to_lowercase();
-    let text_lower = text.to_lowercase();
-
     let mut spans = Vec::new();
     let mut last_end = 0;
 
     // Find all occurrences of the query (case-insensitive)
-    for (idx, _) in text_lower.match_indices(&query_lower) {
+    for (idx, match_end) in find_matches(text, query) {
         // Add the text before the match
         if idx > last_end {
             spans.push(Span::styled(text[last_end..idx].to_string(), base_style));
         }
 
         // Add the matched text with yellow background
-        let match_end = idx + query.len();
         spans.push(Span::styled(
             text[idx..match_end].to_string(),
-            base_style.bg(Color::Yellow).fg(Color::Black),
+            base_style.bg(theme.search_highlight).fg(theme.search_highlight_text),
         ));
 
         last_end = match_end;
@@ -384,6 +608,7 @@ fn highlight_text(text: &str, query: &str, base_style: Style) -> Line<'static> {
 
 /// Build a carousel of model indices with smart windowing
 fn build_model_carousel(app: &App, available_width: usize) -> Vec<Span<'static>> {
+    let theme = app.settings.theme;
     let total_models = app.current_chat_profile.model_ids.len();
     let current_idx = app.current_model_idx;
 
@@ -432,14 +657,30 @@ fn build_model_carousel(app: &App, available_width: usize) -> Vec<Span<'static>>
             .get(&(chat_id, model_id))
             .map(|handle| !handle.is_finished())
             .unwrap_or(false);
+        let is_running = app
+            .current_messages
+            .get(&model_id)
+            .map(|messages| messages.iter().any(|m| app.is_message_running(model_id, m.id)))
+            .unwrap_or(false);
+        let has_error = app
+            .current_messages
+            .get(&model_id)
+            .and_then(|messages| messages.last())
+            .is_some_and(|message| message.error.is_some());
         
-        // Style the index
+        // Style the index, pending requests still waiting on the concurrency limit are dimmed
         let mut style = Style::default();
-        if has_pending {
-            style = style.fg(Color::Yellow);
+        if has_pending && is_running {
+            style = style.fg(theme.warning);
+        } else if has_pending {
+            style = style.fg(theme.muted);
+        } else if has_error {
+            style = style.fg(theme.error);
         }
         if idx == current_idx {
-            style = style.fg(Color::Cyan).add_modifier(Modifier::BOLD);
+            // the current model stays red if it failed so the error isn't hidden by the highlight
+            let color = if has_error && !has_pending { theme.error } else { theme.accent };
+            style = style.fg(color).add_modifier(Modifier::BOLD);
         }
         
         // Format index with padding to match the width of the largest index
@@ -456,6 +697,7 @@ fn build_model_carousel(app: &App, available_width: usize) -> Vec<Span<'static>>
 }
 
 fn render_chat_title(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
     // Get current model info
     let model_id = app
         .current_chat_profile
@@ -463,8 +705,37 @@ fn render_chat_title(f: &mut Frame, app: &App, area: Rect) {
         .get(app.current_model_idx)
         .unwrap_or(&0);
     let model = app.all_models.get(model_id);
-    let model_name: &str = model.map(|m| m.model.as_str()).unwrap_or("?");
-    
+    let model_name: &str = model.map(|m| m.display_name()).unwrap_or("unknown model");
+
+    // flagged when the chat asks for JSON responses, dimmed when this model can't honor it
+    let mut model_spans = Vec::new();
+    if app.current_chat.json_mode {
+        let json_color = if model.is_some_and(|m| m.supports_json_mode) {
+            theme.warning
+        } else {
+            theme.muted
+        };
+        model_spans.push(Span::styled("JSON ", Style::default().fg(json_color)));
+    }
+    model_spans.push(Span::raw(model_name));
+    // old chats keep showing models their provider stopped listing, they just can't answer anymore
+    if model.is_some_and(|m| m.deprecated) {
+        model_spans.push(Span::styled(" (removed)", Style::default().fg(theme.muted)));
+    }
+
+    if area.width < TITLE_BAR_COMPACT_MAX_WIDTH {
+        let position = format!(
+            "model {}/{}: ",
+            app.current_model_idx + 1,
+            app.current_chat_profile.model_ids.len()
+        );
+        model_spans.insert(0, Span::styled(position, Style::default().fg(theme.muted)));
+        let compact_paragraph = Paragraph::new(Line::from(model_spans))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(compact_paragraph, area);
+        return;
+    }
+
     let title_text = if app.title_inference_in_progress_by_chat.contains(&app.current_chat.id) {
         format!("    {}", app.get_spinner_char())
     } else {
@@ -494,7 +765,17 @@ fn render_chat_title(f: &mut Frame, app: &App, area: Rect) {
             .block(Block::default().borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM))
             .alignment(Alignment::Left)
     } else {
-        Paragraph::new(title_text)
+        let mut title_spans = vec![Span::raw(title_text)];
+        if area.width >= TITLE_BAR_TIME_MIN_WIDTH
+            && let Some(last_active) = chat_last_active(app)
+        {
+            let now = chrono::Utc::now().timestamp();
+            title_spans.push(Span::styled(
+                format!(" · {}", format_relative_time(now - last_active)),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        Paragraph::new(Line::from(title_spans))
             .block(Block::default().borders(Borders::LEFT | Borders::TOP | Borders::BOTTOM))
             .alignment(Alignment::Left)
     };
@@ -510,14 +791,265 @@ fn render_chat_title(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(carousel_paragraph, title_layout[1]);
     
     // Render model name (right-aligned)
-    let right_paragraph = Paragraph::new(model_name)
+    let right_paragraph = Paragraph::new(Line::from(model_spans))
         .block(Block::default().borders(Borders::RIGHT | Borders::TOP | Borders::BOTTOM))
         .alignment(Alignment::Right);
     f.render_widget(right_paragraph, title_layout[2]);
 }
 
+/// When the current chat last saw a message, in seconds. Falls back to when it was created, new chats have neither
+fn chat_last_active(app: &App) -> Option<i64> {
+    if app.current_chat.id == 0 {
+        return None;
+    }
+    let last_message_millis = app
+        .current_messages
+        .values()
+        .flatten()
+        .map(|message| message.response_dt.unwrap_or(message.dt))
+        .max();
+    Some(last_message_millis.map_or(app.current_chat.dt, |millis| millis / 1000))
+}
+
+/// Formats a duration in seconds like "2d ago"
+fn format_relative_time(seconds: i64) -> String {
+    match seconds.max(0) {
+        s if s < 60 => "just now".to_string(),
+        s if s < 60 * 60 => format!("{}m ago", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h ago", s / (60 * 60)),
+        s if s < 30 * 24 * 60 * 60 => format!("{}d ago", s / (24 * 60 * 60)),
+        s if s < 365 * 24 * 60 * 60 => format!("{}mo ago", s / (30 * 24 * 60 * 60)),
+        s => format!("{}y ago", s / (365 * 24 * 60 * 60)),
+    }
+}
+
+/// The lines a message takes up in the chat view, and the color it's drawn in
+fn message_text(
+    app: &App,
+    model_id: i64,
+    message: &ChatMessage,
+    max_width: usize,
+) -> (Color, Text<'static>) {
+    let theme = app.settings.theme;
+    let user_alignment = app.settings.user_message_alignment;
+    let assistant_alignment = app.settings.assistant_message_alignment;
+
+    // Determine message styling and content
+    let (color, content, alignment) = if let Some(error) = message.error.as_deref() {
+        (theme.error, error, assistant_alignment)
+    } else if message.incomplete {
+        (
+            theme.warning,
+            "[Interrupted before the response finished]",
+            assistant_alignment,
+        )
+    } else {
+        if message.is_aside() {
+            (
+                theme.muted,
+                message.content.as_deref().unwrap_or("[No content]"),
+                Alignment::Center,
+            )
+        } else if message.chat_role == ChatRole::User {
+            (
+                theme.user_message,
+                message.content.as_deref().unwrap_or("[No content]"),
+                user_alignment,
+            )
+        } else {
+            (
+                theme.assistant_message,
+                message.content.as_deref().unwrap_or("[No content]"),
+                assistant_alignment,
+            )
+        }
+    };
+
+    // Parse and wrap text, unless the message is toggled to show its markdown source
+    let mut text = if app.raw_message_ids.contains(&message.id) {
+        Text::from(content.to_string())
+    } else if message.chat_role == ChatRole::Assistant
+        && message.error.is_none()
+        && let Some(pretty) = pretty_json_object(content)
+    {
+        Text::from(pretty)
+    } else {
+        parse_markdown(content, &theme)
+    };
+
+    if !app.search_query.is_empty() {
+        text = highlight_text_in_parsed(&text, &app.search_query, &theme);
+    }
+
+    let mut wrapped_text = if app.wrap_messages {
+        wrap_text(text, max_width)
+    } else {
+        let offset = app
+            .horizontal_offset_by_model
+            .get(&model_id)
+            .copied()
+            .unwrap_or(0);
+        clip_text(text, offset, max_width)
+    };
+    // only collapse when it hides more than the indicator line it adds
+    if app.collapsed_message_ids.contains(&message.id)
+        && wrapped_text.lines.len() > COLLAPSED_PREVIEW_LINES + 1
+    {
+        let hidden = wrapped_text.lines.split_off(COLLAPSED_PREVIEW_LINES);
+        let mut indicator = format!("[+{} lines]", hidden.len());
+        if !app.search_query.is_empty() {
+            let hidden_text: String = hidden
+                .iter()
+                .flat_map(|line| line.spans.iter().map(|span| span.content.as_ref()))
+                .collect();
+            let hidden_matches = find_matches(&hidden_text, &app.search_query).len();
+            if hidden_matches > 0 {
+                indicator = format!("[+{} lines, {} matches]", hidden.len(), hidden_matches);
+            }
+        }
+        wrapped_text.lines.push(
+            Line::from(indicator)
+                .style(Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC)),
+        );
+    }
+    if app.continuing_message_ids.contains(&message.id) {
+        wrapped_text.lines.push(
+            Line::from("[Continuing...]")
+                .style(Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC)),
+        );
+    } else if message.was_cut_off() {
+        wrapped_text.lines.push(
+            Line::from("[Cut off at the token limit, select it and press a to continue]")
+                .style(Style::default().fg(theme.warning)),
+        );
+    }
+    let reasoning_threshold = app.settings.reasoning_token_warning_threshold;
+    if let Some(reasoning_tokens) = message.reasoning_tokens
+        && reasoning_threshold > 0
+        && reasoning_tokens > reasoning_threshold
+    {
+        wrapped_text.lines.insert(
+            0,
+            // an estimate is marked as one, a count the provider reported is shown as is
+            Line::from(format!(
+                "⚠ {}{} reasoning tokens",
+                if message.reasoning_tokens_estimated { "~" } else { "" },
+                reasoning_tokens
+            ))
+                .style(Style::default().fg(theme.warning)),
+        );
+    }
+    if app.settings.show_timestamps {
+        wrapped_text.lines.insert(
+            0,
+            Line::from(format_message_timestamp(message))
+                .style(Style::default().fg(theme.muted)),
+        );
+    }
+    // the chat's model failed and a model from the fallback chain answered in its place
+    if let Some(answered_by) = message.answered_by_model_id {
+        let answered_by = match app.all_models.get(&answered_by) {
+            Some(model) => model.display_name().to_string(),
+            None => format!("model {}", answered_by),
+        };
+        wrapped_text.lines.insert(
+            0,
+            Line::from(format!("↪ Answered by fallback {}", answered_by))
+                .style(Style::default().fg(theme.warning)),
+        );
+    }
+    if let Some((candidates, shown)) = app.response_candidates.get(&message.id) {
+        wrapped_text.lines.insert(
+            0,
+            Line::from(format!(
+                "Candidate {} of {}, < and > to switch",
+                shown + 1,
+                candidates.len()
+            ))
+            .style(Style::default().fg(theme.accent)),
+        );
+    }
+    if message.is_aside() {
+        for line in &mut wrapped_text.lines {
+            *line = std::mem::take(line)
+                .patch_style(Style::default().add_modifier(Modifier::ITALIC));
+        }
+        let label_style = Style::default().fg(theme.accent).add_modifier(Modifier::BOLD);
+        wrapped_text.lines.insert(0, Line::from("Aside").style(label_style));
+    }
+    if app.bookmarked_message_ids.contains(&message.id) {
+        wrapped_text
+            .lines
+            .insert(0, Line::from("★ Bookmarked").style(Style::default().fg(theme.accent)));
+    }
+    wrapped_text.lines.push(Line::from(""));
+
+    for line in &mut wrapped_text.lines {
+        line.alignment = Some(alignment);
+    }
+
+    (color, wrapped_text)
+}
+
+/// How many chunks a message is split into, based on the chat area size from the last render
+pub fn message_chunk_count(app: &App, model_id: i64, message: &ChatMessage) -> usize {
+    let (max_width, available_height) = app.chat_viewport;
+    if available_height == 0 {
+        return 1;
+    }
+    let (_, text) = message_text(app, model_id, message, max_width);
+    text.lines.len().div_ceil(available_height).max(1)
+}
+
+/// Pin the chat's custom system prompt above the messages, just its first line while collapsed.
+/// It's drawn apart from the message list so it can't be navigated to or yanked. Returns the area
+/// left for the messages
+fn render_system_prompt_header(f: &mut Frame, app: &App, area: Rect) -> Rect {
+    let Some(system_prompt) = app.current_chat.system_prompt.as_deref() else {
+        return area;
+    };
+    let theme = app.settings.theme;
+    let max_width = (area.width as usize).saturating_sub(2);
+    let lines = match app.system_prompt_header {
+        SystemPromptHeader::Hidden => return area,
+        SystemPromptHeader::Collapsed => {
+            let mut prompt_lines = system_prompt.trim().lines();
+            let mut first_line = prompt_lines.next().unwrap_or_default().to_string();
+            if prompt_lines.next().is_some() {
+                first_line.push_str(" …");
+            }
+            vec![Line::from(first_line)]
+        }
+        SystemPromptHeader::Expanded => {
+            wrap_text(Text::from(system_prompt.to_string()), max_width).lines
+        }
+    };
+
+    // the conversation keeps most of the space however long the prompt is
+    let max_lines = (area.height / 3).max(1) as usize;
+    let height = lines.len().min(max_lines) as u16 + 2;
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(height), Constraint::Min(0)])
+        .split(area);
+
+    let paragraph = Paragraph::new(lines)
+        .style(Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.muted))
+                .title("System prompt"),
+        );
+    f.render_widget(paragraph, layout[0]);
+    layout[1]
+}
+
 fn render_chat_content(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.settings.theme;
     let available_height = area.height.saturating_sub(2) as usize;
+    let max_width = (area.width as usize).saturating_sub(4);
+    app.chat_viewport = (max_width, available_height);
 
     // Get the current model_id
     let current_model_id = app
@@ -565,46 +1097,15 @@ fn render_chat_content(f: &mut Frame, app: &mut App, area: Rect) {
         .get(&model_id)
         .copied()
         .unwrap_or(None);
+    app.current_selected_message_index = None;
+    app.current_selected_chunk_text = None;
 
-    for msg_idx in current_msg_idx..messages.len() {
-        let message = &messages[msg_idx];
-
-        // Determine message styling and content
-        let (color, content, alignment) = if let Some(error) = message.error.as_deref() {
-            (Color::Red, error, Alignment::Left)
-        } else {
-            if message.chat_role == ChatRole::User {
-                (
-                    Color::Green,
-                    message.content.as_deref().unwrap_or("[No content]"),
-                    Alignment::Right,
-                )
-            } else {
-                (
-                    Color::default(),
-                    message.content.as_deref().unwrap_or("[No content]"),
-                    Alignment::Left,
-                )
-            }
-        };
-
-        // Parse and wrap text
-        let mut text = parse_markdown(&content);
-
-        if !app.search_query.is_empty() {
-            text = highlight_text_in_parsed(&text, &app.search_query);
-        }
-
-        let mut wrapped_text = wrap_text(text, (area.width as usize).saturating_sub(4));
-        wrapped_text.lines.push(Line::from(""));
-
-        for line in &mut wrapped_text.lines {
-            line.alignment = Some(alignment);
-        }
+    for (msg_idx, message) in messages.iter().enumerate().skip(current_msg_idx) {
+        let (color, wrapped_text) = message_text(app, model_id, message, max_width);
 
         // Calculate chunks for this message
         let total_lines = wrapped_text.lines.len();
-        let num_chunks = (total_lines + available_height - 1) / available_height; // ceiling division
+        let num_chunks = total_lines.div_ceil(available_height); // ceiling division
 
         // Store chunk count for current message
         if msg_idx == current_msg_idx {
@@ -647,16 +1148,23 @@ fn render_chat_content(f: &mut Frame, app: &mut App, area: Rect) {
             }
 
             // Add this chunk to visible items
-            let chunk_text = Text::from(chunk_lines);
-            let list_item = ListItem::new(chunk_text).style(Style::default().fg(color));
-            visible_items.push(list_item);
-
             if let Some(selection_idx) = current_item_selection
-                && selection_idx == visible_items.len() as i64 - 1
+                && selection_idx == visible_items.len() as i64
             {
                 app.current_selected_message_index = Some(msg_idx);
+                // keep the displayed text of the chunk around for chunk yanking
+                let chunk_plain_text = chunk_lines
+                    .iter()
+                    .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect::<String>())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                app.current_selected_chunk_text = Some(chunk_plain_text.trim_end().to_string());
             }
 
+            let chunk_text = Text::from(chunk_lines);
+            let list_item = ListItem::new(chunk_text).style(Style::default().fg(color));
+            visible_items.push(list_item);
+
             lines_used += chunk_line_count;
 
             if lines_used >= available_height {
@@ -664,16 +1172,40 @@ fn render_chat_content(f: &mut Frame, app: &mut App, area: Rect) {
             }
         }
 
+        // Note when older messages were left out of the request to fit the context window
+        if message.chat_role == ChatRole::User
+            && let Some(truncated_count) = app.context_truncated_count(model_id, message.id)
+            && lines_used < available_height
+        {
+            let truncated_line = Line::from(format!(
+                "{} earlier message{} omitted to fit context window",
+                truncated_count,
+                if truncated_count == 1 { "" } else { "s" }
+            ))
+            .alignment(app.settings.user_message_alignment);
+            let list_item = ListItem::new(Text::from(vec![truncated_line]))
+                .style(Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC));
+            visible_items.push(list_item);
+            lines_used += 1;
+        }
+
         // Add loading indicator if applicable
-        if message.chat_role == ChatRole::User && app.is_message_loading(model_id, message.id) {
-            if lines_used < available_height {
-                let spinner_char = app.get_spinner_char().to_string();
-                let loading_line = Line::from(spinner_char).alignment(Alignment::Center);
-                let loading_text = Text::from(vec![loading_line]);
-                let list_item = ListItem::new(loading_text).style(Style::default().fg(Color::Gray));
-                visible_items.push(list_item);
-                lines_used += 1;
-            }
+        if message.chat_role == ChatRole::User
+            && app.is_message_loading(model_id, message.id)
+            && lines_used < available_height
+        {
+            // requests waiting on the concurrency limit get a static marker instead of the spinner
+            let loading_str = app.loading_status(model_id, message.id);
+            let loading_color = if app.is_message_running(model_id, message.id) {
+                theme.running
+            } else {
+                theme.muted
+            };
+            let loading_line = Line::from(loading_str).alignment(Alignment::Center);
+            let loading_text = Text::from(vec![loading_line]);
+            let list_item = ListItem::new(loading_text).style(Style::default().fg(loading_color));
+            visible_items.push(list_item);
+            lines_used += 1;
         }
 
         // Stop if we've filled the screen and have current message's chunk count
@@ -682,14 +1214,6 @@ fn render_chat_content(f: &mut Frame, app: &mut App, area: Rect) {
         }
     }
 
-    // Update current message's chunk count
-    if let Some(chunks_len) = current_message_chunks_count {
-        app.current_message_chunks_length
-            .insert(model_id, chunks_len);
-    } else {
-        app.current_message_chunks_length.insert(model_id, 1);
-    }
-
     // Display current message index in title
     let title = format!("{}/{}", current_msg_idx + 1, messages.len());
 
@@ -716,11 +1240,165 @@ fn render_chat_content(f: &mut Frame, app: &mut App, area: Rect) {
     f.render_stateful_widget(list, area, &mut state);
 }
 
-fn render_prompt_input(f: &mut Frame, app: &mut App, area: Rect) {
-    let block = Block::default().borders(Borders::ALL);
+/// All models' responses interleaved under each prompt, each labeled with its model
+fn render_unified_chat_content(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.settings.theme;
+    let available_height = area.height.saturating_sub(2) as usize;
+    let max_width = (area.width as usize).saturating_sub(4);
+    // selections index into a single model's messages, which this view doesn't show
+    app.current_selected_message_index = None;
+    app.current_selected_chunk_text = None;
+
+    let messages = app.unified_messages();
+    if messages.is_empty() {
+        let paragraph = Paragraph::new("No messages in this chat")
+            .block(Block::default().borders(Borders::ALL))
+            .alignment(Alignment::Center);
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let model_ids = &app.current_chat_profile.model_ids;
+    let model_name = |model_id: i64| {
+        app.all_models
+            .get(&model_id)
+            .map(|model| model.display_name().to_string())
+            .unwrap_or_else(|| "Unknown Model".to_string())
+    };
+    // the carousel number ties a response to its model in the other view, the provider tells apart
+    // the same model served by several of them
+    let model_label = |model_id: i64| {
+        let mut label = Line::default();
+        if let Some(idx) = model_ids.iter().position(|&id| id == model_id) {
+            label.push_span(Span::styled(
+                format!("{} ", idx + 1),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        label.push_span(Span::styled(
+            model_name(model_id),
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+        ));
+        if let Some(provider_name) = app
+            .all_models
+            .get(&model_id)
+            .and_then(|model| app.provider_names.get(&model.provider_id))
+        {
+            label.push_span(Span::styled(
+                format!(" · {}", provider_name),
+                Style::default().fg(theme.muted),
+            ));
+        }
+        label
+    };
+
+    let mut lines: Vec<Line<'static>> = Vec::new();
+    for message in &messages {
+        let model_id = message
+            .model_id
+            .or_else(|| model_ids.first().copied())
+            .unwrap_or_default();
+        if message.chat_role != ChatRole::User {
+            lines.push(model_label(model_id));
+        }
+        let (color, text) = message_text(app, model_id, message, max_width);
+        lines.extend(
+            text.lines
+                .into_iter()
+                .map(|line| line.patch_style(Style::default().fg(color))),
+        );
+
+        if message.chat_role == ChatRole::User {
+            for &loading_model_id in model_ids {
+                if app.is_message_loading(loading_model_id, message.id) {
+                    let status = app.loading_status(loading_model_id, message.id);
+                    lines.push(
+                        Line::from(format!("{} {}", status, model_name(loading_model_id)))
+                            .style(Style::default().fg(theme.muted)),
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(from_end) = app.unified_scroll_from_end.take() {
+        app.unified_scroll = lines.len().saturating_sub(from_end);
+    }
+    app.unified_line_count = lines.len();
+    let max_scroll = lines.len().saturating_sub(available_height);
+    app.unified_scroll = app.unified_scroll.min(max_scroll);
+    let title = format!("All models {}/{}", app.unified_scroll + 1, max_scroll + 1);
+
+    let paragraph = Paragraph::new(lines)
+        .scroll((app.unified_scroll.min(u16::MAX as usize) as u16, 0))
+        .block(
+            Block::default()
+                .title(title.clone())
+                .title_bottom(title)
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL),
+        );
+    f.render_widget(paragraph, area);
+}
+
+/// The prompt editor's mode as a colored badge, since so much of what a key does depends on it
+fn editor_mode_badge(mode: EditorMode, theme: &Theme) -> Span<'static> {
+    let (label, color) = match mode {
+        EditorMode::Normal => ("NORMAL", theme.accent),
+        EditorMode::Insert => ("INSERT", theme.success),
+        EditorMode::Visual => ("VISUAL", theme.warning),
+        EditorMode::Search => ("SEARCH", theme.warning),
+    };
+    Span::styled(
+        format!(" {} ", label),
+        Style::default()
+            .fg(color)
+            .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+    )
+}
+
+/// The prompt rendered the way it will show once sent, for the Ctrl+o preview
+fn prompt_preview_text(app: &App, available_width: u16) -> Text<'static> {
+    let prompt = editor_state_to_string(&app.textarea);
+    let max_width = available_width.saturating_sub(2) as usize;
+    wrap_text(parse_markdown(&prompt, &app.settings.theme), max_width)
+}
+
+fn render_prompt_input(f: &mut Frame, app: &mut App, area: Rect, preview: Option<Text<'static>>) {
+    let theme = app.settings.theme;
+    let mut block = Block::default().borders(Borders::ALL);
+    block = if preview.is_some() {
+        block.title(Span::styled(
+            " PREVIEW ",
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+        ))
+    } else {
+        block.title(editor_mode_badge(app.textarea.mode, &theme))
+    };
+    if app.read_only {
+        block = block.title(Span::styled(
+            " READ ONLY ",
+            Style::default()
+                .fg(theme.error)
+                .add_modifier(Modifier::REVERSED | Modifier::BOLD),
+        ));
+    }
+    if let Some(status) = app.get_status_line() {
+        block = block.title_bottom(
+            Line::from(Span::styled(format!(" {} ", status), Style::default().fg(theme.muted)))
+                .right_aligned(),
+        );
+    }
     let inner_area = block.inner(area);
     f.render_widget(block, area);
 
+    if let Some(preview) = preview {
+        f.render_widget(Paragraph::new(preview), inner_area);
+        return;
+    }
+
     let theme = EditorTheme {
         status_line: None,
         base: Style::default().bg(Color::Reset),
@@ -733,26 +1411,25 @@ fn render_prompt_input(f: &mut Frame, app: &mut App, area: Rect) {
 }
 
 /// Apply search highlighting to already-parsed markdown text
-fn highlight_text_in_parsed<'a>(text: &Text<'a>, query: &str) -> Text<'a> {
+fn highlight_text_in_parsed<'a>(text: &Text<'a>, query: &str, theme: &Theme) -> Text<'a> {
     if query.is_empty() {
         return text.clone();
     }
 
-    let query_lower = query.to_lowercase();
     let mut highlighted_lines = Vec::new();
 
     for line in &text.lines {
         let mut new_spans = Vec::new();
 
         for span in &line.spans {
-            let content_lower = span.content.to_lowercase();
+            let matches = find_matches(&span.content, query);
 
-            if content_lower.contains(&query_lower) {
+            if !matches.is_empty() {
                 // This span contains the search query, we need to split it
                 let mut last_end = 0;
                 let content_str = span.content.as_ref();
 
-                for (idx, _) in content_lower.match_indices(&query_lower) {
+                for (idx, match_end) in matches {
                     // Add text before match
                     if idx > last_end {
                         new_spans.push(Span::styled(
@@ -762,10 +1439,9 @@ fn highlight_text_in_parsed<'a>(text: &Text<'a>, query: &str) -> Text<'a> {
                     }
 
                     // Add matched text with yellow background
-                    let match_end = idx + query.len();
                     new_spans.push(Span::styled(
                         content_str[idx..match_end].to_string(),
-                        span.style.bg(Color::Yellow).fg(Color::Black),
+                        span.style.bg(theme.search_highlight).fg(theme.search_highlight_text),
                     ));
 
                     last_end = match_end;
@@ -793,6 +1469,7 @@ fn highlight_text_in_parsed<'a>(text: &Text<'a>, query: &str) -> Text<'a> {
 }
 
 fn render_provider_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
     let popup_area = centered_rect(70, 60, area);
     f.render_widget(Clear, popup_area);
 
@@ -809,9 +1486,9 @@ fn render_provider_dialog(f: &mut Frame, app: &App, area: Rect) {
         .iter()
         .map(|(name, env_var, is_set)| {
             let status = if *is_set {
-                Cell::from(Span::styled("Yes", Style::default().fg(Color::Green)))
+                Cell::from(Span::styled("Yes", Style::default().fg(theme.success)))
             } else {
-                Cell::from(Span::styled("No", Style::default().fg(Color::Red)))
+                Cell::from(Span::styled("No", Style::default().fg(theme.error)))
             };
             Row::new(vec![
                 Cell::from(name.as_str()),
@@ -863,7 +1540,7 @@ fn render_provider_dialog(f: &mut Frame, app: &App, area: Rect) {
         Block::default()
             .title("Model Providers")
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Yellow)),
+            .border_style(Style::default().fg(theme.warning)),
     )
     .column_spacing(1);
 
@@ -874,11 +1551,11 @@ fn render_provider_dialog(f: &mut Frame, app: &App, area: Rect) {
         let warning = Paragraph::new(
             "⚠️  Prompting will be disabled until at least one provider API key is set!",
         )
-        .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+        .style(Style::default().fg(theme.error).add_modifier(Modifier::BOLD))
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Red)),
+                .border_style(Style::default().fg(theme.error)),
         )
         .alignment(Alignment::Center);
 
@@ -888,21 +1565,21 @@ fn render_provider_dialog(f: &mut Frame, app: &App, area: Rect) {
 
 fn render_model_selection_dialog(f: &mut Frame, app: &App, area: Rect) {
     if let Some(modal) = &app.model_select_modal {
-        modal.render(f, area);
+        modal.render(f, area, &app.settings.theme);
     }
 }
 
 fn render_delete_confirmation_dialog(f: &mut Frame, app: &App, area: Rect) {
-    let popup_area = centered_rect(50, 25, area);
-    f.render_widget(Clear, popup_area);
-
-    let layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Min(3),    // For the message
-            Constraint::Length(3), // For instructions
-        ])
-        .split(popup_area);
+    if let Some((first, last)) = app.chat_history_visual_range()
+        && last > first
+    {
+        let message = format!(
+            "Are you sure you want to delete these {} chats?",
+            last - first + 1
+        );
+        render_confirmation_dialog(f, area, "Delete Chats", message, &app.settings.theme);
+        return;
+    }
 
     // Get the chat title for display
     let chat_title = app
@@ -916,25 +1593,91 @@ fn render_delete_confirmation_dialog(f: &mut Frame, app: &App, area: Rect) {
         chat_title
     );
 
-    let message_paragraph = Paragraph::new(message)
-        .block(
-            Block::default()
-                .title("Delete Chat")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Red)),
-        )
-        .alignment(Alignment::Center)
-        .style(Style::default().fg(Color::Red));
+    render_confirmation_dialog(f, area, "Delete Chat", message, &app.settings.theme);
+}
 
-    f.render_widget(message_paragraph, layout[0]);
+fn render_library_prompt_delete_confirmation_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let name = app
+        .library_prompts
+        .get(app.prompt_library_index)
+        .map(|prompt| prompt.name.as_str())
+        .unwrap_or_default();
+    let message = format!("Delete this system prompt?\n\n\"{}\"", name);
 
-    // Instructions
-    let instructions = vec![Line::from(vec![
-        Span::styled("Y/Enter", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(" to confirm, "),
-        Span::styled("N/Esc", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(" to cancel"),
-    ])];
+    render_confirmation_dialog(f, area, "Delete System Prompt", message, &app.settings.theme);
+}
+
+fn render_clear_confirmation_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let chat_title = app
+        .current_chat
+        .title
+        .clone()
+        .unwrap_or_else(|| "New Chat".to_string());
+
+    let message = format!(
+        "Clear all messages in this chat?\n\n\"{}\"\n\nThe chat and its models are kept.",
+        chat_title
+    );
+
+    render_confirmation_dialog(f, area, "Clear Chat", message, &app.settings.theme);
+}
+
+fn render_quit_confirmation_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let running = app.inference_in_progress_by_message_and_model.len();
+    let mut warnings = Vec::new();
+    if app.has_unsaved_draft() {
+        warnings.push("Your prompt draft will be lost.".to_string());
+    }
+    if running > 0 {
+        warnings.push(format!(
+            "{} response{} still being generated and will be lost.",
+            running,
+            if running == 1 { " is" } else { "s are" }
+        ));
+    }
+
+    let message = format!("Quit anyway?\n\n{}", warnings.join("\n"));
+
+    render_confirmation_dialog(f, area, "Quit", message, &app.settings.theme);
+}
+
+fn render_confirmation_dialog(
+    f: &mut Frame,
+    area: Rect,
+    title: &str,
+    message: String,
+    theme: &Theme,
+) {
+    let popup_area = centered_rect(50, 25, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // For the message
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    let message_paragraph = Paragraph::new(message)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.error)),
+        )
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(theme.error));
+
+    f.render_widget(message_paragraph, layout[0]);
+
+    // Instructions
+    let instructions = vec![Line::from(vec![
+        Span::styled("Y/Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to confirm, "),
+        Span::styled("N/Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to cancel"),
+    ])];
 
     let instructions_paragraph = Paragraph::new(instructions)
         .block(Block::default().borders(Borders::ALL))
@@ -944,6 +1687,11 @@ fn render_delete_confirmation_dialog(f: &mut Frame, app: &App, area: Rect) {
 }
 
 fn render_title_edit_dialog(f: &mut Frame, app: &mut App, area: Rect) {
+    render_text_input_dialog(f, app, area, "Edit Chat Title", "save");
+}
+
+/// A one line text input, `action` is what Enter does with the text
+fn render_text_input_dialog(f: &mut Frame, app: &mut App, area: Rect, title: &str, action: &str) {
     let popup_area = centered_rect(60, 30, area);
     f.render_widget(Clear, popup_area);
 
@@ -958,7 +1706,7 @@ fn render_title_edit_dialog(f: &mut Frame, app: &mut App, area: Rect) {
     // Render the title input area
     let block = Block::default()
         .borders(Borders::ALL)
-        .title("Edit Chat Title");
+        .title(title);
     let inner_area = block.inner(layout[0]);
     f.render_widget(block, layout[0]);
 
@@ -974,7 +1722,7 @@ fn render_title_edit_dialog(f: &mut Frame, app: &mut App, area: Rect) {
     // Instructions
     let instructions = vec![Line::from(vec![
         Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
-        Span::raw(" to save, "),
+        Span::raw(format!(" to {}, ", action)),
         Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
         Span::raw(" to cancel"),
     ])];
@@ -986,7 +1734,371 @@ fn render_title_edit_dialog(f: &mut Frame, app: &mut App, area: Rect) {
     f.render_widget(instructions_paragraph, layout[1]);
 }
 
+fn render_profile_picker_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
+    let popup_area = centered_rect(60, 50, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // For the profile list
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Model Profiles")
+        .border_style(Style::default().fg(theme.warning));
+
+    if app.named_profiles.is_empty() {
+        let paragraph = Paragraph::new("No saved profiles.\nPress S in the model selection dialog to save one.")
+            .block(block)
+            .alignment(Alignment::Center);
+        f.render_widget(paragraph, layout[0]);
+    } else {
+        let items: Vec<ListItem> = app
+            .named_profiles
+            .iter()
+            .enumerate()
+            .map(|(idx, profile)| {
+                let model_names: Vec<&str> = profile
+                    .model_ids
+                    .iter()
+                    .filter_map(|model_id| app.all_models.get(model_id).map(|m| m.display_name()))
+                    .collect();
+                let name_style = if idx == app.profile_picker_index {
+                    Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(profile.name.clone(), name_style),
+                    Span::styled(
+                        format!("  {}", model_names.join(", ")),
+                        Style::default().fg(theme.muted),
+                    ),
+                ]))
+            })
+            .collect();
+
+        let mut state = ListState::default();
+        state.select(Some(app.profile_picker_index));
+        f.render_stateful_widget(List::new(items).block(block), layout[0], &mut state);
+    }
+
+    let instructions = vec![Line::from(vec![
+        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to apply, "),
+        Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to delete, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to close"),
+    ])];
+
+    let instructions_paragraph = Paragraph::new(instructions)
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    f.render_widget(instructions_paragraph, layout[1]);
+}
+
+fn render_prompt_library_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
+    let popup_area = centered_rect(60, 50, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // For the prompt list
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("System Prompts")
+        .border_style(Style::default().fg(theme.warning));
+
+    if app.library_prompts.is_empty() {
+        let paragraph = Paragraph::new("No saved system prompts.\nPress n to add one.")
+            .block(block)
+            .alignment(Alignment::Center);
+        f.render_widget(paragraph, layout[0]);
+    } else {
+        let items: Vec<ListItem> = app
+            .library_prompts
+            .iter()
+            .enumerate()
+            .map(|(idx, prompt)| {
+                let name_style = if idx == app.prompt_library_index {
+                    Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                // mark the prompt the current chat is using
+                let marker = if app.current_chat.system_prompt.as_ref() == Some(&prompt.content) {
+                    "● "
+                } else {
+                    "  "
+                };
+                let preview = prompt.content.lines().next().unwrap_or_default();
+                ListItem::new(Line::from(vec![
+                    Span::styled(marker, Style::default().fg(theme.accent)),
+                    Span::styled(prompt.name.clone(), name_style),
+                    Span::styled(format!("  {}", preview), Style::default().fg(theme.muted)),
+                ]))
+            })
+            .collect();
+
+        let mut state = ListState::default();
+        state.select(Some(app.prompt_library_index));
+        f.render_stateful_widget(List::new(items).block(block), layout[0], &mut state);
+    }
+
+    let instructions = vec![Line::from(vec![
+        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to apply, "),
+        Span::styled("n", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("e", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("/"),
+        Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to add/edit/delete, "),
+        Span::styled("c", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" for the default, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to close"),
+    ])];
+
+    let instructions_paragraph = Paragraph::new(instructions)
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    f.render_widget(instructions_paragraph, layout[1]);
+}
+
+fn render_bookmark_list_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
+    let popup_area = centered_rect(70, 60, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // For the bookmark list
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Bookmarks")
+        .border_style(Style::default().fg(theme.warning));
+
+    if app.bookmarks.is_empty() {
+        let message = "No bookmarks yet.\nSelect a message with ] or [ and press m to add one.";
+        let paragraph = Paragraph::new(message)
+            .block(block)
+            .alignment(Alignment::Center);
+        f.render_widget(paragraph, layout[0]);
+    } else {
+        let items: Vec<ListItem> = app
+            .bookmarks
+            .iter()
+            .enumerate()
+            .map(|(idx, bookmark)| {
+                let title_style = if idx == app.bookmark_list_index {
+                    Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let author = match bookmark.model_id.and_then(|id| app.all_models.get(&id)) {
+                    Some(model) => model.display_name().to_string(),
+                    None if bookmark.model_id.is_some() => "removed model".to_string(),
+                    None => "you".to_string(),
+                };
+                let snippet = bookmark
+                    .content
+                    .as_deref()
+                    .or(bookmark.error.as_deref())
+                    .and_then(|text| text.lines().find(|line| !line.trim().is_empty()))
+                    .unwrap_or_default()
+                    .trim();
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        bookmark.chat_title.clone().unwrap_or_else(|| "New Chat".to_string()),
+                        title_style,
+                    ),
+                    Span::styled(format!("  {}: ", author), Style::default().fg(theme.accent)),
+                    Span::styled(snippet.to_string(), Style::default().fg(theme.muted)),
+                ]))
+            })
+            .collect();
+
+        let mut state = ListState::default();
+        state.select(Some(app.bookmark_list_index));
+        f.render_stateful_widget(List::new(items).block(block), layout[0], &mut state);
+    }
+
+    let instructions = vec![Line::from(vec![
+        Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to go to the message, "),
+        Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to remove, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to close"),
+    ])];
+
+    let instructions_paragraph = Paragraph::new(instructions)
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    f.render_widget(instructions_paragraph, layout[1]);
+}
+
+fn render_global_search_dialog(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.settings.theme;
+    let Some(search) = &mut app.global_search else {
+        return;
+    };
+    let popup_area = centered_rect(70, 60, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // For the query
+            Constraint::Min(3),    // For the results
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    let input_block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("Search all {} databases", search.databases.len()))
+        .border_style(Style::default().fg(theme.warning));
+    let input_area = input_block.inner(layout[0]);
+    f.render_widget(input_block, layout[0]);
+    let editor_theme = EditorTheme {
+        status_line: None,
+        base: Style::default().bg(Color::Reset),
+        ..Default::default()
+    };
+    f.render_widget(EditorView::new(&mut search.textarea).theme(editor_theme), input_area);
+
+    let results_block = Block::default().borders(Borders::ALL);
+    if search.results.is_empty() {
+        let message = if search.searched {
+            "No chats found"
+        } else {
+            "Chat titles and messages in every database are searched"
+        };
+        let paragraph = Paragraph::new(message)
+            .style(Style::default().fg(theme.muted))
+            .block(results_block)
+            .alignment(Alignment::Center);
+        f.render_widget(paragraph, layout[1]);
+    } else {
+        let items: Vec<ListItem> = search
+            .results
+            .iter()
+            .enumerate()
+            .map(|(idx, (path, chat))| {
+                let title_style = if idx == search.index {
+                    Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)
+                } else if chat.archived {
+                    Style::default().fg(theme.muted)
+                } else {
+                    Style::default()
+                };
+                let database_name = path.file_stem().unwrap_or_default().to_string_lossy();
+                let title = chat.title.clone().unwrap_or_else(|| "New Chat".to_string());
+                ListItem::new(Line::from(vec![
+                    Span::styled(title, title_style),
+                    Span::styled(format!("  {}", database_name), Style::default().fg(theme.accent)),
+                ]))
+            })
+            .collect();
+
+        let mut state = ListState::default();
+        state.select(Some(search.index));
+        let count = search.results.len();
+        let results_block =
+            results_block.title(format!("{} result{}", count, if count == 1 { "" } else { "s" }));
+        f.render_stateful_widget(List::new(items).block(results_block), layout[1], &mut state);
+    }
+
+    let instructions = if search.textarea.mode == EditorMode::Insert {
+        vec![Line::from(vec![
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" to search, "),
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" to go to the results"),
+        ])]
+    } else {
+        vec![Line::from(vec![
+            Span::styled("Enter", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" to open the chat, "),
+            Span::styled("/", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" to search again, "),
+            Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" to close"),
+        ])]
+    };
+    let instructions_paragraph = Paragraph::new(instructions)
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+    f.render_widget(instructions_paragraph, layout[2]);
+}
+
+fn render_library_prompt_editor(f: &mut Frame, app: &mut App, area: Rect) {
+    let popup_area = centered_rect(70, 60, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // For the prompt
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("System Prompt: {}", app.pending_library_prompt_name));
+    let inner_area = block.inner(layout[0]);
+    f.render_widget(block, layout[0]);
+
+    let theme = EditorTheme {
+        status_line: None,
+        base: Style::default().bg(Color::Reset),
+        ..Default::default()
+    };
+
+    let editor = EditorView::new(&mut app.library_prompt_textarea)
+        .theme(theme)
+        .wrap(true);
+    f.render_widget(editor, inner_area);
+
+    let instructions = vec![Line::from(vec![
+        Span::styled("Ctrl+s", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to save, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" in normal mode to cancel"),
+    ])];
+
+    let instructions_paragraph = Paragraph::new(instructions)
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    f.render_widget(instructions_paragraph, layout[1]);
+}
+
 fn render_unavailable_models_error_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
     let popup_area = centered_rect(70, 60, area);
     f.render_widget(Clear, popup_area);
 
@@ -1005,18 +2117,20 @@ fn render_unavailable_models_error_dialog(f: &mut Frame, app: &App, area: Rect)
         Line::from(Span::styled(
             "⚠️  Cannot Continue Chat",
             Style::default()
-                .fg(Color::Red)
+                .fg(theme.error)
                 .add_modifier(Modifier::BOLD),
         )),
         Line::from(""),
-        Line::from("The following models are not available because their provider API keys are not set:"),
+        Line::from(
+            "These models are unusable, their provider has no API key, is down or dropped them:",
+        ),
     ];
 
     let message_paragraph = Paragraph::new(error_message)
         .block(
             Block::default()
                 .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
-                .border_style(Style::default().fg(Color::Red)),
+                .border_style(Style::default().fg(theme.error)),
         )
         .alignment(Alignment::Center);
 
@@ -1053,32 +2167,361 @@ fn render_unavailable_models_error_dialog(f: &mut Frame, app: &App, area: Rect)
     .block(
         Block::default()
             .borders(Borders::LEFT | Borders::RIGHT)
-            .border_style(Style::default().fg(Color::Red)),
+            .border_style(Style::default().fg(theme.error)),
     )
     .column_spacing(2);
 
     f.render_widget(table, layout[1]);
 
     // Instructions
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let instructions = if app.has_available_chat_model() {
+        vec![
+            Span::styled("s", bold),
+            Span::raw(" to send to the available models, "),
+            Span::styled("any other key", bold),
+            Span::raw(" to cancel"),
+        ]
+    } else {
+        vec![Span::styled("Press any key", bold), Span::raw(" to go back")]
+    };
+    let instructions = vec![Line::from(instructions)];
+
+    let instructions_paragraph = Paragraph::new(instructions)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.error)),
+        )
+        .alignment(Alignment::Center);
+
+    f.render_widget(instructions_paragraph, layout[2]);
+}
+
+fn render_request_log_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
+    let popup_area = centered_rect(80, 70, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // For the request list
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title("Request Log")
+        .border_style(Style::default().fg(theme.warning));
+
+    if app.request_log.is_empty() {
+        let message = if app.settings.request_log {
+            "No requests logged yet."
+        } else {
+            "No requests logged.\nTurn on the request_log setting to record them."
+        };
+        let paragraph = Paragraph::new(message)
+            .block(block)
+            .alignment(Alignment::Center);
+        f.render_widget(paragraph, layout[0]);
+    } else {
+        let items: Vec<ListItem> = app
+            .request_log
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                let time_style = if idx == app.request_log_index {
+                    Style::default().fg(theme.warning).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let sent = chrono::DateTime::from_timestamp_millis(entry.dt)
+                    .map(|dt| {
+                        dt.with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()
+                    })
+                    .unwrap_or_else(|| "?".to_string());
+                let usage = match (entry.prompt_tokens, entry.completion_tokens) {
+                    (Some(prompt_tokens), Some(completion_tokens)) => {
+                        format!(" · {} in / {} out", prompt_tokens, completion_tokens)
+                    }
+                    _ => String::new(),
+                };
+                let mut spans = vec![
+                    Span::styled(sent, time_style),
+                    Span::styled(format!("  {}", entry.model), Style::default().fg(theme.accent)),
+                    Span::styled(
+                        format!(
+                            "  {} messages · {:.1}s{}",
+                            entry.message_count,
+                            entry.latency_ms as f64 / 1000.0,
+                            usage
+                        ),
+                        Style::default().fg(theme.muted),
+                    ),
+                ];
+                if let Some(error) = &entry.error {
+                    let first_line = error.lines().next().unwrap_or_default();
+                    spans.push(Span::styled(
+                        format!("  {}", first_line),
+                        Style::default().fg(theme.error),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+
+        let mut state = ListState::default();
+        state.select(Some(app.request_log_index));
+        f.render_stateful_widget(List::new(items).block(block), layout[0], &mut state);
+    }
+
     let instructions = vec![Line::from(vec![
-        Span::styled(
-            "Press any key",
-            Style::default().add_modifier(Modifier::BOLD),
+        Span::styled("j/k", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to scroll, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to close"),
+    ])];
+
+    let instructions_paragraph = Paragraph::new(instructions)
+        .block(Block::default().borders(Borders::ALL))
+        .alignment(Alignment::Center);
+
+    f.render_widget(instructions_paragraph, layout[1]);
+}
+
+/// Local send time for user messages, response time and latency for assistant messages.
+/// Message dts are in milliseconds
+fn format_message_timestamp(message: &ChatMessage) -> String {
+    let format_millis = |millis: i64| {
+        chrono::DateTime::from_timestamp_millis(millis)
+            .map(|dt| {
+                dt.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "?".to_string())
+    };
+
+    let timestamp = match message.response_dt {
+        Some(response_dt) if message.chat_role != ChatRole::User => format!(
+            "{} ({:.1}s)",
+            format_millis(response_dt),
+            (response_dt - message.dt) as f64 / 1000.0
         ),
-        Span::raw(" to go back"),
+        _ => format_millis(message.dt),
+    };
+
+    match (message.prompt_tokens, message.completion_tokens) {
+        (Some(prompt_tokens), Some(completion_tokens)) => format!(
+            "{} · {} in / {} out tokens",
+            timestamp, prompt_tokens, completion_tokens
+        ),
+        _ => timestamp,
+    }
+}
+
+/// Pretty print a response that is a single JSON object, like JSON mode ones. They're stored as
+/// the model sent them
+fn pretty_json_object(content: &str) -> Option<String> {
+    if !content.trim_start().starts_with('{') {
+        return None;
+    }
+    let value = serde_json::from_str::<serde_json::Value>(content).ok()?;
+    serde_json::to_string_pretty(&value).ok()
+}
+
+/// Pretty print the JSON body of a provider error if there is one, otherwise return it as is
+fn format_error_detail(error: &str) -> String {
+    if let Some(json_start) = error.find('{')
+        && let Ok(value) = serde_json::from_str::<serde_json::Value>(&error[json_start..])
+        && let Ok(pretty) = serde_json::to_string_pretty(&value)
+    {
+        return format!("{}\n{}", error[..json_start].trim_end(), pretty);
+    }
+    error.to_string()
+}
+
+fn render_error_detail_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
+    let Some((model_name, provider_name, error)) = &app.error_detail else {
+        return;
+    };
+
+    let popup_area = centered_rect(80, 70, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(4), // For the model and provider
+            Constraint::Min(3),    // For the error itself
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    let source = vec![
+        Line::from(vec![
+            Span::styled("Model: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(model_name.clone()),
+        ]),
+        Line::from(vec![
+            Span::styled("Provider: ", Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(provider_name.clone()),
+        ]),
+    ];
+    let source_paragraph = Paragraph::new(source).block(
+        Block::default()
+            .title("Error Detail")
+            .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
+            .border_style(Style::default().fg(theme.error)),
+    );
+    f.render_widget(source_paragraph, layout[0]);
+
+    let error_paragraph = Paragraph::new(format_error_detail(error))
+        .style(Style::default().fg(theme.error))
+        .wrap(Wrap { trim: false })
+        .scroll((app.error_detail_scroll, 0))
+        .block(
+            Block::default()
+                .borders(Borders::LEFT | Borders::RIGHT)
+                .border_style(Style::default().fg(theme.error)),
+        );
+    f.render_widget(error_paragraph, layout[1]);
+
+    // Instructions
+    let instructions = vec![Line::from(vec![
+        Span::styled("j/k", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to scroll, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to close"),
     ])];
 
     let instructions_paragraph = Paragraph::new(instructions)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Red)),
+                .border_style(Style::default().fg(theme.error)),
         )
         .alignment(Alignment::Center);
 
     f.render_widget(instructions_paragraph, layout[2]);
 }
 
+fn render_request_preview_dialog(f: &mut Frame, app: &App, area: Rect) {
+    let theme = app.settings.theme;
+    let Some((model_name, request)) = &app.request_preview else {
+        return;
+    };
+
+    let popup_area = centered_rect(80, 80, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // For the request
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    let request_paragraph = Paragraph::new(request.as_str())
+        .wrap(Wrap { trim: false })
+        .scroll((app.request_preview_scroll, 0))
+        .block(
+            Block::default()
+                .title(format!("Request Preview: {}", model_name))
+                .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
+                .border_style(Style::default().fg(theme.accent)),
+        );
+    f.render_widget(request_paragraph, layout[0]);
+
+    let instructions = vec![Line::from(vec![
+        Span::styled("j/k", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to scroll, "),
+        Span::styled("y", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to copy, "),
+        Span::styled("m", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to copy the messages, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to close"),
+    ])];
+
+    let instructions_paragraph = Paragraph::new(instructions)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.accent)),
+        )
+        .alignment(Alignment::Center);
+
+    f.render_widget(instructions_paragraph, layout[1]);
+}
+
+fn render_log_viewer_dialog(f: &mut Frame, app: &mut App, area: Rect) {
+    let theme = app.settings.theme;
+    let Some(log_buffer) = app.log_buffer.clone() else {
+        return;
+    };
+    let popup_area = centered_rect(90, 80, area);
+    f.render_widget(Clear, popup_area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // For the log lines
+            Constraint::Length(3), // For instructions
+        ])
+        .split(popup_area);
+
+    // lines aren't wrapped so the newest one always lands on the bottom row
+    let visible_rows = layout[0].height.saturating_sub(1) as usize;
+    let line_count = log_buffer.line_count();
+    app.log_viewer_rows = visible_rows;
+    // a smaller terminal fits fewer lines, keep the oldest page full rather than scroll past it
+    app.log_viewer_scroll = app.log_viewer_scroll.min(line_count.saturating_sub(visible_rows));
+    let lines: Vec<Line> = log_buffer
+        .tail(app.log_viewer_scroll, visible_rows)
+        .into_iter()
+        .map(Line::from)
+        .collect();
+    let title = if app.log_viewer_scroll > 0 {
+        format!("Logs ({} lines, {} newer)", line_count, app.log_viewer_scroll)
+    } else {
+        format!("Logs ({} lines)", line_count)
+    };
+
+    let log_paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .title(title)
+            .borders(Borders::TOP | Borders::LEFT | Borders::RIGHT)
+            .border_style(Style::default().fg(theme.accent)),
+    );
+    f.render_widget(log_paragraph, layout[0]);
+
+    let instructions = vec![Line::from(vec![
+        Span::styled("j/k", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to scroll, "),
+        Span::styled("g/G", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" for oldest/newest, "),
+        Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(" to close"),
+    ])];
+
+    let instructions_paragraph = Paragraph::new(instructions)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.accent)),
+        )
+        .alignment(Alignment::Center);
+
+    f.render_widget(instructions_paragraph, layout[1]);
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -1098,3 +2541,102 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         ])
         .split(popup_layout[1])[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_text_pans_by_display_width() {
+        let rendered = |offset: usize, max_width: usize| {
+            let text = Text::from(Line::from(vec![
+                Span::raw("ab"),
+                Span::styled("日本語", Style::default().fg(Color::Cyan)),
+                Span::raw("🚀x"),
+            ]));
+            clip_text(text, offset, max_width).lines[0]
+                .spans
+                .iter()
+                .map(|span| span.content.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // each CJK character and the emoji take two columns
+        assert_eq!(rendered(0, 6), ["ab", "日本"]);
+        assert_eq!(rendered(2, 4), ["日本"]);
+        // the left edge cuts 日 in half, its visible half is a space
+        assert_eq!(rendered(3, 4), [" 本"]);
+        // a wide character that doesn't fit on the right is left off
+        assert_eq!(rendered(4, 5), ["本語"]);
+        // and nothing after it is shown either
+        assert_eq!(rendered(6, 3), ["語"]);
+        assert_eq!(rendered(8, 10), ["🚀x"]);
+        assert_eq!(rendered(20, 10), [""]);
+    }
+
+    #[test]
+    fn test_highlight_text_with_emoji() {
+        let line = highlight_text(
+            "🎉 Party 🎉 party",
+            "party",
+            Style::default(),
+            &Theme::default(),
+        );
+        let rendered: Vec<&str> = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, vec!["🎉 ", "Party", " 🎉 ", "party"]);
+        assert_eq!(line.spans[1].style.bg, Some(Color::Yellow));
+    }
+
+    #[test]
+    fn test_highlight_text_multibyte_query() {
+        let line = highlight_text("Ça va, ÇA VA", "ça", Style::default(), &Theme::default());
+        let rendered: Vec<&str> = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, vec!["Ça", " va, ", "ÇA", " VA"]);
+
+        // 'İ' lowercases to two chars, so byte offsets in the lowercased text don't line up with the original
+        let line = highlight_text("İİ abc", "abc", Style::default(), &Theme::default());
+        let rendered: Vec<&str> = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(rendered, vec!["İİ ", "abc"]);
+    }
+
+    #[test]
+    fn test_highlight_text_in_parsed_with_emoji() {
+        let text = Text::from(Line::from(vec![
+            Span::raw("日本語 🚀"),
+            Span::styled("rocket 🚀", Style::default().fg(Color::Cyan)),
+        ]));
+        let highlighted = highlight_text_in_parsed(&text, "🚀", &Theme::default());
+        let rendered: Vec<&str> = highlighted.lines[0]
+            .spans
+            .iter()
+            .map(|s| s.content.as_ref())
+            .collect();
+        assert_eq!(rendered, vec!["日本語 ", "🚀", "rocket ", "🚀"]);
+        assert_eq!(highlighted.lines[0].spans[3].style.fg, Some(Color::Black));
+    }
+
+    #[test]
+    fn test_pretty_json_object() {
+        assert_eq!(
+            pretty_json_object(r#"{"a":1,"b":[true]}"#).as_deref(),
+            Some("{\n  \"a\": 1,\n  \"b\": [\n    true\n  ]\n}")
+        );
+        // only whole objects, a reply that merely mentions one is markdown
+        assert_eq!(pretty_json_object(r#"Here it is: {"a":1}"#), None);
+        assert_eq!(pretty_json_object(r#"{"a":1} and more"#), None);
+        assert_eq!(pretty_json_object("[1, 2]"), None);
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        assert_eq!(format_relative_time(-5), "just now"); // clock skew
+        assert_eq!(format_relative_time(59), "just now");
+        assert_eq!(format_relative_time(60), "1m ago");
+        assert_eq!(format_relative_time(60 * 60 - 1), "59m ago");
+        assert_eq!(format_relative_time(60 * 60), "1h ago");
+        assert_eq!(format_relative_time(2 * 24 * 60 * 60), "2d ago");
+        assert_eq!(format_relative_time(45 * 24 * 60 * 60), "1mo ago");
+        assert_eq!(format_relative_time(364 * 24 * 60 * 60), "12mo ago");
+        assert_eq!(format_relative_time(800 * 24 * 60 * 60), "2y ago");
+    }
+}